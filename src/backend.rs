@@ -0,0 +1,615 @@
+use std::collections::HashSet;
+
+use serde::Serialize;
+
+use crate::config::Config;
+use crate::create_a_tree_prep;
+use crate::create_commit_on_branch_prep::{generate_file_changes, split_commit_message};
+use crate::error::{Error, Result, parse_oid};
+use crate::git_log::unpushed_commits;
+use crate::git_status::{PathStatus, commit_status};
+use crate::identity::{CommitSignatures, commit_signatures, warn_if_unverified, with_co_author_trailers};
+use crate::lfs::upload_lfs_objects;
+use crate::github::GitHubClient;
+use crate::github::graphql_api::{self, create_commit_on_branch};
+use crate::github::rest_api::shared::ErrorResponseBody;
+use crate::github::rest_api::{compare_two_commits, create_a_commit, create_a_reference, get_a_reference, update_a_reference};
+use crate::log::{print_intent_plain, print_warning};
+
+/// Which of GitHub's APIs commits are created with
+#[derive(Clone, Copy, Debug, Default, Eq, PartialEq, clap::ValueEnum)]
+pub enum BackendKind {
+    /// Git database REST API: blobs, trees, commits, and refs are created
+    /// separately
+    #[default]
+    Rest,
+    /// GraphQL `createCommitOnBranch` mutation: the commit is created and the
+    /// branch is moved in one call
+    #[value(name = "graphql")]
+    GraphQl,
+}
+
+/// Whether the branch was created or an existing branch was moved
+#[derive(Clone, Copy, Debug, Eq, PartialEq, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum RefAction {
+    Created,
+    Updated,
+}
+
+impl RefAction {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            RefAction::Created => "created",
+            RefAction::Updated => "updated",
+        }
+    }
+
+    fn for_remote_tip(remote_tip: Option<&str>) -> RefAction {
+        match remote_tip {
+            Some(_) => RefAction::Updated,
+            None => RefAction::Created,
+        }
+    }
+}
+
+/// The commit that the branch was moved to
+#[derive(Debug)]
+pub struct CreatedCommit {
+    pub sha: String,
+    pub html_url: String,
+    pub tree_sha: String,
+    /// Whether GitHub considers the commit's signature verified
+    pub verified: bool,
+    pub ref_action: RefAction,
+}
+
+impl CreatedCommit {
+    fn from_rest(commit: create_a_commit::ResponseBody, ref_action: RefAction) -> CreatedCommit {
+        CreatedCommit {
+            sha: commit.sha,
+            html_url: commit.html_url,
+            tree_sha: commit.tree.sha,
+            verified: commit.verification.verified,
+            ref_action: ref_action,
+        }
+    }
+
+    fn from_graphql(commit: create_commit_on_branch::Commit, ref_action: RefAction) -> CreatedCommit {
+        CreatedCommit {
+            sha: commit.oid,
+            html_url: commit.url,
+            tree_sha: commit.tree.oid,
+            verified: commit.signature.is_some_and(|signature| signature.is_valid),
+            ref_action: ref_action,
+        }
+    }
+}
+
+pub trait CommitBackend {
+    /// Creates a single commit from the staged changes on top of the remote
+    /// branch and moves the branch to it
+    fn commit_staged_changes(&self, config: &Config, status: &[PathStatus]) -> Result<CreatedCommit>;
+
+    /// Recreates each local commit that isn't on the remote branch yet as a
+    /// remote commit, oldest first, with each one parented on the previously
+    /// created remote commit. Only the last commit created is returned since
+    /// the rest are reachable from it.
+    ///
+    /// The branch is moved once, after the whole chain is built, so it never
+    /// points to a partially replayed chain.
+    fn replay_unpushed_commits(&self, config: &Config) -> Result<CreatedCommit>;
+}
+
+pub fn new_backend(kind: BackendKind, github_client: &GitHubClient) -> Box<dyn CommitBackend + '_> {
+    match kind {
+        BackendKind::Rest => Box::new(RestBackend { github_client: github_client }),
+        BackendKind::GraphQl => Box::new(GraphQlBackend { github_client: github_client }),
+    }
+}
+
+fn generate_create_a_commit_body(message: &str, parent_sha: &str, tree_sha: &str, signatures: &CommitSignatures) -> create_a_commit::RequestBody {
+    create_a_commit::RequestBody {
+        message: message.to_string(),
+        parents: vec![parent_sha.to_string()],
+        tree: tree_sha.to_string(),
+        author: signatures.author.clone(),
+        committer: signatures.committer.clone(),
+    }
+}
+
+fn fully_qualify_branch_name(unqualified_name: &str) -> String {
+    format!("refs/heads/{}", unqualified_name)
+}
+
+fn partially_qualify_branch_name(unqualified_name: &str) -> String {
+    format!("heads/{}", unqualified_name)
+}
+
+/// Returns the SHA the remote branch points to, or `None` if the branch
+/// doesn't exist on the remote
+fn remote_branch_tip(github_client: &GitHubClient, config: &Config) -> Result<Option<String>> {
+    let reference_name = partially_qualify_branch_name(&config.git_branch_name);
+
+    let get_a_reference_response = github_client.get_a_reference(&reference_name)?;
+
+    let tip = match get_a_reference_response {
+        get_a_reference::ResponseBody::Ok(reference) => Some(reference.object.sha),
+        get_a_reference::ResponseBody::NotFound(_) => None,
+    };
+
+    Ok(tip)
+}
+
+fn update_a_reference(config: &Config, github_client: &GitHubClient, commit_sha: &str) -> Result<update_a_reference::ResponseBody> {
+    let payload = update_a_reference::RequestBody {
+        sha: commit_sha.to_string(),
+        force: config.git_should_force_push,
+    };
+
+    let reference_name = partially_qualify_branch_name(&config.git_branch_name);
+
+    github_client.update_a_reference(&reference_name, &payload)
+}
+
+fn create_a_reference(config: &Config, github_client: &GitHubClient, commit_sha: &str) -> Result<create_a_reference::ResponseBody> {
+    let payload = create_a_reference::RequestBody {
+        reference: fully_qualify_branch_name(&config.git_branch_name),
+        sha: commit_sha.to_string(),
+    };
+
+    github_client.create_a_reference(&payload)
+}
+
+/// GitHub's message when an unforced update isn't a fast-forward, which
+/// means the branch moved since its tip was read
+const NOT_A_FAST_FORWARD_MESSAGE: &str = "Update is not a fast forward";
+
+/// GitHub's message when creating a branch that already exists, which means
+/// the branch was created since it was found missing
+const REFERENCE_ALREADY_EXISTS_MESSAGE: &str = "Reference already exists";
+
+/// Tells a 422 that means the remote branch moved, which is worth retrying,
+/// from the ones retrying can't fix, such as a missing object or a protected
+/// branch, which become `Error::Api`
+///
+/// - A forced update doesn't depend on the branch's tip, so none of its 422s
+///   mean that the branch moved
+fn branch_moved(config: &Config, operation: &str, failure: ErrorResponseBody, moved_message: &str) -> Result<bool> {
+    if !config.git_should_force_push && failure.message == moved_message {
+        return Ok(false)
+    }
+
+    Err(Error::Api {
+        operation: operation.to_string(),
+        status: 422,
+        body: failure.message,
+    })
+}
+
+/// Points the branch at `commit_sha`, creating the branch if `remote_tip` says
+/// it doesn't exist yet
+///
+/// Returns `false` if the remote branch moved since `remote_tip` was read. An
+/// unforced update is rejected by GitHub unless it's a fast-forward, and since
+/// the commit's parent is `remote_tip`, that makes the update a
+/// compare-and-swap.
+fn move_branch(config: &Config, github_client: &GitHubClient, remote_tip: Option<&str>, commit_sha: &str) -> Result<bool> {
+    match remote_tip {
+        Some(_) => match update_a_reference(config, github_client, commit_sha)? {
+            update_a_reference::ResponseBody::Ok(_) => Ok(true),
+            update_a_reference::ResponseBody::UnprocessableEntity(failure) => branch_moved(config, "update a reference", failure, NOT_A_FAST_FORWARD_MESSAGE),
+        },
+        None => match create_a_reference(config, github_client, commit_sha)? {
+            create_a_reference::ResponseBody::Ok(_) => Ok(true),
+            create_a_reference::ResponseBody::UnprocessableEntity(failure) => branch_moved(config, "create a reference", failure, REFERENCE_ALREADY_EXISTS_MESSAGE),
+        },
+    }
+}
+
+/// The number of times to rebuild the commit when the remote branch moves
+/// while committing
+const MAX_COMMIT_ATTEMPTS: u32 = 3;
+
+/// Every path the changes touch, including the original paths of renames
+pub(crate) fn changed_paths(status: &[PathStatus]) -> HashSet<&str> {
+    status.iter()
+        .flat_map(|path_status| [Some(path_status.path.as_str()), path_status.original_path.as_deref()])
+        .flatten()
+        .collect()
+}
+
+/// Decides which commit the staged changes should be parented on when the
+/// remote branch tip is no longer the local HEAD
+///
+/// If the remote branch only moved ahead and none of the commits it moved
+/// ahead by touched the staged paths, the changes are rebased onto the remote
+/// tip. If it moved ahead by touching the staged paths or if it diverged, the
+/// remote moved in a way that can't be reconciled automatically.
+///
+/// - When following the remote branch, which usually started from an older
+///   base, the changes go on top of its tip even if it's behind or diverged,
+///   as long as it didn't change the staged paths since the local base
+fn parent_for_remote_tip(config: &Config, github_client: &GitHubClient, status: &[PathStatus], remote_tip: &str) -> Result<String> {
+    let local_base = &config.git_base_object_id;
+    let comparison = match github_client.compare_two_commits(local_base, remote_tip) {
+        // - GitHub can only compare commits it has, and a local base that the
+        //   remote branch never started from may not have been pushed
+        Err(Error::Api { status: 404, .. }) if config.git_should_follow_remote_branch => compare_locally(config, remote_tip)?,
+        result => result?,
+    };
+
+    let remote_moved = |reason: &str| {
+        format!("Remote branch {:?} is at {} but the local base is {}: {}", config.git_branch_name, remote_tip, local_base, reason)
+    };
+
+    match comparison.status {
+        compare_two_commits::Status::Identical => Ok(local_base.clone()),
+        compare_two_commits::Status::Behind if !config.git_should_follow_remote_branch => Ok(local_base.clone()),
+        compare_two_commits::Status::Diverged if !config.git_should_follow_remote_branch => Err(Error::conflict(remote_moved("the branches have diverged"))),
+        _ => {
+            if comparison.files.len() >= compare_two_commits::MAX_FILES {
+                return Err(Error::conflict(remote_moved("too many paths changed remotely to check for conflicts")))
+            }
+
+            let staged_paths = changed_paths(status);
+
+            let mut conflicting_paths: Vec<&str> = comparison.files.iter()
+                .flat_map(|file| [Some(file.filename.as_str()), file.previous_filename.as_deref()])
+                .flatten()
+                .filter(|path| staged_paths.contains(path))
+                .collect();
+
+            if conflicting_paths.is_empty() {
+                print_intent_plain(&format!("Rebasing staged changes onto remote branch tip {}", remote_tip));
+                Ok(remote_tip.to_string())
+            } else {
+                conflicting_paths.sort();
+                conflicting_paths.dedup();
+
+                Err(Error::conflict(remote_moved(&format!("conflicting paths: {:?}", conflicting_paths))))
+            }
+        },
+    }
+}
+
+/// Compares the local base to the remote tip the way Compare two commits
+/// would, for a remote tip that has been fetched
+fn compare_locally(config: &Config, remote_tip: &str) -> Result<compare_two_commits::ResponseBody> {
+    let repo = &config.git_repo;
+    let base_id = parse_oid(&config.git_base_object_id)?;
+    let tip_id = parse_oid(remote_tip)?;
+
+    let tip = repo.find_commit(tip_id)
+        .map_err(|_| Error::conflict(format!("Remote branch {:?} can't be checked for conflicting changes: GitHub doesn't have the local base {} and its tip {} hasn't been fetched", config.git_branch_name, config.git_base_object_id, remote_tip)))?;
+
+    let (ahead_by, behind_by) = repo.graph_ahead_behind(tip_id, base_id)
+        .map_err(|e| Error::git(format!("count the commits between {} and {}", config.git_base_object_id, remote_tip), e))?;
+
+    let status = match (ahead_by, behind_by) {
+        (0, 0) => compare_two_commits::Status::Identical,
+        (_, 0) => compare_two_commits::Status::Ahead,
+        (0, _) => compare_two_commits::Status::Behind,
+        _ => compare_two_commits::Status::Diverged,
+    };
+
+    // - Like GitHub, the files are the ones changed since the merge base
+    let merge_base = repo.merge_base(base_id, tip_id)
+        .and_then(|merge_base_id| repo.find_commit(merge_base_id))
+        .map_err(|e| Error::git(format!("find the merge base of {} and {}", config.git_base_object_id, remote_tip), e))?;
+    let merge_base_tree = merge_base.tree()
+        .map_err(|e| Error::git(format!("find the tree of commit {}", merge_base.id()), e))?;
+    let tip_tree = tip.tree()
+        .map_err(|e| Error::git(format!("find the tree of commit {}", remote_tip), e))?;
+
+    let mut diff = repo.diff_tree_to_tree(Some(&merge_base_tree), Some(&tip_tree), None)
+        .map_err(|e| Error::git(format!("diff {} against {}", merge_base.id(), remote_tip), e))?;
+    diff.find_similar(None)
+        .map_err(|e| Error::git(format!("find renames between {} and {}", merge_base.id(), remote_tip), e))?;
+
+    let path_of = |file: git2::DiffFile| file.path().map(|path| path.to_string_lossy().into_owned());
+
+    let files = diff.deltas()
+        .take(compare_two_commits::MAX_FILES)
+        .filter_map(|delta| {
+            let (status, filename, previous_filename) = match delta.status() {
+                git2::Delta::Added => ("added", path_of(delta.new_file()), None),
+                git2::Delta::Deleted => ("removed", path_of(delta.old_file()), None),
+                git2::Delta::Renamed => ("renamed", path_of(delta.new_file()), path_of(delta.old_file())),
+                _ => ("modified", path_of(delta.new_file()), None),
+            };
+
+            Some(compare_two_commits::File {
+                filename: filename?,
+                status: status.to_string(),
+                previous_filename: previous_filename,
+            })
+        })
+        .collect();
+
+    Ok(compare_two_commits::ResponseBody {
+        status: status,
+        ahead_by: ahead_by as u64,
+        behind_by: behind_by as u64,
+        files: files,
+    })
+}
+
+/// A local commit the remote is known to have, whose blobs can be referenced
+/// by SHA instead of uploaded, or `None` if there isn't one
+///
+/// - The local base is the commit's parent, or an ancestor of the remote tip
+///   it's rebased onto, unless following the remote branch, where the remote
+///   tip may have diverged from a local base that was never pushed
+/// - The remote tip only helps if it has been fetched
+fn known_remote_commit<'r>(config: &'r Config, github_client: &GitHubClient) -> Result<Option<git2::Commit<'r>>> {
+    let find_commit = |sha: &str| {
+        config.git_repo.find_commit(parse_oid(sha)?)
+            .map_err(|e| Error::git(format!("find commit {}", sha), e))
+    };
+
+    if !config.git_should_follow_remote_branch || config.git_should_force_push {
+        return Ok(Some(find_commit(&config.git_base_object_id)?))
+    }
+
+    match remote_branch_tip(github_client, config)? {
+        Some(tip) => Ok(find_commit(&tip).ok()),
+        None => Ok(Some(find_commit(&config.git_base_object_id)?)),
+    }
+}
+
+pub struct RestBackend<'a> {
+    github_client: &'a GitHubClient,
+}
+
+impl CommitBackend for RestBackend<'_> {
+    fn commit_staged_changes(&self, config: &Config, status: &[PathStatus]) -> Result<CreatedCommit> {
+        let github_client = self.github_client;
+
+        // - Upload the Git LFS objects first so the commit never points to
+        //   ones that don't exist
+
+        upload_lfs_objects(&config.git_repo, status, &config.git_branch_name, config.github_max_concurrent_uploads, github_client)?;
+
+        // - Generate the tree once, creating the blobs if necessary implicitly,
+        //   since only its base changes if the commit needs to be rebuilt
+
+        let known_remote_commit = known_remote_commit(config, github_client)?;
+        let tree_payload = create_a_tree_prep::generate_request_body(&config.git_base_object_id, known_remote_commit.as_ref(), &config.git_repo, status, config.github_max_concurrent_uploads, github_client)?;
+        let mut tree_chunks = create_a_tree_prep::split_request_body(tree_payload);
+
+        let message = with_co_author_trailers(&config.commit_message, &config.commit_identity.co_authors);
+        let signatures = commit_signatures(&config.commit_identity, &config.git_repo, None)?;
+        warn_if_unverified(&config.commit_identity);
+
+        for attempt in 1..=MAX_COMMIT_ATTEMPTS {
+            let remote_tip = remote_branch_tip(github_client, config)?;
+
+            let parent_sha = match &remote_tip {
+                Some(tip) if *tip != config.git_base_object_id && !config.git_should_force_push => {
+                    parent_for_remote_tip(config, github_client, status, tip)?
+                },
+                _ => config.git_base_object_id.clone(),
+            };
+
+            // - Create the tree, in several requests if it's large
+
+            let tree = create_a_tree_prep::create_a_tree_in_chunks(github_client, &parent_sha, &mut tree_chunks)?;
+
+            // - Create the commit
+
+            let commit_payload = generate_create_a_commit_body(&message, &parent_sha, &tree.sha, &signatures);
+            let commit = github_client.create_a_commit(&commit_payload)?;
+
+            // - If branch exists, update it, else create it
+
+            if move_branch(config, github_client, remote_tip.as_deref(), &commit.sha)? {
+                return Ok(CreatedCommit::from_rest(commit, RefAction::for_remote_tip(remote_tip.as_deref())))
+            }
+
+            print_intent_plain(&format!("Remote branch {:?} moved while committing (attempt {} of {})", config.git_branch_name, attempt, MAX_COMMIT_ATTEMPTS));
+        }
+
+        Err(Error::conflict(format!("Remote branch {:?} kept moving while committing; gave up after {} attempts", config.git_branch_name, MAX_COMMIT_ATTEMPTS)))
+    }
+
+    fn replay_unpushed_commits(&self, config: &Config) -> Result<CreatedCommit> {
+        let github_client = self.github_client;
+
+        let remote_tip = remote_branch_tip(github_client, config)?
+            .ok_or_else(|| Error::conflict(format!("Branch {:?} must exist on the remote to replay commits onto it", config.git_branch_name)))?;
+
+        let repo = &config.git_repo;
+        let commit_ids = unpushed_commits(repo, parse_oid(&config.git_head_object_id)?, parse_oid(&remote_tip)?)?;
+
+        let mut parent_sha = remote_tip.clone();
+        let mut last_commit = None;
+
+        warn_if_unverified(&config.commit_identity);
+
+        for commit_id in commit_ids {
+            let commit = repo.find_commit(commit_id)
+                .map_err(|e| Error::git(format!("find commit {}", commit_id), e))?;
+            let message = commit.message()
+                .ok_or_else(|| Error::unsupported(format!("Commit {} has a message that is not valid UTF-8", commit_id)))?;
+            let local_tree_sha = commit.tree_id().to_string();
+
+            let status = commit_status(repo, &commit)?;
+
+            upload_lfs_objects(repo, &status, &config.git_branch_name, config.github_max_concurrent_uploads, github_client)?;
+
+            // - A commit without changes has the same tree as its parent,
+            //   which the remote already has
+            let tree_sha = if status.is_empty() {
+                local_tree_sha.clone()
+            } else {
+                // - The remote parent is a different commit, but it has the
+                //   same tree as the local one
+                let local_parent = commit.parent(0).ok();
+                let tree_payload = create_a_tree_prep::generate_request_body(&parent_sha, local_parent.as_ref(), repo, &status, config.github_max_concurrent_uploads, github_client)?;
+                let mut tree_chunks = create_a_tree_prep::split_request_body(tree_payload);
+
+                create_a_tree_prep::create_a_tree_in_chunks(github_client, &parent_sha, &mut tree_chunks)?.sha
+            };
+
+            // - The branch only moves after every commit is replayed, so
+            //   stopping here leaves it untouched
+            if tree_sha != local_tree_sha {
+                Err(Error::conflict(format!("Local commit {} can't be replayed as is: the remote built tree {} instead of its tree {}", commit_id, tree_sha, local_tree_sha)))?
+            }
+
+            let message = with_co_author_trailers(message, &config.commit_identity.co_authors);
+            let signatures = commit_signatures(&config.commit_identity, repo, Some(&commit))?;
+
+            let commit_payload = generate_create_a_commit_body(&message, &parent_sha, &tree_sha, &signatures);
+            let remote_commit = github_client.create_a_commit(&commit_payload)?;
+
+            parent_sha = remote_commit.sha.clone();
+            last_commit = Some(remote_commit);
+        }
+
+        let last_commit = last_commit.ok_or(Error::NoChanges)?;
+
+        if !move_branch(config, github_client, Some(&remote_tip), &last_commit.sha)? {
+            Err(Error::conflict(format!("Remote branch {:?} moved away from {} while replaying commits; fetch and try again", config.git_branch_name, remote_tip)))?
+        }
+
+        Ok(CreatedCommit::from_rest(last_commit, RefAction::Updated))
+    }
+}
+
+pub struct GraphQlBackend<'a> {
+    github_client: &'a GitHubClient,
+}
+
+impl GraphQlBackend<'_> {
+    /// Returns the GraphQL errors' messages along with whether any of the
+    /// errors were due to the branch having moved past `expectedHeadOid`
+    fn describe_errors(errors: &[graphql_api::shared::Error]) -> (String, bool) {
+        let is_stale = errors.iter()
+            .any(|error| error.error_type.as_deref() == Some(graphql_api::shared::STALE_DATA_ERROR_TYPE));
+
+        let messages: Vec<&str> = errors.iter()
+            .map(|error| error.message.as_str())
+            .collect();
+
+        (messages.join("; "), is_stale)
+    }
+
+    /// Returns `None` if the branch moved past `expectedHeadOid`
+    fn create_commit(config: &Config, github_client: &GitHubClient, input: &create_commit_on_branch::Input) -> Result<Option<create_commit_on_branch::Commit>> {
+        let response = github_client.create_commit_on_branch(input)?;
+
+        if !response.errors.is_empty() {
+            let (message, is_stale) = Self::describe_errors(&response.errors);
+
+            if is_stale {
+                return Ok(None)
+            }
+
+            Err(Error::GraphQl {
+                operation: format!("create a commit on branch {:?}", config.git_branch_name),
+                message: message,
+            })?
+        }
+
+        let commit = response.data
+            .and_then(|data| data.create_commit_on_branch)
+            .map(|payload| payload.commit)
+            .ok_or_else(|| Error::GraphQl {
+                operation: format!("create a commit on branch {:?}", config.git_branch_name),
+                message: "createCommitOnBranch returned neither a commit nor errors".to_string(),
+            })?;
+
+        Ok(Some(commit))
+    }
+
+    /// Deletes the branch that was created for a commit that failed, returning
+    /// the commit's error
+    ///
+    /// - If the branch can't be deleted either, it's left pointing at the
+    ///   base, which is warned about
+    fn delete_created_branch(config: &Config, github_client: &GitHubClient, error: Error) -> Error {
+        let reference_name = partially_qualify_branch_name(&config.git_branch_name);
+
+        if let Err(delete_error) = github_client.delete_a_reference(&reference_name) {
+            print_warning(&format!("Unable to delete branch {:?}, which was created for the commit and is left pointing at {}: {}", config.git_branch_name, config.git_base_object_id, delete_error));
+        }
+
+        error
+    }
+}
+
+impl CommitBackend for GraphQlBackend<'_> {
+    fn commit_staged_changes(&self, config: &Config, status: &[PathStatus]) -> Result<CreatedCommit> {
+        let github_client = self.github_client;
+
+        if config.git_should_force_push {
+            Err(Error::unsupported("Force pushing is not supported by the GraphQL backend since createCommitOnBranch only fast-forwards; use the REST backend instead"))?
+        }
+
+        if config.commit_identity.is_custom() {
+            Err(Error::unsupported("Setting the author or committer is not supported by the GraphQL backend since createCommitOnBranch always commits as the authenticated app or user; use the REST backend or --co-author instead"))?
+        }
+
+        upload_lfs_objects(&config.git_repo, status, &config.git_branch_name, config.github_max_concurrent_uploads, github_client)?;
+
+        let mut input = create_commit_on_branch::Input {
+            branch: create_commit_on_branch::CommittableBranch {
+                repository_name_with_owner: format!("{}/{}", config.github_repo_owner, config.github_repo_name),
+                branch_name: config.git_branch_name.clone(),
+            },
+            expected_head_oid: config.git_base_object_id.clone(),
+            file_changes: generate_file_changes(&config.git_repo, status)?,
+            message: split_commit_message(&with_co_author_trailers(&config.commit_message, &config.commit_identity.co_authors)),
+        };
+
+        for attempt in 1..=MAX_COMMIT_ATTEMPTS {
+            let remote_tip = remote_branch_tip(github_client, config)?;
+
+            let parent_sha = match &remote_tip {
+                // - createCommitOnBranch requires the branch to exist, so
+                //   create it at the local base first
+                None => match create_a_reference(config, github_client, &config.git_base_object_id)? {
+                    create_a_reference::ResponseBody::Ok(_) => config.git_base_object_id.clone(),
+                    create_a_reference::ResponseBody::UnprocessableEntity(failure) => {
+                        branch_moved(config, "create a reference", failure, REFERENCE_ALREADY_EXISTS_MESSAGE)?;
+                        print_intent_plain(&format!("Remote branch {:?} was created while committing (attempt {} of {})", config.git_branch_name, attempt, MAX_COMMIT_ATTEMPTS));
+                        continue
+                    },
+                },
+                Some(tip) if *tip == config.git_base_object_id => tip.clone(),
+                Some(tip) => parent_for_remote_tip(config, github_client, status, tip)?,
+            };
+
+            // - Since createCommitOnBranch always parents the commit on the
+            //   branch tip, a local base that is ahead of the remote branch
+            //   can't be committed on top of
+            if remote_tip.as_ref().is_some_and(|tip| *tip != parent_sha) {
+                Err(Error::unsupported(format!("Remote branch {:?} is behind the local base {}, which the GraphQL backend can't commit on top of; use the REST backend instead", config.git_branch_name, config.git_base_object_id)))?
+            }
+
+            input.expected_head_oid = parent_sha;
+
+            let result = Self::create_commit(config, github_client, &input);
+
+            // - The branch was only created for this commit, so it shouldn't be
+            //   left behind pointing at the base when the commit fails
+            let result = match (result, &remote_tip) {
+                (Err(e), None) => Err(Self::delete_created_branch(config, github_client, e)),
+                (result, _) => result,
+            };
+
+            match result? {
+                Some(commit) => return Ok(CreatedCommit::from_graphql(commit, RefAction::for_remote_tip(remote_tip.as_deref()))),
+                None => print_intent_plain(&format!("Remote branch {:?} moved while committing (attempt {} of {})", config.git_branch_name, attempt, MAX_COMMIT_ATTEMPTS)),
+            }
+        }
+
+        Err(Error::conflict(format!("Remote branch {:?} kept moving while committing; gave up after {} attempts", config.git_branch_name, MAX_COMMIT_ATTEMPTS)))
+    }
+
+    fn replay_unpushed_commits(&self, _config: &Config) -> Result<CreatedCommit> {
+        Err(Error::unsupported("Replaying commits is not supported by the GraphQL backend since each createCommitOnBranch call moves the branch; use the REST backend instead"))
+    }
+}
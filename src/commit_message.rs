@@ -0,0 +1,256 @@
+use std::env;
+use std::fs;
+use std::io::Read;
+use std::path::Path;
+use std::process::Command;
+
+use git2::Repository;
+
+use crate::error::{Error, Result};
+
+/// Joins messages given with `-m` as separate paragraphs, like `git commit`
+pub fn join_paragraphs(messages: &[String]) -> String {
+    messages.join("\n\n")
+}
+
+/// Reads a commit message from a file, or from stdin if `path` is `-`
+pub fn read_message_file(path: &str) -> Result<String> {
+    let mut message = String::new();
+
+    let result = match path {
+        "-" => std::io::stdin().read_to_string(&mut message).map(|_| ()),
+        path => fs::read_to_string(path).map(|contents| message = contents),
+    };
+
+    result.map_err(|e| Error::Io {
+        path: match path {
+            "-" => "(stdin)".to_string(),
+            path => path.to_string(),
+        },
+        message: e.to_string(),
+    })?;
+
+    Ok(message)
+}
+
+/// Cleans up a message the way `git commit` does by default: trailing
+/// whitespace is stripped from every line, consecutive blank lines are
+/// collapsed, and leading and trailing blank lines are removed
+///
+/// - `comment_char` strips the lines starting with it as well, which is only
+///   done for messages written in an editor
+pub fn cleanup(message: &str, comment_char: Option<char>) -> String {
+    let mut cleaned = String::with_capacity(message.len());
+    let mut pending_blank_line = false;
+
+    for line in message.lines() {
+        if comment_char.is_some_and(|c| line.starts_with(c)) {
+            continue
+        }
+
+        let line = line.trim_end();
+
+        if line.is_empty() {
+            pending_blank_line = !cleaned.is_empty();
+            continue
+        }
+
+        if pending_blank_line {
+            cleaned.push('\n');
+            pending_blank_line = false;
+        }
+
+        cleaned.push_str(line);
+        cleaned.push('\n');
+    }
+
+    cleaned
+}
+
+/// - `core.commentChar` may be `auto`, which picks a character that doesn't
+///   start any line; the default is used instead since the template is
+///   written before the message exists
+fn comment_char(repo: &Repository) -> char {
+    let configured = repo.config().ok()
+        .and_then(|config| config.get_string("core.commentChar").ok());
+
+    match configured.as_deref().map(|value| value.chars().collect::<Vec<char>>()).as_deref() {
+        Some([c]) => *c,
+        _ => '#',
+    }
+}
+
+/// The editor `git commit` would use
+///
+/// - [git var GIT_EDITOR](https://git-scm.com/docs/git-var#Documentation/git-var.txt-GITEDITOR)
+fn editor(repo: &Repository) -> Result<String> {
+    let non_empty = |value: String| Some(value).filter(|value| !value.trim().is_empty());
+    let is_dumb_terminal = env::var("TERM").map_or(true, |term| term == "dumb");
+
+    let configured = env::var("GIT_EDITOR").ok().and_then(non_empty)
+        .or_else(|| repo.config().ok().and_then(|config| config.get_string("core.editor").ok()).and_then(non_empty))
+        .or_else(|| env::var("VISUAL").ok().and_then(non_empty).filter(|_| !is_dumb_terminal))
+        .or_else(|| env::var("EDITOR").ok().and_then(non_empty));
+
+    match configured {
+        Some(editor) => Ok(editor),
+        None if is_dumb_terminal => Err(Error::config("No editor is configured and the terminal is dumb; set GIT_EDITOR, core.editor, or EDITOR, or pass --message or --file instead")),
+        None => Ok("vi".to_string()),
+    }
+}
+
+/// Reads the file `commit.template` points to, if it's set
+fn template(repo: &Repository) -> Result<Option<String>> {
+    let path = match repo.config().ok().and_then(|config| config.get_path("commit.template").ok()) {
+        Some(path) => path,
+        None => return Ok(None),
+    };
+
+    fs::read_to_string(&path)
+        .map(Some)
+        .map_err(|e| Error::Io {
+            path: path.to_string_lossy().into_owned(),
+            message: e.to_string(),
+        })
+}
+
+/// Runs `editor` on `path` through the shell, like git does, so that editors
+/// configured with arguments such as `code --wait` work
+fn run_editor(editor: &str, path: &Path) -> Result<()> {
+    let status = Command::new("sh")
+        .arg("-c")
+        .arg(format!("{} \"$@\"", editor))
+        .arg(editor)
+        .arg(path)
+        .status()
+        .map_err(|e| Error::config(format!("Unable to run editor {:?}: {}", editor, e)))?;
+
+    match status.success() {
+        true => Ok(()),
+        false => Err(Error::config(format!("Editor {:?} exited with {}; aborting", editor, status))),
+    }
+}
+
+fn edit_message_with(repo: &Repository, message: &str, editor: &str) -> Result<String> {
+    let comment_char = comment_char(repo);
+
+    // - Like `git commit`, the template is only used when no message was given
+    let template = match message.trim().is_empty() {
+        true => template(repo)?,
+        false => None,
+    };
+
+    let initial = template.as_deref().unwrap_or(message);
+
+    let instructions = format!(
+        "\n{c} Please enter the commit message for your changes. Lines starting\n{c} with '{c}' will be ignored, and an empty message aborts the commit.\n",
+        c = comment_char,
+    );
+
+    let path = repo.path().join("COMMIT_EDITMSG");
+    let path_string = path.to_string_lossy().into_owned();
+    let io_error = |e: std::io::Error| Error::Io {
+        path: path_string.clone(),
+        message: e.to_string(),
+    };
+
+    fs::write(&path, format!("{}{}", cleanup(initial, None), instructions))
+        .map_err(io_error)?;
+
+    run_editor(editor, &path)?;
+
+    let edited = fs::read_to_string(&path)
+        .map_err(io_error)?;
+
+    let edited = cleanup(&edited, Some(comment_char));
+
+    if template.is_some_and(|template| cleanup(&template, Some(comment_char)) == edited) {
+        Err(Error::config("Aborting commit; the commit message template was not edited"))?
+    }
+
+    Ok(edited)
+}
+
+/// Finalizes the commit message, opening an editor first if `edit` is set
+///
+/// - The message is cleaned up if it was edited or `clean_up` is set, and
+///   otherwise used verbatim
+/// - An empty message aborts the commit, like it does with `git commit`
+pub fn resolve(repo: &Repository, message: &str, edit: bool, clean_up: bool) -> Result<String> {
+    let message = match (edit, clean_up) {
+        (true, _) => edit_message_with(repo, message, &editor(repo)?)?,
+        (false, true) => cleanup(message, None),
+        (false, false) => message.to_string(),
+    };
+
+    if message.trim().is_empty() {
+        Err(Error::config("Aborting commit due to empty commit message"))?
+    }
+
+    Ok(message)
+}
+
+#[cfg(test)]
+mod commit_message_tests {
+    use std::fs;
+
+    use crate::test_utils::test_utils::TempGitRepo;
+
+    use super::{cleanup, edit_message_with, join_paragraphs, resolve};
+
+    #[test]
+    fn paragraphs_are_separated_by_blank_lines() {
+        let messages = vec!["Add foo".to_string(), "Because bar".to_string()];
+
+        assert_eq!(join_paragraphs(&messages), "Add foo\n\nBecause bar");
+    }
+
+    #[test]
+    fn cleanup_strips_whitespace_and_comments() {
+        let message = "\n\nAdd foo  \n\n\n# A comment\nBecause bar\n\n";
+
+        assert_eq!(cleanup(message, None), "Add foo\n\n# A comment\nBecause bar\n");
+        assert_eq!(cleanup(message, Some('#')), "Add foo\n\nBecause bar\n");
+    }
+
+    #[test]
+    fn empty_message_aborts() {
+        let repo = TempGitRepo::new();
+
+        assert!(resolve(&repo.repo, " \n\n", false, false).is_err());
+        assert!(resolve(&repo.repo, " \n\n", false, true).is_err());
+    }
+
+    #[test]
+    fn only_messages_to_clean_up_are_cleaned_up() {
+        let repo = TempGitRepo::new();
+        let message = "Add foo  \n\n\nBecause bar\n\n";
+
+        assert_eq!(resolve(&repo.repo, message, false, false).unwrap(), message);
+        assert_eq!(resolve(&repo.repo, message, false, true).unwrap(), "Add foo\n\nBecause bar\n");
+    }
+
+    #[test]
+    fn editor_edits_the_template() {
+        let repo = TempGitRepo::new();
+
+        let template_path = repo.directory.path().join("template.txt");
+        fs::write(&template_path, "Subject\n\n# Explain why\n").unwrap();
+        repo.repo.config().unwrap().set_str("commit.template", template_path.to_str().unwrap()).unwrap();
+
+        let unedited = edit_message_with(&repo.repo, "", "true");
+        assert!(unedited.is_err());
+
+        let edited = edit_message_with(&repo.repo, "", "sed -i -e 's/^Subject$/Add foo/'").unwrap();
+        assert_eq!(edited, "Add foo\n");
+    }
+
+    #[test]
+    fn editor_starts_from_the_given_message() {
+        let repo = TempGitRepo::new();
+
+        let edited = edit_message_with(&repo.repo, "Add foo", "printf 'Because bar\\n' >>").unwrap();
+
+        assert_eq!(edited, "Add foo\n\nBecause bar\n");
+    }
+}
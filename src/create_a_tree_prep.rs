@@ -1,10 +1,13 @@
 use std::collections::HashSet;
+use std::fmt;
 use std::io::Write;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::thread;
 
 use base64::write::EncoderStringWriter;
 use once_cell::sync::Lazy;
 
-use crate::config::Config;
+use crate::error::{Error, Result};
 use crate::github::GitHubClient;
 use crate::github::rest_api::create_a_blob;
 use crate::github::rest_api::create_a_tree;
@@ -18,6 +21,7 @@ use crate::git_status::PathStatus;
 ///   `git2::FileMode::Unreadable`
 /// - Upon testing, the API responds the same no matter what accepted mode is
 /// used, so `Blob` was chosen arbitrarily
+#[allow(clippy::doc_lazy_continuation)]
 const DELETED_FILE_MODE: create_a_tree::FileMode = create_a_tree::FileMode::Blob;
 
 /// - [GitHub's Create a tree API endpoint](https://docs.github.com/en/rest/git/trees?apiVersion=2022-11-28#create-a-tree)
@@ -28,16 +32,34 @@ const DELETED_FILE_MODE: create_a_tree::FileMode = create_a_tree::FileMode::Blob
 /// to `Option<NodeType>` as it would introduce invalid states
 /// - A "more correct" fix is possible, but the cost of the complexity does not
 /// seem worthwhile
+#[allow(clippy::doc_lazy_continuation)]
 const DELETED_NODE_TYPE: create_a_tree::NodeType = create_a_tree::NodeType::Blob;
 
+/// GitHub rejects blobs over 100 MiB, whether they're sent inline or
+/// uploaded
+///
+/// - [About large files on GitHub](https://docs.github.com/en/repositories/working-with-files/managing-large-files/about-large-files-on-github)
+pub(crate) const MAX_BLOB_SIZE: usize = 100 * 1024 * 1024;
+
+/// Text files over this size are uploaded as blobs rather than sent inline,
+/// so that one large file doesn't make the tree's request too large
+const MAX_INLINE_CONTENT_SIZE: usize = 1024 * 1024;
+
+/// Roughly how large a single Create a tree request may get before the rest
+/// of the nodes are sent in another request on top of the resulting tree
+///
+/// - The size is the sum of the nodes' serialized sizes, so inline contents
+///   count with their JSON escaping
+const MAX_TREE_REQUEST_SIZE: usize = 4 * 1024 * 1024;
+
 #[derive(Debug)]
-enum ObjectContents {
+pub enum ObjectContents {
     Text(String),
     Base64(String),
 }
 
 #[derive(Clone, Copy, Debug, Eq, PartialEq, Hash)]
-enum GitCommitAction {
+pub(crate) enum GitCommitAction {
     AddPath,
     DeleteOriginalPath,
     DeletePath,
@@ -45,7 +67,7 @@ enum GitCommitAction {
     Unsupported,
 }
 
-fn delta_to_actions(git2_delta: git2::Delta) -> &'static HashSet<GitCommitAction> {
+pub(crate) fn delta_to_actions(git2_delta: git2::Delta) -> &'static HashSet<GitCommitAction> {
     static ADD_PATH: Lazy<HashSet<GitCommitAction>> = Lazy::new(|| HashSet::from([
         GitCommitAction::AddPath,
     ]));
@@ -85,7 +107,7 @@ fn delta_to_actions(git2_delta: git2::Delta) -> &'static HashSet<GitCommitAction
 
 /// Note: Files must be read by their object ID, not the path, since
 /// modifications could have been made after being staged
-fn read_file(path_status: &PathStatus, git_object: &git2::Object) -> Result<ObjectContents, String> {
+fn read_file(path_status: &PathStatus, git_object: &git2::Object) -> Result<ObjectContents> {
     if let Some(blob) = git_object.as_blob() {
         let bytes = blob.content();
 
@@ -96,16 +118,16 @@ fn read_file(path_status: &PathStatus, git_object: &git2::Object) -> Result<Obje
 
                 match enc.write_all(bytes) {
                     Ok(_) => Ok(ObjectContents::Base64(enc.into_inner())),
-                    Err(_) => Err(format!("Failed to Base64-encode contents of path {} with corresponding object ID {:?}", path_status.path, git_object.id())),
+                    Err(e) => Err(Error::git_path(format!("Base64-encode contents of object {}", git_object.id()), &path_status.path, e)),
                 }
             }
         }
     } else {
-        Err(format!("Path {:?} was expected to be a blob, but found {:?}", path_status.path, git_object.kind()))
+        Err(Error::unsupported_path(&path_status.path, format!("Expected a blob, but found {:?}", git_object.kind())))
     }
 }
 
-fn git2_mode_to_github_mode(path_status: &PathStatus) -> Result<create_a_tree::FileMode, String> {
+fn git2_mode_to_github_mode(path_status: &PathStatus) -> Result<create_a_tree::FileMode> {
     let github_mode = match path_status.file_mode {
         git2::FileMode::Blob => create_a_tree::FileMode::Blob,
         git2::FileMode::BlobExecutable => create_a_tree::FileMode::BlobExecutable,
@@ -116,13 +138,13 @@ fn git2_mode_to_github_mode(path_status: &PathStatus) -> Result<create_a_tree::F
         // - Not supported by GitHub
         // git2::FileMode::BlobGroupWritable => todo!(),
         // git2::FileMode::Unreadable => todo!(),
-        _ => Err(format!("Path {:?} has file mode {:?} which is not supported by GitHub", path_status.path, path_status.file_mode))?,
+        _ => Err(Error::unsupported_path(&path_status.path, format!("File mode {:?} is not supported by GitHub", path_status.file_mode)))?,
     };
 
     Ok(github_mode)
 }
 
-fn git2_node_type_to_github_node_type(path_status: &PathStatus, git_object: &git2::Object) -> Result<create_a_tree::NodeType, String> {
+fn git2_node_type_to_github_node_type(path_status: &PathStatus, git_object: &git2::Object) -> Result<create_a_tree::NodeType> {
     match git_object.kind() {
         Some(object_type) => {
             let node_type = match object_type {
@@ -133,17 +155,174 @@ fn git2_node_type_to_github_node_type(path_status: &PathStatus, git_object: &git
                 // - Not supported by GitHub
                 // git2::ObjectType::Any => todo!(),
                 // git2::ObjectType::Tag => todo!(),
-                _ => Err(format!("Path {:?} has object type {:?} which is not supported by GitHub's API", path_status.path, git_object.kind()))?
+                _ => Err(Error::unsupported_path(&path_status.path, format!("Object type {:?} is not supported by GitHub's API", git_object.kind())))?
             };
 
             Ok(node_type)
         },
-        None => Err(format!("Unknown object type on object with ID {:?} with path {:?}", git_object.id(), path_status.path)),
+        None => Err(Error::unsupported_path(&path_status.path, format!("Unknown object type on object with ID {}", git_object.id()))),
+    }
+}
+
+/// What a tree node's content will be once the plan is carried out
+pub enum PlannedContent {
+    /// Sent inline as part of the tree
+    Text(String),
+    /// Uploaded as a blob before the tree is created since the tree API only
+    /// accepts inline content that is valid UTF-8, or since the text is too
+    /// large to send inline
+    Blob {
+        object_id: git2::Oid,
+        contents: ObjectContents,
+        size: usize,
+    },
+    /// A blob the remote already has, which is referenced by SHA rather than
+    /// sent again
+    Existing {
+        object_id: git2::Oid,
+    },
+    /// A submodule pointing to a commit in the submodule's repository, which
+    /// is referenced by SHA since the superproject doesn't have the commit
+    Submodule {
+        commit_id: git2::Oid,
+    },
+    /// Removed from the tree
+    Deletion,
+}
+
+// - Like `create_a_tree::ShaOrContent`, only print the size of the contents
+impl fmt::Debug for PlannedContent {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            PlannedContent::Text(text) => {
+                f.debug_tuple("Text")
+                    .field(&format_args!("(content size: {})", text.len()))
+                    .finish()
+            },
+            PlannedContent::Blob { object_id, size, .. } => {
+                f.debug_struct("Blob")
+                    .field("object_id", object_id)
+                    .field("size", size)
+                    .finish()
+            },
+            PlannedContent::Existing { object_id } => {
+                f.debug_struct("Existing")
+                    .field("object_id", object_id)
+                    .finish()
+            },
+            PlannedContent::Submodule { commit_id } => {
+                f.debug_struct("Submodule")
+                    .field("commit_id", commit_id)
+                    .finish()
+            },
+            PlannedContent::Deletion => f.write_str("Deletion"),
+        }
+    }
+}
+
+#[derive(Debug)]
+pub struct PlannedTreeNode {
+    pub path: String,
+    pub file_mode: create_a_tree::FileMode,
+    pub node_type: create_a_tree::NodeType,
+    pub content: PlannedContent,
+}
+
+/// IDs of the blobs the remote already has, given `local_base`, a local commit
+/// whose tree the remote has
+///
+/// - Every blob in `local_base`'s tree is included, which covers renamed,
+///   copied, and moved files, as are the blobs `local_base` replaced in its
+///   parent's tree, which covers reverting `local_base`
+/// - This is worked out from the local repository, so unlike asking GitHub
+///   about each blob, it doesn't cost any requests
+pub fn existing_blob_ids(repo: &git2::Repository, local_base: Option<&git2::Commit>) -> Result<HashSet<git2::Oid>> {
+    let mut blob_ids = HashSet::new();
+
+    let local_base = match local_base {
+        Some(local_base) => local_base,
+        None => return Ok(blob_ids),
+    };
+
+    let tree = local_base.tree()
+        .map_err(|e| Error::git(format!("read tree of commit {}", local_base.id()), e))?;
+
+    tree.walk(git2::TreeWalkMode::PreOrder, |_, entry| {
+        if entry.kind() == Some(git2::ObjectType::Blob) {
+            blob_ids.insert(entry.id());
+        }
+
+        git2::TreeWalkResult::Ok
+    }).map_err(|e| Error::git(format!("walk tree of commit {}", local_base.id()), e))?;
+
+    // - The parent may be missing from a shallow clone, in which case only
+    //   the base's own tree is used
+    if let Ok(parent_tree) = local_base.parent(0).and_then(|parent| parent.tree()) {
+        let diff = repo.diff_tree_to_tree(Some(&parent_tree), Some(&tree), None)
+            .map_err(|e| Error::git(format!("diff commit {} against its parent", local_base.id()), e))?;
+
+        for delta in diff.deltas() {
+            let old_file = delta.old_file();
+
+            if !old_file.id().is_zero() && old_file.mode() != git2::FileMode::Commit {
+                blob_ids.insert(old_file.id());
+            }
+        }
+    }
+
+    Ok(blob_ids)
+}
+
+/// Checks that none of the blobs that would be sent are over `max_blob_size`,
+/// reporting every path that is
+///
+/// - Only the objects' headers are read, so large files aren't loaded just to
+///   be rejected
+pub(crate) fn ensure_blobs_within_size_limit(repo: &git2::Repository, git_status: &[PathStatus], existing_blob_ids: &HashSet<git2::Oid>, max_blob_size: usize) -> Result<()> {
+    let odb = repo.odb()
+        .map_err(|e| Error::git(format!("open the object database of repo {:?}", repo.path()), e))?;
+
+    let mut failures = Vec::new();
+
+    for path_status in git_status {
+        let is_added = delta_to_actions(path_status.delta).contains(&GitCommitAction::AddPath);
+        let is_blob = path_status.file_mode != git2::FileMode::Commit;
+
+        if !is_added || !is_blob || existing_blob_ids.contains(&path_status.object_id) {
+            continue;
+        }
+
+        let (size, _) = odb.read_header(path_status.object_id)
+            .map_err(|e| Error::git_path(format!("read the header of object {}", path_status.object_id), &path_status.path, e))?;
+
+        if size > max_blob_size {
+            let message = format!("File is {} bytes, which is over GitHub's limit of {} bytes per file; consider Git LFS", size, max_blob_size);
+
+            failures.push((path_status.path.clone(), Error::unsupported_path(&path_status.path, message)));
+        }
+    }
+
+    match failures.len() {
+        0 => Ok(()),
+        1 => Err(failures.remove(0).1),
+        _ => Err(Error::Multiple {
+            operation: "commit files over GitHub's size limit".to_string(),
+            failures: failures,
+        }),
     }
 }
 
-pub fn generate_request_body(config: &Config, repo: &git2::Repository, git_status: &Vec<PathStatus>, github_client: &GitHubClient) -> Result<create_a_tree::RequestBody, String> {
-    let mut tree = Vec::with_capacity(git_status.len());
+/// Works out the tree nodes for the changes in `git_status` without making
+/// any network calls
+///
+/// - Blobs in `existing_blob_ids` are referenced by SHA instead of being read
+///   and sent again
+/// - Files over GitHub's size limit are rejected up front, before anything is
+///   uploaded
+pub fn plan_tree(repo: &git2::Repository, git_status: &[PathStatus], existing_blob_ids: &HashSet<git2::Oid>) -> Result<Vec<PlannedTreeNode>> {
+    ensure_blobs_within_size_limit(repo, git_status, existing_blob_ids, MAX_BLOB_SIZE)?;
+
+    let mut plan = Vec::with_capacity(git_status.len());
 
     for path_status in git_status {
         let actions = delta_to_actions(path_status.delta);
@@ -152,36 +331,58 @@ pub fn generate_request_body(config: &Config, repo: &git2::Repository, git_statu
             let path = path_status.path.clone();
 
             match action {
+                // - A gitlink's object ID is a commit in the submodule's
+                //   repository, so there's nothing to look up or read here
+                GitCommitAction::AddPath if path_status.file_mode == git2::FileMode::Commit => {
+                    let node = PlannedTreeNode {
+                        path: path,
+                        file_mode: create_a_tree::FileMode::Commit,
+                        node_type: create_a_tree::NodeType::Commit,
+                        content: PlannedContent::Submodule {
+                            commit_id: path_status.object_id,
+                        },
+                    };
+
+                    plan.push(node);
+                },
+                GitCommitAction::AddPath if existing_blob_ids.contains(&path_status.object_id) => {
+                    let node = PlannedTreeNode {
+                        path: path,
+                        file_mode: git2_mode_to_github_mode(path_status)?,
+                        node_type: create_a_tree::NodeType::Blob,
+                        content: PlannedContent::Existing {
+                            object_id: path_status.object_id,
+                        },
+                    };
+
+                    plan.push(node);
+                },
                 GitCommitAction::AddPath => {
                     let git_object_id = path_status.object_id;
                     let git_object = repo.find_object(git_object_id, None)
-                        .map_err(|_| format!("Unable to find object {:?} in repo {:?}", git_object_id, repo.path()))?;
+                        .map_err(|e| Error::git_path(format!("find object {} in repo {:?}", git_object_id, repo.path()), &path_status.path, e))?;
 
                     let node_type = git2_node_type_to_github_node_type(path_status, &git_object)?;
                     let file_mode = git2_mode_to_github_mode(path_status)?;
                     let object_contents = read_file(path_status, &git_object)?;
 
-                    let sha_or_content = match object_contents {
-                        ObjectContents::Text(text) => create_a_tree::ShaOrContent::Content(text),
-                        ObjectContents::Base64(base64_string) => {
-                            let request_body = create_a_blob::RequestBody {
-                                content: &base64_string,
-                                encoding: create_a_blob::Encoding::Base64,
-                            };
-
-                            let response = github_client.create_a_blob(&request_body)?;
-                            create_a_tree::ShaOrContent::Sha(Some(response.sha))
+                    let content = match object_contents {
+                        ObjectContents::Text(text) if text.len() <= MAX_INLINE_CONTENT_SIZE => PlannedContent::Text(text),
+                        object_contents => PlannedContent::Blob {
+                            object_id: git_object_id,
+                            contents: object_contents,
+                            size: git_object.as_blob().map(|blob| blob.size()).unwrap_or_default(),
                         },
                     };
 
-                    let node = create_a_tree::TreeNode {
+                    let node = PlannedTreeNode {
                         path: path,
                         file_mode: file_mode,
                         node_type: node_type,
-                        sha_or_content: sha_or_content,
+                        content: content,
                     };
 
-                    tree.push(node);
+                    plan.push(node);
                 },
                 GitCommitAction::DeletePath | GitCommitAction::DeleteOriginalPath => {
                     let path = match action {
@@ -189,45 +390,263 @@ pub fn generate_request_body(config: &Config, repo: &git2::Repository, git_statu
                         GitCommitAction::DeleteOriginalPath => {
                             match &path_status.original_path {
                                 Some(path) => path.clone(),
-                                None => Err(format!("Expected an original path, but none was found for {:?}", path_status))?,
+                                None => Err(Error::internal(format!("Expected an original path, but none was found for {:?}", path_status)))?,
                             }
                         },
-                        _ => Err(format!("Expected delete action, but found {:?}", action))?,
+                        _ => Err(Error::internal(format!("Expected delete action, but found {:?}", action)))?,
                     };
 
-                    let node = create_a_tree::TreeNode {
+                    let node = PlannedTreeNode {
                         path: path,
                         file_mode: DELETED_FILE_MODE,
                         node_type: DELETED_NODE_TYPE,
-                        sha_or_content: create_a_tree::ShaOrContent::Sha(None),
+                        content: PlannedContent::Deletion,
                     };
 
-                    tree.push(node);
+                    plan.push(node);
                 },
                 GitCommitAction::Nop => {},
                 GitCommitAction::Unsupported => {
-                    Err(format!("Unsupported delta {:?} for path status {:?}", path_status.delta, path_status))?
+                    Err(Error::unsupported_path(&path_status.path, format!("Unsupported delta {:?}", path_status.delta)))?
                 },
             }
         }
     }
 
+    Ok(plan)
+}
+
+/// Runs `upload` on every item with up to `max_concurrent` calls in flight at
+/// once, returning the results in the items' order
+///
+/// - Every item is uploaded even after one fails, so that every failure can
+///   be reported together rather than only the first one
+pub(crate) fn upload_concurrently<T: Sync, R: Send>(items: &[T], max_concurrent: usize, upload: impl Fn(&T) -> Result<R> + Sync) -> Vec<Result<R>> {
+    let next_item = AtomicUsize::new(0);
+    let worker_count = max_concurrent.clamp(1, items.len().max(1));
+
+    let mut results: Vec<(usize, Result<R>)> = thread::scope(|scope| {
+        let workers: Vec<_> = (0..worker_count)
+            .map(|_| scope.spawn(|| {
+                let mut results = Vec::new();
+
+                loop {
+                    let index = next_item.fetch_add(1, Ordering::Relaxed);
+
+                    let item = match items.get(index) {
+                        Some(item) => item,
+                        None => break,
+                    };
+
+                    results.push((index, upload(item)));
+                }
+
+                results
+            }))
+            .collect();
+
+        workers.into_iter()
+            .flat_map(|worker| worker.join().unwrap_or_else(|panic| std::panic::resume_unwind(panic)))
+            .collect()
+    });
+
+    results.sort_by_key(|(index, _)| *index);
+    results.into_iter().map(|(_, result)| result).collect()
+}
+
+/// Uploads the blobs `plan` calls for, returning each blob's SHA by its
+/// node's position in `plan`
+///
+/// - Every failed upload is reported, each with its path, rather than only
+///   the first one
+fn upload_blobs(plan: &[PlannedTreeNode], max_concurrent_uploads: usize, github_client: &GitHubClient) -> Result<Vec<Option<String>>> {
+    let blobs: Vec<(usize, &str, &ObjectContents)> = plan.iter()
+        .enumerate()
+        .filter_map(|(index, node)| match &node.content {
+            PlannedContent::Blob { contents, .. } => Some((index, node.path.as_str(), contents)),
+            _ => None,
+        })
+        .collect();
+
+    let results = upload_concurrently(&blobs, max_concurrent_uploads, |(_, _, contents)| {
+        let request_body = match contents {
+            ObjectContents::Text(text) => create_a_blob::RequestBody {
+                content: text,
+                encoding: create_a_blob::Encoding::Utf8,
+            },
+            ObjectContents::Base64(base64) => create_a_blob::RequestBody {
+                content: base64,
+                encoding: create_a_blob::Encoding::Base64,
+            },
+        };
+
+        github_client.create_a_blob(&request_body)
+    });
+
+    let mut shas = vec![None; plan.len()];
+    let mut failures = Vec::new();
+
+    for ((index, path, _), result) in blobs.into_iter().zip(results) {
+        match result {
+            Ok(response) => shas[index] = Some(response.sha),
+            Err(e) => failures.push((path.to_string(), e)),
+        }
+    }
+
+    match failures.len() {
+        0 => Ok(shas),
+        1 => Err(failures.remove(0).1),
+        _ => Err(Error::Multiple {
+            operation: "create a blob".to_string(),
+            failures: failures,
+        }),
+    }
+}
+
+/// Carries out a plan from `plan_tree`, uploading the blobs it calls for, and
+/// returns the body to create the tree with
+///
+/// - Blobs are uploaded with up to `max_concurrent_uploads` requests in
+///   flight, but the tree's nodes keep the plan's order
+pub fn execute_plan(base_tree: &str, plan: Vec<PlannedTreeNode>, max_concurrent_uploads: usize, github_client: &GitHubClient) -> Result<create_a_tree::RequestBody> {
+    let mut blob_shas = upload_blobs(&plan, max_concurrent_uploads, github_client)?;
+    let mut tree = Vec::with_capacity(plan.len());
+
+    for (index, planned_node) in plan.into_iter().enumerate() {
+        let sha_or_content = match planned_node.content {
+            PlannedContent::Text(text) => create_a_tree::ShaOrContent::Content(text),
+            PlannedContent::Blob { object_id, .. } => match blob_shas[index].take() {
+                Some(sha) => create_a_tree::ShaOrContent::Sha(Some(sha)),
+                None => Err(Error::internal(format!("Blob {} for path {:?} was planned but not uploaded", object_id, planned_node.path)))?,
+            },
+            PlannedContent::Existing { object_id } => create_a_tree::ShaOrContent::Sha(Some(object_id.to_string())),
+            PlannedContent::Submodule { commit_id } => create_a_tree::ShaOrContent::Sha(Some(commit_id.to_string())),
+            PlannedContent::Deletion => create_a_tree::ShaOrContent::Sha(None),
+        };
+
+        let node = create_a_tree::TreeNode {
+            path: planned_node.path,
+            file_mode: planned_node.file_mode,
+            node_type: planned_node.node_type,
+            sha_or_content: sha_or_content,
+        };
+
+        tree.push(node);
+    }
+
     let body = create_a_tree::RequestBody {
-        base_tree: config.git_head_object_id.clone(),
+        base_tree: base_tree.to_owned(),
         tree: tree,
     };
 
     Ok(body)
 }
 
+/// Plans and carries out the tree for the changes in `git_status` on top of
+/// `base_tree`
+///
+/// - The blobs the remote already has because of `local_base` are referenced
+///   by SHA; see `existing_blob_ids`
+pub fn generate_request_body(base_tree: &str, local_base: Option<&git2::Commit>, repo: &git2::Repository, git_status: &[PathStatus], max_concurrent_uploads: usize, github_client: &GitHubClient) -> Result<create_a_tree::RequestBody> {
+    let existing_blob_ids = existing_blob_ids(repo, local_base)?;
+    let plan = plan_tree(repo, git_status, &existing_blob_ids)?;
+
+    execute_plan(base_tree, plan, max_concurrent_uploads, github_client)
+}
+
+/// The size of `node` in a request's JSON, including the comma separating it
+/// from the next node
+///
+/// - Escaping can make inline contents several times larger than they are,
+///   such as six bytes for each control character, so the node is measured
+///   serialized
+fn serialized_node_size(node: &create_a_tree::TreeNode) -> usize {
+    let serialized_size = serde_json::to_vec(node)
+        .map(|json| json.len())
+        .unwrap_or(usize::MAX);
+
+    serialized_size.saturating_add(1)
+}
+
+/// Splits `body` into requests of up to roughly `max_request_size` each, to be
+/// sent with `create_a_tree_in_chunks`
+///
+/// - There's always at least one request, even if there are no nodes
+fn split_request_body_with_max_size(body: create_a_tree::RequestBody, max_request_size: usize) -> Vec<create_a_tree::RequestBody> {
+    let mut chunks: Vec<create_a_tree::RequestBody> = Vec::new();
+    let mut chunk_size = 0;
+
+    for node in body.tree {
+        let node_size = serialized_node_size(&node);
+
+        match chunks.last_mut() {
+            Some(chunk) if chunk_size + node_size <= max_request_size || chunk.tree.is_empty() => {
+                chunk_size += node_size;
+                chunk.tree.push(node);
+            },
+            _ => {
+                chunk_size = node_size;
+                chunks.push(create_a_tree::RequestBody {
+                    base_tree: body.base_tree.clone(),
+                    tree: vec![node],
+                });
+            },
+        }
+    }
+
+    if chunks.is_empty() {
+        chunks.push(create_a_tree::RequestBody {
+            base_tree: body.base_tree,
+            tree: Vec::new(),
+        });
+    }
+
+    chunks
+}
+
+/// Splits `body` into requests that are small enough for GitHub to accept, to
+/// be sent with `create_a_tree_in_chunks`
+pub fn split_request_body(body: create_a_tree::RequestBody) -> Vec<create_a_tree::RequestBody> {
+    split_request_body_with_max_size(body, MAX_TREE_REQUEST_SIZE)
+}
+
+/// Creates the tree that `chunks` from `split_request_body` describe on top of
+/// `base_tree`, one request per chunk, each on top of the tree the previous
+/// one created
+///
+/// - The chunks' base trees are overwritten, so the same chunks can be sent
+///   again on top of another base
+pub fn create_a_tree_in_chunks(github_client: &GitHubClient, base_tree: &str, chunks: &mut [create_a_tree::RequestBody]) -> Result<create_a_tree::ResponseBody> {
+    let mut tree: Option<create_a_tree::ResponseBody> = None;
+
+    for chunk in chunks.iter_mut() {
+        chunk.base_tree = match &tree {
+            Some(tree) => tree.sha.clone(),
+            None => base_tree.to_owned(),
+        };
+
+        tree = Some(github_client.create_a_tree(chunk)?);
+    }
+
+    tree.ok_or_else(|| Error::internal("Expected at least one request to create the tree with, but found none"))
+}
+
 #[cfg(test)]
+#[allow(clippy::expect_fun_call, clippy::needless_borrow, clippy::ptr_arg)]
 mod create_a_tree_prep_tests {
+    use std::collections::HashSet;
+    use std::path::Path;
+    use std::sync::atomic::{AtomicUsize, Ordering};
+    use std::thread;
+    use std::time::Duration;
+
     use git2::Repository;
 
+    use crate::error::Error;
     use crate::git_status::{PathStatus, git_status};
-    use crate::test_utils::test_utils::TempGitRepo;
+    use crate::test_utils::test_utils::{TempGitRepo, github_client, oid_from_str};
 
-    use super::{ObjectContents, read_file};
+    use super::{MAX_INLINE_CONTENT_SIZE, ObjectContents, PlannedContent, create_a_tree, ensure_blobs_within_size_limit, execute_plan, existing_blob_ids, plan_tree, read_file, split_request_body_with_max_size, upload_concurrently};
 
     fn read_git_object(repo: &Repository, git_status: &Vec<PathStatus>, filename: &str) -> ObjectContents {
         let path_status = git_status.iter().find(|ps| ps.path == filename)
@@ -292,4 +711,462 @@ mod create_a_tree_prep_tests {
             ObjectContents::Base64(_) => panic!("Expected ObjectContents::Text, but found ObjectContents::Base64"),
         }
     }
+
+    #[test]
+    fn plan_tree_without_network_calls() {
+        let repo = TempGitRepo::new();
+
+        let foo = repo.create_or_replace_blob_file("foo", "foo\n".as_bytes());
+        let bar = repo.create_or_replace_blob_file("bar", "bar\n".as_bytes());
+
+        repo.git_add(&foo);
+        repo.git_add(&bar);
+        repo.git_commit("Add foo and bar");
+
+        let baz = repo.create_or_replace_blob_file("baz", &[0x80]);
+
+        repo.git_add(&baz);
+        repo.git_rm(&bar);
+
+        let status = git_status(&repo.repo)
+            .expect("Unable to get a git status");
+
+        let plan = plan_tree(&repo.repo, &status, &HashSet::new())
+            .expect("Unable to plan a tree");
+
+        let mut planned: Vec<(&str, &PlannedContent)> = plan.iter()
+            .map(|node| (node.path.as_str(), &node.content))
+            .collect();
+        planned.sort_by_key(|(path, _)| *path);
+
+        match planned.as_slice() {
+            [("bar", PlannedContent::Deletion), ("baz", PlannedContent::Blob { contents: ObjectContents::Base64(base64), size, .. })] => {
+                assert_eq!(base64, "gA==");
+                assert_eq!(*size, 1);
+            },
+            _ => panic!("Unexpected plan: {:?}", planned),
+        }
+    }
+
+    fn plan_paths(repo: &TempGitRepo) -> Vec<(String, String, PlannedContent)> {
+        let status = git_status(&repo.repo)
+            .expect("Unable to get a git status");
+
+        let plan = plan_tree(&repo.repo, &status, &HashSet::new())
+            .expect("Unable to plan a tree");
+
+        let mut planned: Vec<(String, String, PlannedContent)> = plan.into_iter()
+            .map(|node| (node.path, format!("{:?} {:?}", node.file_mode, node.node_type), node.content))
+            .collect();
+        planned.sort_by(|(a, _, _), (b, _, _)| a.cmp(b));
+
+        planned
+    }
+
+    #[test]
+    fn plan_tree_submodule_update() {
+        let repo = TempGitRepo::new();
+
+        let old_commit_id = oid_from_str("1111111111111111111111111111111111111111");
+        let new_commit_id = oid_from_str("2222222222222222222222222222222222222222");
+
+        repo.git_add_submodule("sub", old_commit_id);
+        repo.git_commit("Add sub");
+
+        repo.git_add_submodule("sub", new_commit_id);
+
+        let planned = plan_paths(&repo);
+
+        match planned.as_slice() {
+            [(path, mode_and_type, PlannedContent::Submodule { commit_id })] => {
+                assert_eq!(path, "sub");
+                assert_eq!(mode_and_type, "Commit Commit");
+                assert_eq!(*commit_id, new_commit_id);
+            },
+            _ => panic!("Unexpected plan: {:?}", planned),
+        }
+    }
+
+    #[test]
+    fn plan_tree_submodule_addition() {
+        let repo = TempGitRepo::new();
+
+        let gitmodules_contents = "[submodule \"sub\"]\n\tpath = sub\n\turl = https://github.com/gemini-oss/sub.git\n";
+        let gitmodules = repo.create_or_replace_blob_file(".gitmodules", gitmodules_contents.as_bytes());
+        let commit_id = oid_from_str("1111111111111111111111111111111111111111");
+
+        repo.git_add(&gitmodules);
+        repo.git_add_submodule("sub", commit_id);
+
+        let planned = plan_paths(&repo);
+
+        match planned.as_slice() {
+            [(gitmodules_path, _, PlannedContent::Text(text)), (sub_path, mode_and_type, PlannedContent::Submodule { commit_id: planned_commit_id })] => {
+                assert_eq!(gitmodules_path, ".gitmodules");
+                assert_eq!(text, gitmodules_contents);
+                assert_eq!(sub_path, "sub");
+                assert_eq!(mode_and_type, "Commit Commit");
+                assert_eq!(*planned_commit_id, commit_id);
+            },
+            _ => panic!("Unexpected plan: {:?}", planned),
+        }
+    }
+
+    #[test]
+    fn plan_tree_submodule_removal() {
+        let repo = TempGitRepo::new();
+
+        let gitmodules = repo.create_or_replace_blob_file(".gitmodules", "[submodule \"sub\"]\n\tpath = sub\n".as_bytes());
+
+        repo.git_add(&gitmodules);
+        repo.git_add_submodule("sub", oid_from_str("1111111111111111111111111111111111111111"));
+        repo.git_commit("Add sub");
+
+        repo.git_rm(&gitmodules);
+        repo.git_rm(Path::new("sub"));
+
+        let planned = plan_paths(&repo);
+
+        match planned.as_slice() {
+            [(gitmodules_path, _, PlannedContent::Deletion), (sub_path, _, PlannedContent::Deletion)] => {
+                assert_eq!(gitmodules_path, ".gitmodules");
+                assert_eq!(sub_path, "sub");
+            },
+            _ => panic!("Unexpected plan: {:?}", planned),
+        }
+    }
+
+    #[test]
+    fn submodule_is_referenced_by_commit_sha() {
+        let repo = TempGitRepo::new();
+        let commit_id = oid_from_str("1111111111111111111111111111111111111111");
+
+        repo.git_add_submodule("sub", commit_id);
+
+        let status = git_status(&repo.repo)
+            .expect("Unable to get a git status");
+        let plan = plan_tree(&repo.repo, &status, &HashSet::new())
+            .expect("Unable to plan a tree");
+
+        let github_client = github_client();
+
+        // - There are no blobs to upload, so no requests are made
+        let body = execute_plan("base", plan, 8, &github_client)
+            .expect("Unable to execute the plan");
+
+        let json = serde_json::to_value(&body)
+            .expect("Unable to serialize the request body");
+
+        assert_eq!(json["tree"], serde_json::json!([
+            { "path": "sub", "mode": "160000", "type": "commit", "sha": commit_id.to_string() },
+        ]));
+    }
+
+    fn plan_on_head(repo: &TempGitRepo) -> Vec<(String, PlannedContent)> {
+        let status = git_status(&repo.repo)
+            .expect("Unable to get a git status");
+
+        let head = repo.repo.head()
+            .and_then(|head| head.peel_to_commit())
+            .unwrap_or_else(|e| panic!("Unable to resolve HEAD of repo in {:?}: {}", repo.directory, e));
+
+        let existing_blob_ids = existing_blob_ids(&repo.repo, Some(&head))
+            .expect("Unable to find the existing blobs");
+
+        let plan = plan_tree(&repo.repo, &status, &existing_blob_ids)
+            .expect("Unable to plan a tree");
+
+        let mut planned: Vec<(String, PlannedContent)> = plan.into_iter()
+            .map(|node| (node.path, node.content))
+            .collect();
+        planned.sort_by(|(a, _), (b, _)| a.cmp(b));
+
+        planned
+    }
+
+    #[test]
+    fn plan_tree_references_copied_blobs() {
+        let repo = TempGitRepo::new();
+
+        let foo = repo.create_or_replace_blob_file("foo", &[0x80, 0x81]);
+
+        repo.git_add(&foo);
+        repo.git_commit("Add foo");
+
+        let bar = repo.create_or_replace_blob_file("bar", &[0x80, 0x81]);
+        let baz = repo.create_or_replace_blob_file("baz", &[0x80, 0x82]);
+
+        repo.git_add(&bar);
+        repo.git_add(&baz);
+
+        let foo_id = repo.repo.blob(&[0x80, 0x81])
+            .expect("Unable to hash foo's contents");
+
+        let planned = plan_on_head(&repo);
+
+        match planned.as_slice() {
+            [(bar_path, PlannedContent::Existing { object_id }), (baz_path, PlannedContent::Blob { .. })] => {
+                assert_eq!(bar_path, "bar");
+                assert_eq!(*object_id, foo_id);
+                assert_eq!(baz_path, "baz");
+            },
+            _ => panic!("Unexpected plan: {:?}", planned),
+        }
+    }
+
+    #[test]
+    fn plan_tree_references_reverted_blobs() {
+        let repo = TempGitRepo::new();
+
+        let foo = repo.create_or_replace_blob_file("foo", "before\n".as_bytes());
+
+        repo.git_add(&foo);
+        repo.git_commit("Add foo");
+
+        repo.create_or_replace_blob_file("foo", "after\n".as_bytes());
+        repo.git_add(&foo);
+        repo.git_commit("Change foo");
+
+        repo.create_or_replace_blob_file("foo", "before\n".as_bytes());
+        repo.git_add(&foo);
+
+        let planned = plan_on_head(&repo);
+
+        match planned.as_slice() {
+            [(path, PlannedContent::Existing { .. })] => assert_eq!(path, "foo"),
+            _ => panic!("Unexpected plan: {:?}", planned),
+        }
+    }
+
+    #[test]
+    fn existing_blobs_are_referenced_by_sha() {
+        let repo = TempGitRepo::new();
+
+        let foo = repo.create_or_replace_blob_file("foo", "foo\n".as_bytes());
+
+        repo.git_add(&foo);
+        let head_id = repo.git_commit("Add foo");
+
+        let bar = repo.create_or_replace_blob_file("bar", "foo\n".as_bytes());
+
+        repo.git_add(&bar);
+
+        let head = repo.repo.find_commit(head_id)
+            .unwrap_or_else(|e| panic!("Unable to find commit {}: {}", head_id, e));
+        let existing_blob_ids = existing_blob_ids(&repo.repo, Some(&head))
+            .expect("Unable to find the existing blobs");
+
+        let status = git_status(&repo.repo)
+            .expect("Unable to get a git status");
+        let plan = plan_tree(&repo.repo, &status, &existing_blob_ids)
+            .expect("Unable to plan a tree");
+
+        let github_client = github_client();
+
+        // - Neither the contents nor a blob upload are sent
+        let body = execute_plan("base", plan, 8, &github_client)
+            .expect("Unable to execute the plan");
+
+        let json = serde_json::to_value(&body)
+            .expect("Unable to serialize the request body");
+
+        // printf 'foo\n' | git hash-object --stdin
+        assert_eq!(json["tree"], serde_json::json!([
+            { "path": "bar", "mode": "100644", "type": "blob", "sha": "257cc5642cb1a054f08cc83f2d943e56fd3ebe99" },
+        ]));
+    }
+
+    #[test]
+    fn large_text_is_uploaded_as_a_blob() {
+        let repo = TempGitRepo::new();
+
+        let small = repo.create_or_replace_blob_file("small", "small\n".as_bytes());
+        let large = repo.create_or_replace_blob_file("large", "a".repeat(MAX_INLINE_CONTENT_SIZE + 1).as_bytes());
+
+        repo.git_add(&small);
+        repo.git_add(&large);
+
+        let planned = plan_on_head(&repo);
+
+        match planned.as_slice() {
+            [(large_path, PlannedContent::Blob { contents: ObjectContents::Text(text), size, .. }), (small_path, PlannedContent::Text(_))] => {
+                assert_eq!(large_path, "large");
+                assert_eq!(text.len(), MAX_INLINE_CONTENT_SIZE + 1);
+                assert_eq!(*size, MAX_INLINE_CONTENT_SIZE + 1);
+                assert_eq!(small_path, "small");
+            },
+            _ => panic!("Unexpected plan: {:?}", planned),
+        }
+    }
+
+    #[test]
+    fn files_over_the_size_limit_are_rejected() {
+        let repo = TempGitRepo::new();
+
+        let foo = repo.create_or_replace_blob_file("foo", "existing\n".as_bytes());
+
+        repo.git_add(&foo);
+        repo.git_commit("Add foo");
+
+        let copy = repo.create_or_replace_blob_file("copy", "existing\n".as_bytes());
+        let large = repo.create_or_replace_blob_file("large", "large\n".as_bytes());
+        let larger = repo.create_or_replace_blob_file("larger", &[0x80; 16]);
+        let small = repo.create_or_replace_blob_file("small", "s\n".as_bytes());
+
+        repo.git_add(&copy);
+        repo.git_add(&large);
+        repo.git_add(&larger);
+        repo.git_add(&small);
+        repo.git_rm(&foo);
+
+        let status = git_status(&repo.repo)
+            .expect("Unable to get a git status");
+
+        let head = repo.repo.head()
+            .and_then(|head| head.peel_to_commit())
+            .unwrap_or_else(|e| panic!("Unable to resolve HEAD of repo in {:?}: {}", repo.directory, e));
+        let existing_blob_ids = existing_blob_ids(&repo.repo, Some(&head))
+            .expect("Unable to find the existing blobs");
+
+        // - Blobs the remote already has aren't sent, so they don't count
+        //   against the limit
+        let result = ensure_blobs_within_size_limit(&repo.repo, &status, &existing_blob_ids, 4);
+
+        match result {
+            Err(Error::Multiple { failures, .. }) => {
+                let mut paths: Vec<&str> = failures.iter().map(|(path, _)| path.as_str()).collect();
+                paths.sort();
+
+                assert_eq!(paths, vec!["large", "larger"]);
+                assert!(failures.iter().all(|(_, error)| error.kind() == "unsupported"));
+            },
+            _ => panic!("Expected an error for both large files, but found {:?}", result),
+        }
+
+        ensure_blobs_within_size_limit(&repo.repo, &status, &existing_blob_ids, 16)
+            .expect("Expected files of up to the limit to be accepted");
+    }
+
+    fn tree_node(path: &str, content: &str) -> create_a_tree::TreeNode {
+        create_a_tree::TreeNode {
+            path: path.to_string(),
+            file_mode: create_a_tree::FileMode::Blob,
+            node_type: create_a_tree::NodeType::Blob,
+            sha_or_content: create_a_tree::ShaOrContent::Content(content.to_string()),
+        }
+    }
+
+    #[test]
+    fn request_bodies_are_split_by_size() {
+        let body = create_a_tree::RequestBody {
+            base_tree: "base".to_string(),
+            tree: vec![
+                tree_node("a", &"a".repeat(1000)),
+                tree_node("b", &"b".repeat(1000)),
+                tree_node("c", &"c".repeat(3000)),
+                tree_node("d", "d"),
+            ],
+        };
+
+        let chunks = split_request_body_with_max_size(body, 2500);
+
+        let paths: Vec<Vec<&str>> = chunks.iter()
+            .map(|chunk| chunk.tree.iter().map(|node| node.path.as_str()).collect())
+            .collect();
+
+        // - A node over the limit on its own still gets sent, in a request of
+        //   its own
+        assert_eq!(paths, vec![vec!["a", "b"], vec!["c"], vec!["d"]]);
+        assert!(chunks.iter().all(|chunk| chunk.base_tree == "base"));
+    }
+
+    #[test]
+    fn request_bodies_are_split_by_escaped_size() {
+        let body = create_a_tree::RequestBody {
+            base_tree: "base".to_string(),
+            tree: vec![
+                // - Each quote and control character is escaped, making the
+                //   contents twice and six times as large respectively
+                tree_node("quotes", &"\"".repeat(700)),
+                tree_node("controls", &"\u{1}".repeat(300)),
+                tree_node("plain", "plain"),
+            ],
+        };
+
+        let chunks = split_request_body_with_max_size(body, 2500);
+
+        let paths: Vec<Vec<&str>> = chunks.iter()
+            .map(|chunk| chunk.tree.iter().map(|node| node.path.as_str()).collect())
+            .collect();
+
+        assert_eq!(paths, vec![vec!["quotes"], vec!["controls", "plain"]]);
+
+        for chunk in &chunks {
+            let size = serde_json::to_vec(chunk).unwrap().len();
+            assert!(size <= 2500, "A request of {} bytes is over the limit", size);
+        }
+    }
+
+    #[test]
+    fn empty_request_bodies_are_not_split() {
+        let body = create_a_tree::RequestBody {
+            base_tree: "base".to_string(),
+            tree: Vec::new(),
+        };
+
+        let chunks = split_request_body_with_max_size(body, 2500);
+
+        assert_eq!(chunks.len(), 1);
+        assert!(chunks[0].tree.is_empty());
+    }
+
+    #[test]
+    fn concurrent_uploads_are_bounded_and_keep_their_order() {
+        let items: Vec<usize> = (0..20).collect();
+        let in_flight = AtomicUsize::new(0);
+        let most_in_flight = AtomicUsize::new(0);
+
+        let results = upload_concurrently(&items, 4, |item| {
+            let now_in_flight = in_flight.fetch_add(1, Ordering::SeqCst) + 1;
+            most_in_flight.fetch_max(now_in_flight, Ordering::SeqCst);
+
+            // - Finish out of order so that the results have to be sorted
+            thread::sleep(Duration::from_millis(((20 - item) % 5) as u64));
+            in_flight.fetch_sub(1, Ordering::SeqCst);
+
+            Ok(item * 2)
+        });
+
+        let results: Vec<usize> = results.into_iter().map(|result| result.unwrap()).collect();
+
+        assert_eq!(results, items.iter().map(|item| item * 2).collect::<Vec<usize>>());
+        assert!(most_in_flight.load(Ordering::SeqCst) <= 4);
+    }
+
+    #[test]
+    fn concurrent_uploads_report_every_failure() {
+        let items: Vec<usize> = (0..100).collect();
+        let attempts = AtomicUsize::new(0);
+
+        let results = upload_concurrently(&items, 4, |item| {
+            attempts.fetch_add(1, Ordering::SeqCst);
+
+            match item {
+                3 | 50 => Err(Error::internal(format!("upload {} failed", item))),
+                item => Ok(*item),
+            }
+        });
+
+        assert_eq!(attempts.load(Ordering::SeqCst), 100);
+        assert_eq!(results.len(), 100);
+
+        let failed: Vec<usize> = results.iter()
+            .enumerate()
+            .filter(|(_, result)| result.is_err())
+            .map(|(index, _)| index)
+            .collect();
+
+        assert_eq!(failed, vec![3, 50]);
+        assert_eq!(*results[99].as_ref().unwrap(), 99);
+    }
 }
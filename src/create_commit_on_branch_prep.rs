@@ -0,0 +1,165 @@
+use std::collections::HashSet;
+use std::io::Write;
+
+use base64::write::EncoderStringWriter;
+
+use crate::create_a_tree_prep::{GitCommitAction, MAX_BLOB_SIZE, delta_to_actions, ensure_blobs_within_size_limit};
+use crate::error::{Error, Result};
+use crate::github::graphql_api::create_commit_on_branch;
+use crate::git_status::PathStatus;
+
+/// `createCommitOnBranch` only takes paths and contents, so unlike the REST
+/// API, file modes can't be set. Rather than silently dropping executable
+/// bits, symlinks, and submodules, anything other than a regular file is
+/// rejected.
+fn ensure_regular_file(path_status: &PathStatus) -> Result<()> {
+    match path_status.file_mode {
+        git2::FileMode::Blob => Ok(()),
+        file_mode => Err(Error::unsupported_path(&path_status.path, format!("File mode {:?} is not supported by the GraphQL backend; use the REST backend instead", file_mode))),
+    }
+}
+
+/// Reads the staged blob, like `create_a_tree_prep::read_file`, as the Base64
+/// that `createCommitOnBranch` takes file contents in
+fn read_file_base64(repo: &git2::Repository, path_status: &PathStatus) -> Result<String> {
+    let blob = repo.find_blob(path_status.object_id)
+        .map_err(|e| Error::git_path(format!("find blob {} in repo {:?}", path_status.object_id, repo.path()), &path_status.path, e))?;
+
+    let mut enc = EncoderStringWriter::new(&base64::engine::general_purpose::STANDARD);
+
+    match enc.write_all(blob.content()) {
+        Ok(_) => Ok(enc.into_inner()),
+        Err(e) => Err(Error::git_path(format!("Base64-encode contents of object {}", path_status.object_id), &path_status.path, e)),
+    }
+}
+
+/// The additions and deletions `createCommitOnBranch` takes for the changes in
+/// `git_status`
+///
+/// - Files over GitHub's size limit are rejected up front, like they are for
+///   the REST API, rather than being encoded only for GitHub to reject them
+pub fn generate_file_changes(repo: &git2::Repository, git_status: &[PathStatus]) -> Result<create_commit_on_branch::FileChanges> {
+    // - Every file is sent inline, so none are known to exist already
+    ensure_blobs_within_size_limit(repo, git_status, &HashSet::new(), MAX_BLOB_SIZE)?;
+
+    let mut file_changes = create_commit_on_branch::FileChanges::default();
+
+    for path_status in git_status {
+        for action in delta_to_actions(path_status.delta) {
+            match action {
+                GitCommitAction::AddPath => {
+                    ensure_regular_file(path_status)?;
+
+                    file_changes.additions.push(create_commit_on_branch::FileAddition {
+                        path: path_status.path.clone(),
+                        contents: read_file_base64(repo, path_status)?,
+                    });
+                },
+                GitCommitAction::DeletePath => {
+                    file_changes.deletions.push(create_commit_on_branch::FileDeletion {
+                        path: path_status.path.clone(),
+                    });
+                },
+                GitCommitAction::DeleteOriginalPath => {
+                    let path = match &path_status.original_path {
+                        Some(path) => path.clone(),
+                        None => Err(Error::internal(format!("Expected an original path, but none was found for {:?}", path_status)))?,
+                    };
+
+                    file_changes.deletions.push(create_commit_on_branch::FileDeletion {
+                        path: path,
+                    });
+                },
+                GitCommitAction::Nop => {},
+                GitCommitAction::Unsupported => {
+                    Err(Error::unsupported_path(&path_status.path, format!("Unsupported delta {:?}", path_status.delta)))?
+                },
+            }
+        }
+    }
+
+    Ok(file_changes)
+}
+
+/// Splits a commit message into the headline (the first line) and the body
+/// (everything after the blank lines following the first line), the way
+/// `createCommitOnBranch` expects it
+pub fn split_commit_message(message: &str) -> create_commit_on_branch::CommitMessage {
+    let (headline, body) = match message.split_once('\n') {
+        Some((headline, body)) => (headline, body.trim_start_matches(['\r', '\n'])),
+        None => (message, ""),
+    };
+
+    create_commit_on_branch::CommitMessage {
+        headline: headline.trim_end().to_string(),
+        body: match body.is_empty() {
+            true => None,
+            false => Some(body.to_string()),
+        },
+    }
+}
+
+#[cfg(test)]
+mod create_commit_on_branch_prep_tests {
+    use crate::git_status::git_status;
+    use crate::test_utils::test_utils::TempGitRepo;
+
+    use super::{generate_file_changes, split_commit_message};
+
+    #[test]
+    fn headline_only() {
+        let message = split_commit_message("Add foo");
+
+        assert_eq!(message.headline, "Add foo");
+        assert_eq!(message.body, None);
+    }
+
+    #[test]
+    fn headline_and_body() {
+        let message = split_commit_message("Add foo\n\nFoo is needed\nfor bar\n");
+
+        assert_eq!(message.headline, "Add foo");
+        assert_eq!(message.body.as_deref(), Some("Foo is needed\nfor bar\n"));
+    }
+
+    #[test]
+    fn additions_and_deletions() {
+        let repo = TempGitRepo::new();
+
+        let foo = repo.create_or_replace_blob_file("foo", "foo\n".as_bytes());
+        repo.git_add(&foo);
+        repo.git_commit("Add foo");
+
+        let bar = repo.create_or_replace_blob_file("bar", &[0x80]);
+        repo.git_add(&bar);
+        repo.git_rm(&foo);
+
+        let status = git_status(&repo.repo)
+            .expect("Unable to get a git status");
+
+        let file_changes = generate_file_changes(&repo.repo, &status)
+            .expect("Unable to generate file changes");
+
+        assert_eq!(file_changes.additions.len(), 1);
+        assert_eq!(file_changes.additions[0].path, "bar");
+        // printf '\x80' | base64
+        assert_eq!(file_changes.additions[0].contents, "gA==");
+
+        assert_eq!(file_changes.deletions.len(), 1);
+        assert_eq!(file_changes.deletions[0].path, "foo");
+    }
+
+    #[test]
+    #[cfg(unix)]
+    fn symlinks_are_rejected() {
+        let repo = TempGitRepo::new();
+
+        let bar = repo.create_or_replace_symlink_file("bar", "foo");
+        repo.git_add(&bar);
+
+        let status = git_status(&repo.repo)
+            .expect("Unable to get a git status");
+
+        assert!(generate_file_changes(&repo.repo, &status).is_err());
+    }
+}
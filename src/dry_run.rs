@@ -0,0 +1,205 @@
+use std::fmt::Debug;
+
+use serde::Serialize;
+
+use crate::backend::BackendKind;
+use crate::config::Config;
+use crate::create_a_tree_prep::{PlannedContent, PlannedTreeNode, existing_blob_ids, plan_tree};
+use crate::create_commit_on_branch_prep::generate_file_changes;
+use crate::error::{Error, Result, parse_oid};
+use crate::git_log::unpushed_commits;
+use crate::git_status::{PathStatus, commit_status};
+use crate::identity::{commit_signatures, with_co_author_trailers};
+use crate::lfs::{LfsObject, lfs_objects};
+
+/// Returns the name a value is sent to GitHub as, such as `100644` for
+/// `FileMode::Blob`
+fn serialized_name<T: Serialize + Debug>(value: &T) -> String {
+    match serde_json::to_value(value) {
+        Ok(serde_json::Value::String(name)) => name,
+        _ => format!("{:?}", value),
+    }
+}
+
+/// The remote branch's tip as of the last fetch, according to the local
+/// remote-tracking branch
+///
+/// - The remote itself isn't asked since a dry run makes no network calls
+fn remote_tracking_tip(config: &Config) -> Option<git2::Oid> {
+    let reference_name = format!("refs/remotes/origin/{}", config.git_branch_name);
+
+    config.git_repo.refname_to_id(&reference_name).ok()
+}
+
+fn print_tree_plan(plan: &[PlannedTreeNode]) {
+    println!("Tree entries:");
+
+    for node in plan {
+        let mode = serialized_name(&node.file_mode);
+        let node_type = serialized_name(&node.node_type);
+
+        match &node.content {
+            PlannedContent::Text(text) => println!("  {} {} {} (inline, {} bytes)", mode, node_type, node.path, text.len()),
+            PlannedContent::Blob { object_id, size, .. } => println!("  {} {} {} (blob {}, {} bytes)", mode, node_type, node.path, object_id, size),
+            PlannedContent::Existing { object_id } => println!("  {} {} {} (existing blob {})", mode, node_type, node.path, object_id),
+            PlannedContent::Submodule { commit_id } => println!("  {} {} {} (submodule at {})", mode, node_type, node.path, commit_id),
+            PlannedContent::Deletion => println!("  delete {}", node.path),
+        }
+    }
+
+    let blobs: Vec<&PlannedTreeNode> = plan.iter()
+        .filter(|node| matches!(node.content, PlannedContent::Blob { .. }))
+        .collect();
+
+    println!("Blobs to upload: {}", blobs.len());
+
+    for node in blobs {
+        if let PlannedContent::Blob { object_id, size, .. } = &node.content {
+            println!("  {} {} ({} bytes)", object_id, node.path, size);
+        }
+    }
+}
+
+fn print_lfs_plan(objects: &[LfsObject]) {
+    if objects.is_empty() {
+        return
+    }
+
+    println!("Git LFS objects to upload, unless GitHub already has them: {}", objects.len());
+
+    for object in objects {
+        println!("  {} {} ({} bytes)", object.oid, object.path, object.size);
+    }
+}
+
+fn print_signatures_plan(config: &Config, local_commit: Option<&git2::Commit>) -> Result<()> {
+    let signatures = commit_signatures(&config.commit_identity, &config.git_repo, local_commit)?;
+
+    for (role, signature) in [("Author", signatures.author), ("Committer", signatures.committer)] {
+        if let Some(signature) = signature {
+            println!("{}: {} <{}> ({})", role, signature.name, signature.email, signature.date.as_deref().unwrap_or("now"));
+        }
+    }
+
+    Ok(())
+}
+
+fn print_branch_plan(config: &Config, remote_tip: Option<git2::Oid>) {
+    match remote_tip {
+        Some(tip) if config.git_should_force_push => println!("Branch {:?} would be force-updated from {} (as of the last fetch)", config.git_branch_name, tip),
+        Some(tip) => println!("Branch {:?} would be updated from {} (as of the last fetch)", config.git_branch_name, tip),
+        // - Without a remote-tracking branch, the branch may still exist on the
+        //   remote; it just hasn't been fetched
+        None => println!("Branch {:?}: remote state unknown (not fetched)", config.git_branch_name),
+    }
+}
+
+fn print_sync_plan(config: &Config) {
+    if config.git_should_sync_local {
+        println!("Local branch {:?} would be moved to the remote commit", config.git_branch_name);
+    }
+}
+
+fn print_pull_request_plan(config: &Config) {
+    if let Some(options) = &config.pull_request {
+        let base = match &options.base {
+            Some(base) => format!("{:?}", base),
+            None => "the repository's default branch".to_string(),
+        };
+
+        println!("A pull request from {:?} into {} would be opened, or the open one updated", config.git_branch_name, base);
+    }
+}
+
+/// Prints what committing the staged changes would do without making any
+/// network calls
+pub fn print_commit_plan(config: &Config, status: &[PathStatus]) -> Result<()> {
+    // - Check that the GraphQL backend can represent the changes at all
+    if config.commit_backend == BackendKind::GraphQl {
+        generate_file_changes(&config.git_repo, status)?;
+
+        if config.commit_identity.is_custom() {
+            Err(Error::unsupported("Setting the author or committer is not supported by the GraphQL backend; use the REST backend or --co-author instead"))?
+        }
+    }
+
+    let remote_tip = remote_tracking_tip(config);
+
+    // - Following the remote branch parents the commit on its tip, which may
+    //   not have the local base's blobs; see `backend::known_remote_commit`
+    let known_remote_commit_id = match remote_tip {
+        Some(tip) if config.git_should_follow_remote_branch && !config.git_should_force_push => tip,
+        _ => parse_oid(&config.git_base_object_id)?,
+    };
+    let known_remote_commit = config.git_repo.find_commit(known_remote_commit_id)
+        .map_err(|e| Error::git(format!("find commit {}", known_remote_commit_id), e))?;
+    let lfs_objects = lfs_objects(&config.git_repo, status)?;
+    let plan = plan_tree(&config.git_repo, status, &existing_blob_ids(&config.git_repo, Some(&known_remote_commit))?)?;
+
+    println!("Dry run: nothing will be sent to GitHub");
+    println!("Backend: {:?}", config.commit_backend);
+    println!("Message: {:?}", with_co_author_trailers(&config.commit_message, &config.commit_identity.co_authors));
+    print_signatures_plan(config, None)?;
+
+    match remote_tip {
+        Some(tip) if config.git_should_follow_remote_branch && !config.git_should_force_push => {
+            println!("Parent: {}", tip);
+            println!("  - The remote branch exists, so the changes would be committed on top of it if it didn't change the same paths since the base");
+        },
+        _ => {
+            println!("Parent: {}", config.git_base_object_id);
+
+            if let Some(tip) = remote_tip {
+                let remote_is_ahead = config.git_repo.graph_descendant_of(tip, parse_oid(&config.git_base_object_id)?).unwrap_or(false);
+
+                if remote_is_ahead && !config.git_should_force_push {
+                    println!("  - The remote branch is ahead at {}, so the changes would be rebased onto it if it didn't change the same paths", tip);
+                }
+            }
+        },
+    }
+
+    print_lfs_plan(&lfs_objects);
+    print_tree_plan(&plan);
+    print_branch_plan(config, remote_tip);
+    print_sync_plan(config);
+    print_pull_request_plan(config);
+
+    Ok(())
+}
+
+/// Prints what replaying the unpushed commits would do without making any
+/// network calls
+pub fn print_replay_plan(config: &Config) -> Result<()> {
+    let repo = &config.git_repo;
+
+    let remote_tip = remote_tracking_tip(config)
+        .ok_or_else(|| Error::conflict(format!("Branch {:?} has no remote-tracking branch to replay commits onto; fetch it first", config.git_branch_name)))?;
+
+    let commit_ids = unpushed_commits(repo, parse_oid(&config.git_head_object_id)?, remote_tip)?;
+
+    println!("Dry run: nothing will be sent to GitHub");
+    println!("Commits to replay onto {}: {}", remote_tip, commit_ids.len());
+
+    for commit_id in commit_ids {
+        let commit = repo.find_commit(commit_id)
+            .map_err(|e| Error::git(format!("find commit {}", commit_id), e))?;
+        let status = commit_status(repo, &commit)?;
+        let local_parent = commit.parent(0).ok();
+        let lfs_objects = lfs_objects(repo, &status)?;
+        let plan = plan_tree(repo, &status, &existing_blob_ids(repo, local_parent.as_ref())?)?;
+
+        println!();
+        println!("Commit {}: {:?}", commit_id, commit.summary().unwrap_or_default());
+        print_signatures_plan(config, Some(&commit))?;
+        print_lfs_plan(&lfs_objects);
+        print_tree_plan(&plan);
+    }
+
+    println!();
+    print_branch_plan(config, Some(remote_tip));
+    print_sync_plan(config);
+    print_pull_request_plan(config);
+
+    Ok(())
+}
@@ -0,0 +1,290 @@
+use std::fmt;
+use std::time::Duration;
+
+/// Everything that can go wrong while committing through GitHub
+///
+/// Variants are grouped by what a caller would do about them: fix the
+/// configuration, fix the repository, retry later, fetch and try again, and so
+/// on. Each group has its own process exit code.
+#[derive(Debug)]
+pub enum Error {
+    /// The command line arguments were invalid, or help or version
+    /// information was requested
+    Usage(clap::Error),
+    /// The environment variables or repository configuration were invalid
+    Config {
+        message: String,
+    },
+    /// Nothing was staged
+    NoChanges,
+    /// Reading from or writing to the local repository failed
+    Git {
+        operation: String,
+        path: Option<String>,
+        message: String,
+    },
+    /// The change can't be represented by GitHub's APIs or the chosen backend
+    Unsupported {
+        path: Option<String>,
+        message: String,
+    },
+    /// Credentials were missing, invalid, or rejected
+    Auth {
+        message: String,
+    },
+    /// An API responded with a status code other than the expected one
+    Api {
+        operation: String,
+        status: u16,
+        body: String,
+    },
+    /// An API responded as expected, but with a body that couldn't be read
+    InvalidResponse {
+        operation: String,
+        message: String,
+        body: String,
+    },
+    /// The GraphQL API reported errors
+    GraphQl {
+        operation: String,
+        message: String,
+    },
+    /// A rate limit was hit and didn't reset in time to retry
+    RateLimited {
+        operation: String,
+        status: u16,
+        body: String,
+        retry_after: Option<Duration>,
+    },
+    /// The remote branch moved in a way that can't be reconciled automatically
+    Conflict {
+        message: String,
+    },
+    /// A request couldn't be sent or its response couldn't be received
+    Transport {
+        operation: String,
+        message: String,
+    },
+    /// Reading or writing a file outside of the repository failed
+    Io {
+        path: String,
+        message: String,
+    },
+    /// Something that shouldn't be possible happened
+    Internal {
+        message: String,
+    },
+    /// Several operations that ran concurrently failed, such as uploading
+    /// blobs, each for its own path
+    ///
+    /// - The kind and exit code are the first failure's
+    Multiple {
+        operation: String,
+        failures: Vec<(String, Error)>,
+    },
+}
+
+pub type Result<T> = std::result::Result<T, Error>;
+
+/// Parses a SHA, such as one from the configuration or from an API response,
+/// into a git object ID
+pub(crate) fn parse_oid(sha: &str) -> Result<git2::Oid> {
+    git2::Oid::from_str(sha)
+        .map_err(|e| Error::git(format!("parse {:?} as a git object ID", sha), e))
+}
+
+impl Error {
+    pub fn config(message: impl Into<String>) -> Error {
+        Error::Config {
+            message: message.into(),
+        }
+    }
+
+    pub fn git(operation: impl Into<String>, error: impl fmt::Display) -> Error {
+        Error::Git {
+            operation: operation.into(),
+            path: None,
+            message: error.to_string(),
+        }
+    }
+
+    pub fn git_path(operation: impl Into<String>, path: &str, error: impl fmt::Display) -> Error {
+        Error::Git {
+            operation: operation.into(),
+            path: Some(path.to_string()),
+            message: error.to_string(),
+        }
+    }
+
+    pub fn unsupported(message: impl Into<String>) -> Error {
+        Error::Unsupported {
+            path: None,
+            message: message.into(),
+        }
+    }
+
+    pub fn unsupported_path(path: &str, message: impl Into<String>) -> Error {
+        Error::Unsupported {
+            path: Some(path.to_string()),
+            message: message.into(),
+        }
+    }
+
+    pub fn auth(message: impl Into<String>) -> Error {
+        Error::Auth {
+            message: message.into(),
+        }
+    }
+
+    pub fn conflict(message: impl Into<String>) -> Error {
+        Error::Conflict {
+            message: message.into(),
+        }
+    }
+
+    pub fn internal(message: impl Into<String>) -> Error {
+        Error::Internal {
+            message: message.into(),
+        }
+    }
+
+    /// A stable, machine-readable name for the kind of error
+    pub fn kind(&self) -> &'static str {
+        match self {
+            Error::Usage(_) => "usage",
+            Error::Config { .. } => "config",
+            Error::NoChanges => "no_changes",
+            Error::Git { .. } => "git",
+            Error::Unsupported { .. } => "unsupported",
+            Error::Auth { .. } => "auth",
+            Error::Api { .. } => "api",
+            Error::InvalidResponse { .. } => "invalid_response",
+            Error::GraphQl { .. } => "graphql",
+            Error::RateLimited { .. } => "rate_limited",
+            Error::Conflict { .. } => "conflict",
+            Error::Transport { .. } => "transport",
+            Error::Io { .. } => "io",
+            Error::Internal { .. } => "internal",
+            Error::Multiple { failures, .. } => failures.first().map_or("internal", |(_, error)| error.kind()),
+        }
+    }
+
+    /// The process exit code for the error's class
+    ///
+    /// - Usage errors use clap's exit codes, which are 0 for help and version
+    ///   information and 2 otherwise
+    pub fn exit_code(&self) -> i32 {
+        match self {
+            Error::Usage(e) => e.exit_code(),
+            Error::Internal { .. } => 1,
+            Error::Config { .. } => 3,
+            Error::NoChanges => 4,
+            Error::Git { .. } | Error::Io { .. } => 5,
+            Error::Unsupported { .. } => 6,
+            Error::Auth { .. } => 7,
+            Error::Api { .. } | Error::InvalidResponse { .. } | Error::GraphQl { .. } => 8,
+            Error::RateLimited { .. } => 9,
+            Error::Conflict { .. } => 10,
+            Error::Transport { .. } => 11,
+            Error::Multiple { failures, .. } => failures.first().map_or(1, |(_, error)| error.exit_code()),
+        }
+    }
+}
+
+impl fmt::Display for Error {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Error::Usage(e) => write!(f, "{}", e.render()),
+            Error::Config { message } => write!(f, "{}", message),
+            Error::NoChanges => write!(f, "No changes to commit"),
+            Error::Git { operation, path: Some(path), message } => write!(f, "Unable to {} for path {:?}: {}", operation, path, message),
+            Error::Git { operation, path: None, message } => write!(f, "Unable to {}: {}", operation, message),
+            Error::Unsupported { path: Some(path), message } => write!(f, "Path {:?} is not supported: {}", path, message),
+            Error::Unsupported { path: None, message } => write!(f, "{}", message),
+            Error::Auth { message } => write!(f, "{}", message),
+            Error::Api { operation, status, body } => write!(f, "Unexpected status code {} while trying to {}: {}", status, operation, body),
+            Error::InvalidResponse { operation, message, body } => write!(f, "Unable to read response while trying to {}: {}: {}", operation, message, body),
+            Error::GraphQl { operation, message } => write!(f, "GraphQL errors while trying to {}: {}", operation, message),
+            Error::RateLimited { operation, status, body, retry_after: Some(retry_after) } => write!(f, "Rate limited with status code {} while trying to {}, retry after {}s: {}", status, operation, retry_after.as_secs(), body),
+            Error::RateLimited { operation, status, body, retry_after: None } => write!(f, "Rate limited with status code {} while trying to {}: {}", status, operation, body),
+            Error::Conflict { message } => write!(f, "{}", message),
+            Error::Transport { operation, message } => write!(f, "Request failed while trying to {}: {}", operation, message),
+            Error::Io { path, message } => write!(f, "Unable to access {:?}: {}", path, message),
+            Error::Internal { message } => write!(f, "{}", message),
+            Error::Multiple { operation, failures } => {
+                write!(f, "Unable to {} for {} paths", operation, failures.len())?;
+
+                for (path, error) in failures {
+                    write!(f, "\n  - {:?}: {}", path, error)?;
+                }
+
+                Ok(())
+            },
+        }
+    }
+}
+
+impl std::error::Error for Error {}
+
+#[cfg(test)]
+mod error_tests {
+    use super::Error;
+
+    #[test]
+    fn api_error_display() {
+        let error = Error::Api {
+            operation: "create a tree".to_string(),
+            status: 422,
+            body: r#"{"message":"Invalid tree info"}"#.to_string(),
+        };
+
+        assert_eq!(error.to_string(), r#"Unexpected status code 422 while trying to create a tree: {"message":"Invalid tree info"}"#);
+        assert_eq!(error.kind(), "api");
+    }
+
+    #[test]
+    fn git_path_error_display() {
+        let error = Error::git_path("find object 1234", "foo", "object not found");
+
+        assert_eq!(error.to_string(), r#"Unable to find object 1234 for path "foo": object not found"#);
+    }
+
+    #[test]
+    fn multiple_error_display() {
+        let error = Error::Multiple {
+            operation: "create a blob".to_string(),
+            failures: vec![
+                ("foo.png".to_string(), Error::Transport { operation: "send POST /git/blobs".to_string(), message: "reset".to_string() }),
+                ("bar.png".to_string(), Error::Api { operation: "create a blob".to_string(), status: 500, body: "".to_string() }),
+            ],
+        };
+
+        assert_eq!(error.to_string(), "Unable to create a blob for 2 paths\n  - \"foo.png\": Request failed while trying to send POST /git/blobs: reset\n  - \"bar.png\": Unexpected status code 500 while trying to create a blob: ");
+        assert_eq!(error.kind(), "transport");
+        assert_eq!(error.exit_code(), 11);
+    }
+
+    #[test]
+    fn exit_codes_differ_per_class() {
+        let errors = [
+            Error::internal("poisoned"),
+            Error::config("missing"),
+            Error::NoChanges,
+            Error::git("walk commits", "broken"),
+            Error::unsupported("merge commit"),
+            Error::auth("bad key"),
+            Error::Api { operation: "x".to_string(), status: 500, body: "".to_string() },
+            Error::RateLimited { operation: "x".to_string(), status: 429, body: "".to_string(), retry_after: None },
+            Error::conflict("moved"),
+            Error::Transport { operation: "x".to_string(), message: "reset".to_string() },
+        ];
+
+        let mut exit_codes: Vec<i32> = errors.iter().map(|error| error.exit_code()).collect();
+        exit_codes.sort();
+        exit_codes.dedup();
+
+        assert_eq!(exit_codes.len(), errors.len());
+        assert!(!exit_codes.contains(&0));
+        assert!(!exit_codes.contains(&2));
+    }
+}
@@ -0,0 +1,116 @@
+use git2::{Oid, Repository, Sort};
+
+use crate::error::{Error, Result};
+
+/// Returns the commits reachable from `head` that aren't reachable from
+/// `remote_tip`, oldest first, so that they can be replayed in order on top of
+/// `remote_tip`
+///
+/// The commits must form a single linear chain starting at `remote_tip`, which
+/// rules out merge commits and a local branch that has diverged from the
+/// remote branch.
+pub fn unpushed_commits(repo: &Repository, head: Oid, remote_tip: Oid) -> Result<Vec<Oid>> {
+    if head == remote_tip {
+        return Ok(vec![])
+    }
+
+    if repo.find_commit(remote_tip).is_err() {
+        Err(Error::conflict(format!("Remote branch tip {} is not present in the local repository; fetch it before replaying commits", remote_tip)))?
+    }
+
+    let is_descendant = repo.graph_descendant_of(head, remote_tip)
+        .map_err(|e| Error::git(format!("determine whether {} descends from {}", head, remote_tip), e))?;
+
+    if !is_descendant {
+        Err(Error::conflict(format!("Local commit {} does not descend from remote branch tip {}; the branches have diverged", head, remote_tip)))?
+    }
+
+    let mut revwalk = repo.revwalk()
+        .map_err(|e| Error::git("walk commits", e))?;
+
+    revwalk.set_sorting(Sort::TOPOLOGICAL | Sort::REVERSE)
+        .map_err(|e| Error::git("sort commits", e))?;
+    revwalk.push(head)
+        .map_err(|e| Error::git(format!("walk commits from {}", head), e))?;
+    revwalk.hide(remote_tip)
+        .map_err(|e| Error::git(format!("exclude commits reachable from {}", remote_tip), e))?;
+
+    let mut commits = vec![];
+    let mut expected_parent = remote_tip;
+
+    for maybe_oid in revwalk {
+        let oid = maybe_oid
+            .map_err(|e| Error::git("walk commits", e))?;
+        let commit = repo.find_commit(oid)
+            .map_err(|e| Error::git(format!("find commit {}", oid), e))?;
+
+        let parent_ids: Vec<Oid> = commit.parent_ids().collect();
+
+        if parent_ids != [expected_parent] {
+            Err(Error::unsupported(format!("Commit {} is not a linear descendant of {}; merge commits are not supported", oid, expected_parent)))?
+        }
+
+        commits.push(oid);
+        expected_parent = oid;
+    }
+
+    Ok(commits)
+}
+
+#[cfg(test)]
+mod git_log_tests {
+    use crate::test_utils::test_utils::TempGitRepo;
+
+    use super::unpushed_commits;
+
+    #[test]
+    fn linear_commits_oldest_first() {
+        let repo = TempGitRepo::new();
+
+        let foo = repo.create_or_replace_blob_file("foo", "foo\n".as_bytes());
+        repo.git_add(&foo);
+        let base = repo.git_commit("Add foo");
+
+        let bar = repo.create_or_replace_blob_file("bar", "bar\n".as_bytes());
+        repo.git_add(&bar);
+        let first = repo.git_commit("Add bar");
+
+        let baz = repo.create_or_replace_blob_file("baz", "baz\n".as_bytes());
+        repo.git_add(&baz);
+        let second = repo.git_commit("Add baz");
+
+        let actual = unpushed_commits(&repo.repo, second, base)
+            .expect("Unable to list unpushed commits");
+
+        assert_eq!(actual, vec![first, second]);
+    }
+
+    #[test]
+    fn nothing_unpushed() {
+        let repo = TempGitRepo::new();
+
+        let foo = repo.create_or_replace_blob_file("foo", "foo\n".as_bytes());
+        repo.git_add(&foo);
+        let head = repo.git_commit("Add foo");
+
+        let actual = unpushed_commits(&repo.repo, head, head)
+            .expect("Unable to list unpushed commits");
+
+        assert!(actual.is_empty());
+    }
+
+    #[test]
+    fn remote_ahead_of_local() {
+        let repo = TempGitRepo::new();
+
+        let foo = repo.create_or_replace_blob_file("foo", "foo\n".as_bytes());
+        repo.git_add(&foo);
+        let older = repo.git_commit("Add foo");
+
+        let bar = repo.create_or_replace_blob_file("bar", "bar\n".as_bytes());
+        repo.git_add(&bar);
+        let newer = repo.git_commit("Add bar");
+
+        assert!(unpushed_commits(&repo.repo, older, newer).is_err());
+    }
+}
@@ -1,4 +1,8 @@
-use git2::{Delta, DiffOptions, FileMode, Index, ObjectType, Oid, Repository};
+use std::path::Path;
+
+use git2::{Commit, Delta, Diff, DiffOptions, FileMode, Index, ObjectType, Oid, Repository};
+
+use crate::error::{Error, Result};
 
 #[derive(Debug, Eq, PartialEq)]
 pub struct PathStatus {
@@ -10,40 +14,45 @@ pub struct PathStatus {
     pub path: String,
 }
 
+/// Which changes besides the staged ones to commit
+#[derive(Clone, Debug, Default)]
+pub struct StatusOptions {
+    /// Include modified and deleted tracked files from the working directory,
+    /// like `git commit -a`
+    pub include_tracked_changes: bool,
+    /// Include new files from the working directory that aren't ignored
+    pub include_untracked: bool,
+    /// Only commit changes to paths matching these pathspecs, relative to the
+    /// repository root; everything when empty
+    pub pathspecs: Vec<String>,
+}
+
 /// Currently conflicts are not supported. Should that change in the future,
 /// this function would likely be removed and other changes would need to be
 /// made to accommodate.
 ///
 /// https://git-scm.com/docs/revisions/2.39.3#Documentation/revisions.txt-emltngtltpathgtemegem0READMEememREADMEem
-fn stage_number(index: &Index) -> Result<i32, String> {
+fn stage_number(index: &Index) -> Result<i32> {
     if index.has_conflicts() {
-        Err("Handling conflicts is not supported, and conflicts were detected".to_owned())
+        Err(Error::unsupported("Handling conflicts is not supported, and conflicts were detected"))
     } else {
         Ok(0)
     }
 }
 
-pub fn git_status(repo: &Repository) -> Result<Vec<PathStatus>, String> {
-    let index = repo.index()
-        .map_err(|e| format!("Unable to read git index: {}", e))?;
-
-    let stage_number = stage_number(&index)?;
-
-    let head = repo.head()
-        .map_err(|e| format!("Unable to read git head: {}", e))?;
-    let head_tree = head.peel_to_tree()
-        .map_err(|e| format!("Unable to peel git head to tree: {}", e))?;
-
-    let mut diff_options = DiffOptions::new();
-    diff_options.include_typechange(true);
-    diff_options.include_typechange_trees(true);
-
-    let diff = repo.diff_tree_to_index(
-        Some(&head_tree),
-        Some(&index),
-        Some(&mut diff_options),
-    ).map_err(|e| format!("Unable to create diff between head tree and index: {}", e))?;
+fn path_to_string(path: &Path) -> Result<String> {
+    match path.to_str() {
+        Some(path_str) => Ok(path_str.to_owned()),
+        None => Err(Error::unsupported_path(&path.to_string_lossy(), "Path could not be converted to a string since it isn't valid UTF-8")),
+    }
+}
 
+/// Converts each delta of a diff into a `PathStatus`, with `object_type`
+/// deciding the object type of the new side of the delta
+fn diff_to_path_statuses<F>(diff: &Diff, object_type: F) -> Result<Vec<PathStatus>>
+where
+    F: Fn(&Path) -> Result<Option<ObjectType>>,
+{
     let mut changes: Vec<PathStatus> = vec![];
 
     for diff_delta in diff.deltas() {
@@ -54,33 +63,14 @@ pub fn git_status(repo: &Repository) -> Result<Vec<PathStatus>, String> {
         let object_id = new_file.id();
 
         let new_path = new_file.path()
-            .ok_or_else(|| format!("Delta is missing path: {:?}", diff_delta))?;
+            .ok_or_else(|| Error::git("read path of delta", format!("Delta is missing path: {:?}", diff_delta)))?;
 
-        let path_string = match new_path.to_str() {
-            Some(path_str) => path_str.to_owned(),
-            None => Err(format!("Path could not be converted to a string: {:?}", new_path))?,
-        };
+        let path_string = path_to_string(new_path)?;
 
-        let object_type = match index.get_path(new_path, stage_number) {
-            Some(index_entry) => {
-                let object = repo.find_object(index_entry.id, None);
-
-                match object {
-                    Ok(object) => object.kind(),
-                    Err(_) => Err(format!("Unable to find object with ID {}", index_entry.id))?,
-                }
-            },
-            // - Deleted files will not have a tree entry
-            None => None,
-        };
+        let object_type = object_type(new_path)?;
 
         let original_path = match diff_delta.old_file().path() {
-            Some(path) => {
-                match path.to_str() {
-                    Some(path_str) => Some(path_str.to_owned()),
-                    None => Err(format!("Path could not be converted to a string: {:?}", path))?,
-                }
-            }
+            Some(path) => Some(path_to_string(path)?),
             None => None,
         };
 
@@ -99,12 +89,163 @@ pub fn git_status(repo: &Repository) -> Result<Vec<PathStatus>, String> {
     Ok(changes)
 }
 
+fn diff_options() -> DiffOptions {
+    let mut diff_options = DiffOptions::new();
+    diff_options.include_typechange(true);
+    diff_options.include_typechange_trees(true);
+
+    diff_options
+}
+
+/// Adds working directory changes to `index`, which writes their blobs to the
+/// object database the same way `git add` would
+///
+/// - The index is never written back to disk, so the changes aren't staged
+///   as far as git is concerned
+fn add_working_directory_changes(repo: &Repository, index: &mut Index, options: &StatusOptions) -> Result<()> {
+    // - No pathspecs match every path
+    let pathspecs = &options.pathspecs;
+
+    if options.include_tracked_changes {
+        index.update_all(pathspecs, None)
+            .map_err(|e| Error::git("add tracked changes from the working directory", e))?;
+    }
+
+    if options.include_untracked {
+        let mut status_options = git2::StatusOptions::new();
+        status_options.include_untracked(true);
+        status_options.recurse_untracked_dirs(true);
+        status_options.include_ignored(false);
+
+        for pathspec in pathspecs {
+            status_options.pathspec(pathspec);
+        }
+
+        let statuses = repo.statuses(Some(&mut status_options))
+            .map_err(|e| Error::git("list untracked files in the working directory", e))?;
+
+        for entry in statuses.iter().filter(|entry| entry.status().contains(git2::Status::WT_NEW)) {
+            let path = entry.path()
+                .ok_or_else(|| Error::unsupported_path(&String::from_utf8_lossy(entry.path_bytes()), "Path could not be converted to a string since it isn't valid UTF-8"))?;
+
+            index.add_path(Path::new(path))
+                .map_err(|e| Error::git_path("add an untracked file from the working directory", path, e))?;
+        }
+    }
+
+    Ok(())
+}
+
+pub fn git_status(repo: &Repository) -> Result<Vec<PathStatus>> {
+    let head = repo.head()
+        .map_err(|e| Error::git("read git head", e))?;
+    let head_commit = head.peel_to_commit()
+        .map_err(|e| Error::git("peel git head to commit", e))?;
+
+    git_status_with(repo, head_commit.id(), &StatusOptions::default())
+}
+
+/// Like `git_status`, but for the changes between `base_commit_id` and the
+/// index rather than between HEAD and the index, optionally including changes
+/// from the working directory and limited to some paths
+///
+/// - The index's tree is what gets committed on top of the base, so anything
+///   HEAD has that the base doesn't is part of the changes
+pub fn git_status_with(repo: &Repository, base_commit_id: Oid, options: &StatusOptions) -> Result<Vec<PathStatus>> {
+    // - `repo.index()` is shared by everything using `repo`, so working
+    //   directory changes are added to the index of a separate handle instead,
+    //   where nothing else sees them
+    let index_repo = match options.include_tracked_changes || options.include_untracked {
+        true => Some(Repository::open(repo.path()).map_err(|e| Error::git(format!("open repo {:?}", repo.path()), e))?),
+        false => None,
+    };
+
+    let mut index = index_repo.as_ref().unwrap_or(repo).index()
+        .map_err(|e| Error::git("read git index", e))?;
+
+    let stage_number = stage_number(&index)?;
+
+    add_working_directory_changes(repo, &mut index, options)?;
+
+    let mut diff_options = diff_options();
+
+    for pathspec in &options.pathspecs {
+        diff_options.pathspec(pathspec);
+    }
+
+    let base_tree = repo.find_commit(base_commit_id)
+        .and_then(|commit| commit.tree())
+        .map_err(|e| Error::git(format!("read tree of commit {}", base_commit_id), e))?;
+
+    let diff = repo.diff_tree_to_index(
+        Some(&base_tree),
+        Some(&index),
+        Some(&mut diff_options),
+    ).map_err(|e| Error::git(format!("create diff between tree of commit {} and index", base_commit_id), e))?;
+
+    diff_to_path_statuses(&diff, |new_path| {
+        match index.get_path(new_path, stage_number) {
+            // - A submodule's commit lives in the submodule's repository
+            //   rather than this one, so it can't be looked up
+            Some(index_entry) if index_entry.mode == u32::from(FileMode::Commit) => Ok(Some(ObjectType::Commit)),
+            Some(index_entry) => {
+                let object = repo.find_object(index_entry.id, None);
+
+                match object {
+                    Ok(object) => Ok(object.kind()),
+                    Err(e) => Err(Error::git_path(format!("find object with ID {}", index_entry.id), &new_path.to_string_lossy(), e)),
+                }
+            },
+            // - Deleted files will not have a tree entry
+            None => Ok(None),
+        }
+    })
+}
+
+/// Like `git_status`, but for the changes a commit introduced on top of its
+/// parent rather than the changes staged on top of HEAD
+///
+/// Merge commits are not supported since there would be more than one set of
+/// changes.
+pub fn commit_status(repo: &Repository, commit: &Commit) -> Result<Vec<PathStatus>> {
+    let parent_tree = match commit.parent_count() {
+        0 => None,
+        1 => {
+            let parent = commit.parent(0)
+                .map_err(|e| Error::git(format!("read parent of commit {}", commit.id()), e))?;
+            let parent_tree = parent.tree()
+                .map_err(|e| Error::git(format!("read tree of commit {}", parent.id()), e))?;
+
+            Some(parent_tree)
+        },
+        parent_count => Err(Error::unsupported(format!("Commit {} has {} parents, but merge commits are not supported", commit.id(), parent_count)))?,
+    };
+
+    let commit_tree = commit.tree()
+        .map_err(|e| Error::git(format!("read tree of commit {}", commit.id()), e))?;
+
+    let diff = repo.diff_tree_to_tree(
+        parent_tree.as_ref(),
+        Some(&commit_tree),
+        Some(&mut diff_options()),
+    ).map_err(|e| Error::git(format!("create diff between commit {} and its parent", commit.id()), e))?;
+
+    diff_to_path_statuses(&diff, |new_path| {
+        match commit_tree.get_path(new_path) {
+            Ok(tree_entry) => Ok(tree_entry.kind()),
+            // - Deleted files will not have a tree entry
+            Err(_) => Ok(None),
+        }
+    })
+}
+
 #[cfg(test)]
+#[allow(clippy::bool_assert_comparison, clippy::needless_borrow, clippy::ptr_arg)]
 mod git_status_tests {
     use git2::FileMode;
 
-    use crate::test_utils::test_utils::{DELETED_FILE_OID, TempGitRepo, git_hash_object_stdin, path_to_str};
-    use super::{PathStatus, git_status};
+    use crate::test_utils::test_utils::{DELETED_FILE_OID, TempGitRepo, git_hash_object_stdin, oid_from_str, path_to_str};
+    use super::{PathStatus, StatusOptions, commit_status, git_status, git_status_with};
 
     /// This is O(n^2). Since inputs are small, this shouldn't be an issue, but
     /// if it becomes an issue, consider implementing the traits necessary so
@@ -385,4 +526,239 @@ mod git_status_tests {
 
         assert_eq_order_independent(&actual, &expected);
     }
+
+    #[test]
+    fn submodule_update() {
+        let repo = TempGitRepo::new();
+
+        // - Neither commit exists in the superproject, just like a real
+        //   submodule's commits
+        let old_commit_id = oid_from_str("1111111111111111111111111111111111111111");
+        let new_commit_id = oid_from_str("2222222222222222222222222222222222222222");
+
+        repo.git_add_submodule("sub", old_commit_id);
+        repo.git_commit("Add sub");
+
+        repo.git_add_submodule("sub", new_commit_id);
+
+        let actual = git_status(&repo.repo)
+            .expect("Unable to get a git status");
+
+        let expected = vec![
+            PathStatus {
+                delta: git2::Delta::Modified,
+                file_mode: FileMode::Commit,
+                object_id: new_commit_id,
+                object_type: Some(git2::ObjectType::Commit),
+                original_path: Some("sub".to_owned()),
+                path: "sub".to_owned(),
+            },
+        ];
+
+        assert_eq!(actual, expected);
+    }
+
+    #[test]
+    fn commit_changes() {
+        let repo = TempGitRepo::new();
+
+        let foo = repo.create_or_replace_blob_file("foo", "foo\n".as_bytes());
+        let bar = repo.create_or_replace_blob_file("bar", "bar\n".as_bytes());
+
+        repo.git_add(&foo);
+        repo.git_add(&bar);
+        repo.git_commit("Adding foo and bar");
+
+        let foo_contents = "foo\nfoo\n";
+        let foo = repo.create_or_replace_blob_file("foo", foo_contents.as_bytes());
+
+        let baz_contents = "baz\n";
+        let baz = repo.create_or_replace_blob_file("baz", baz_contents.as_bytes());
+
+        repo.git_add(&foo);
+        repo.git_add(&baz);
+        repo.git_rm(&bar);
+        let commit_oid = repo.git_commit("Modifying foo, adding baz, and removing bar");
+
+        // - Changes staged after the commit should not show up
+        let quux = repo.create_or_replace_blob_file("quux", "quux\n".as_bytes());
+        repo.git_add(&quux);
+
+        let commit = repo.repo.find_commit(commit_oid)
+            .expect("Unable to find commit");
+
+        let actual = commit_status(&repo.repo, &commit)
+            .expect("Unable to get a commit status");
+
+        let expected = {
+            let foo_path = path_to_str(&foo);
+            let bar_path = path_to_str(&bar);
+            let baz_path = path_to_str(&baz);
+
+            vec![
+                PathStatus {
+                    delta: git2::Delta::Modified,
+                    file_mode: FileMode::Blob,
+                    object_id: git_hash_object_stdin(foo_contents),
+                    object_type: Some(git2::ObjectType::Blob),
+                    original_path: Some(foo_path.to_owned()),
+                    path: foo_path.to_owned(),
+                },
+                PathStatus {
+                    delta: git2::Delta::Deleted,
+                    file_mode: FileMode::Unreadable,
+                    object_id: *DELETED_FILE_OID,
+                    object_type: None,
+                    original_path: Some(bar_path.to_owned()),
+                    path: bar_path.to_owned(),
+                },
+                PathStatus {
+                    delta: git2::Delta::Added,
+                    file_mode: FileMode::Blob,
+                    object_id: git_hash_object_stdin(baz_contents),
+                    object_type: Some(git2::ObjectType::Blob),
+                    original_path: Some(baz_path.to_owned()),
+                    path: baz_path.to_owned(),
+                },
+            ]
+        };
+
+        assert_eq_order_independent(&actual, &expected);
+    }
+
+    fn head_id(repo: &TempGitRepo) -> git2::Oid {
+        repo.repo.head()
+            .and_then(|head| head.peel_to_commit())
+            .map(|commit| commit.id())
+            .expect("Unable to resolve HEAD")
+    }
+
+    fn sorted_paths(status: &[PathStatus]) -> Vec<(git2::Delta, String)> {
+        let mut paths: Vec<(git2::Delta, String)> = status.iter()
+            .map(|path_status| (path_status.delta, path_status.path.clone()))
+            .collect();
+
+        paths.sort_by(|a, b| a.1.cmp(&b.1));
+        paths
+    }
+
+    fn repo_with_working_directory_changes() -> TempGitRepo<'static> {
+        let repo = TempGitRepo::new();
+
+        let foo = repo.create_or_replace_blob_file("foo", "foo\n".as_bytes());
+        let bar = repo.create_or_replace_blob_file("bar", "bar\n".as_bytes());
+        let gitignore = repo.create_or_replace_blob_file(".gitignore", "*.log\n".as_bytes());
+
+        repo.git_add(&foo);
+        repo.git_add(&bar);
+        repo.git_add(&gitignore);
+        repo.git_commit("Adding foo, bar, and .gitignore");
+
+        let staged = repo.create_or_replace_blob_file("staged", "staged\n".as_bytes());
+        repo.git_add(&staged);
+
+        repo.create_or_replace_blob_file("foo", "foo\nfoo\n".as_bytes());
+        std::fs::remove_file(repo.directory.path().join("bar"))
+            .expect("Unable to delete bar");
+        repo.create_or_replace_blob_file("baz", "baz\n".as_bytes());
+        repo.create_or_replace_blob_file("debug.log", "debug\n".as_bytes());
+
+        repo
+    }
+
+    #[test]
+    fn all_includes_tracked_working_directory_changes() {
+        let repo = repo_with_working_directory_changes();
+
+        let options = StatusOptions {
+            include_tracked_changes: true,
+            ..StatusOptions::default()
+        };
+
+        let actual = git_status_with(&repo.repo, head_id(&repo), &options)
+            .expect("Unable to get a git status");
+
+        assert_eq!(sorted_paths(&actual), vec![
+            (git2::Delta::Deleted, "bar".to_string()),
+            (git2::Delta::Modified, "foo".to_string()),
+            (git2::Delta::Added, "staged".to_string()),
+        ]);
+
+        let foo = actual.iter().find(|path_status| path_status.path == "foo").unwrap();
+        assert_eq!(foo.object_id, git_hash_object_stdin("foo\nfoo\n"));
+        assert!(repo.repo.find_blob(foo.object_id).is_ok());
+
+        // - The changes are only added to a separate in-memory index, which
+        //   neither the index on disk nor the repository's own index sees
+        let index_on_disk = git2::Repository::open(repo.directory.path()).unwrap().index().unwrap();
+        assert_eq!(index_on_disk.get_path(std::path::Path::new("foo"), 0).unwrap().id, git_hash_object_stdin("foo\n"));
+
+        let repo_index = repo.repo.index().unwrap();
+        assert_eq!(repo_index.get_path(std::path::Path::new("foo"), 0).unwrap().id, git_hash_object_stdin("foo\n"));
+    }
+
+    #[test]
+    fn include_untracked_skips_ignored_files() {
+        let repo = repo_with_working_directory_changes();
+
+        let options = StatusOptions {
+            include_untracked: true,
+            ..StatusOptions::default()
+        };
+
+        let actual = git_status_with(&repo.repo, head_id(&repo), &options)
+            .expect("Unable to get a git status");
+
+        assert_eq!(sorted_paths(&actual), vec![
+            (git2::Delta::Added, "baz".to_string()),
+            (git2::Delta::Added, "staged".to_string()),
+        ]);
+    }
+
+    #[test]
+    fn pathspecs_limit_the_changes() {
+        let repo = repo_with_working_directory_changes();
+
+        let options = StatusOptions {
+            include_tracked_changes: true,
+            include_untracked: true,
+            pathspecs: vec!["ba*".to_string()],
+        };
+
+        let actual = git_status_with(&repo.repo, head_id(&repo), &options)
+            .expect("Unable to get a git status");
+
+        assert_eq!(sorted_paths(&actual), vec![
+            (git2::Delta::Deleted, "bar".to_string()),
+            (git2::Delta::Added, "baz".to_string()),
+        ]);
+    }
+
+    #[test]
+    fn changes_are_relative_to_the_base() {
+        let repo = TempGitRepo::new();
+
+        let foo = repo.create_or_replace_blob_file("foo", "foo\n".as_bytes());
+        repo.git_add(&foo);
+        let base = repo.git_commit("Add foo");
+
+        let bar = repo.create_or_replace_blob_file("bar", "bar\n".as_bytes());
+        repo.git_add(&bar);
+        repo.git_commit("Add bar");
+
+        let baz = repo.create_or_replace_blob_file("baz", "baz\n".as_bytes());
+        repo.git_add(&baz);
+        repo.git_rm(&foo);
+
+        let actual = git_status_with(&repo.repo, base, &StatusOptions::default())
+            .expect("Unable to get a git status");
+
+        // - `bar` is only in HEAD, but it's in the index too, so it's added on
+        //   top of the base along with the staged changes
+        assert_eq!(sorted_paths(&actual), vec![
+            (git2::Delta::Added, "bar".to_string()),
+            (git2::Delta::Added, "baz".to_string()),
+            (git2::Delta::Deleted, "foo".to_string()),
+        ]);
+    }
 }
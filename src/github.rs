@@ -1,18 +1,27 @@
+use std::fmt;
+use std::fs;
+use std::path::{Path, PathBuf};
 use std::time::{SystemTime, Duration, UNIX_EPOCH};
 use std::sync::{Arc, Mutex};
 
+use base64::Engine;
 use jsonwebtoken::{Algorithm, EncodingKey, Header};
+use once_cell::sync::OnceCell;
 use reqwest::StatusCode;
-use reqwest::blocking::Response;
+use reqwest::blocking::{RequestBuilder, Response};
 use reqwest::header::{self, HeaderMap, HeaderValue};
 use serde::de::DeserializeOwned;
 use serde::Serialize;
 use time::OffsetDateTime;
 
-use crate::github::rest_api::create_an_installation_access_token;
-use crate::log::{print_intent, print_intent_plain, print_success_and_return, print_success_plain};
+use crate::error::{Error, Result};
+use crate::github::graphql_api::{create_commit_on_branch, enable_pull_request_auto_merge};
+use crate::github::rest_api::{create_an_installation_access_token, get_a_repository_installation};
+use crate::log::{print_intent, print_intent_plain, print_retry, print_success_and_return, print_success_plain};
+use crate::retry::{RetryPolicy, RetryableFailure, retryable_response};
 
-use self::rest_api::{create_a_blob, create_a_commit, create_a_reference, create_a_tree, get_a_reference, update_a_reference};
+use self::rest_api::{add_labels_to_an_issue, create_a_pull_request, get_a_repository, list_pull_requests, request_reviewers_for_a_pull_request, update_a_pull_request};
+use self::rest_api::{compare_two_commits, create_a_blob, create_a_commit, create_a_reference, create_a_tree, get_a_commit_object, get_a_reference, update_a_reference};
 
 struct AccessToken {
     token: Arc<String>,
@@ -32,13 +41,187 @@ pub struct GitHubRepo {
     pub name: String,
 }
 
+/// How requests to GitHub are authenticated
+#[derive(Clone)]
+pub enum Authentication {
+    /// The app's private key signs JWTs which are exchanged for short-lived
+    /// installation access tokens
+    GitHubApp {
+        app_id: u64,
+        /// When `None`, the installation is looked up from the repository
+        installation_id: Option<u64>,
+        private_key: EncodingKey,
+    },
+    /// A token used as-is, such as a personal access token or the
+    /// `GITHUB_TOKEN` of a GitHub Actions workflow
+    Token(Arc<String>),
+}
+
+// - Rather than printing out the private key or the token, just print which
+//   kind of authentication is used
+impl fmt::Debug for Authentication {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Authentication::GitHubApp { app_id, installation_id, .. } => {
+                f.debug_struct("GitHubApp")
+                    .field("app_id", app_id)
+                    .field("installation_id", installation_id)
+                    .field("private_key", &format_args!("EncodingKey {{ ... }}"))
+                    .finish()
+            },
+            Authentication::Token(_) => {
+                f.debug_tuple("Token")
+                    .field(&format_args!("..."))
+                    .finish()
+            },
+        }
+    }
+}
+
+/// Where GitHub's APIs are served from, which differs between github.com and
+/// GitHub Enterprise Server
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct GitHubEndpoints {
+    /// REST API URL that paths like `/repos/{owner}/{repo}` are appended to
+    pub rest_api_base_url: String,
+    pub graphql_api_url: String,
+    /// URL that repositories are served from, which Git LFS's API is under,
+    /// like `https://github.com`
+    pub server_url: String,
+}
+
+impl GitHubEndpoints {
+    /// The endpoints for github.com or a GitHub Enterprise Server instance,
+    /// which serves its REST API under `/api/v3` and its GraphQL API at
+    /// `/api/graphql`
+    ///
+    /// - [GitHub Enterprise Server REST API](https://docs.github.com/en/enterprise-server@latest/rest/using-the-rest-api/getting-started-with-the-rest-api)
+    pub fn for_host(host: &str) -> GitHubEndpoints {
+        match host {
+            "github.com" => GitHubEndpoints {
+                rest_api_base_url: "https://api.github.com".to_owned(),
+                graphql_api_url: "https://api.github.com/graphql".to_owned(),
+                server_url: "https://github.com".to_owned(),
+            },
+            _ => GitHubEndpoints {
+                rest_api_base_url: format!("https://{}/api/v3", host),
+                graphql_api_url: format!("https://{}/api/graphql", host),
+                server_url: format!("https://{}", host),
+            },
+        }
+    }
+
+    /// The endpoints for an explicitly configured REST API URL, deriving the
+    /// GraphQL API and server URLs from it
+    ///
+    /// - When the REST API isn't under `/api/v3`, it's assumed to be served
+    ///   from the server's root
+    pub fn for_rest_api_base_url(rest_api_base_url: &str) -> GitHubEndpoints {
+        let rest_api_base_url = rest_api_base_url.trim_end_matches('/');
+
+        let (graphql_api_url, server_url) = match rest_api_base_url.strip_suffix("/api/v3") {
+            Some(server_url) => (format!("{}/api/graphql", server_url), server_url.to_owned()),
+            None if rest_api_base_url == "https://api.github.com" => (format!("{}/graphql", rest_api_base_url), "https://github.com".to_owned()),
+            None => (format!("{}/graphql", rest_api_base_url), rest_api_base_url.to_owned()),
+        };
+
+        GitHubEndpoints {
+            rest_api_base_url: rest_api_base_url.to_owned(),
+            graphql_api_url: graphql_api_url,
+            server_url: server_url,
+        }
+    }
+}
+
+impl Default for GitHubEndpoints {
+    fn default() -> GitHubEndpoints {
+        GitHubEndpoints::for_host("github.com")
+    }
+}
+
+/// How the HTTP client that every request shares is set up
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct HttpClientOptions {
+    /// How long a single request may take, from connecting until the response
+    /// body has been read
+    pub timeout: Duration,
+    /// Proxy URL to send every request through
+    ///
+    /// - When `None`, the `HTTPS_PROXY`, `ALL_PROXY`, and `NO_PROXY`
+    ///   environment variables are honored
+    pub proxy: Option<String>,
+    /// PEM file with certificates to trust in addition to the system's, such
+    /// as a GitHub Enterprise Server's internal certificate authority
+    pub ca_bundle: Option<PathBuf>,
+    /// - GitHub requires the User-Agent header
+    ///   - https://docs.github.com/en/rest/overview/resources-in-the-rest-api#user-agent-required
+    pub user_agent: String,
+}
+
+impl Default for HttpClientOptions {
+    fn default() -> HttpClientOptions {
+        HttpClientOptions {
+            timeout: Duration::from_secs(60),
+            proxy: None,
+            ca_bundle: None,
+            user_agent: "ghommit".to_owned(),
+        }
+    }
+}
+
+impl HttpClientOptions {
+    /// Builds the client, which keeps connections alive and pools them so
+    /// that requests after the first skip the TCP and TLS handshakes
+    fn build(&self) -> Result<reqwest::blocking::Client> {
+        let mut builder = reqwest::blocking::Client::builder()
+            .timeout(self.timeout)
+            .tcp_keepalive(Duration::from_secs(60))
+            .pool_idle_timeout(Duration::from_secs(90))
+            .user_agent(&self.user_agent);
+
+        if let Some(proxy) = &self.proxy {
+            let proxy = reqwest::Proxy::all(proxy)
+                .map_err(|e| Error::config(format!("Invalid proxy URL {:?}: {}", proxy, e)))?;
+
+            builder = builder.proxy(proxy);
+        }
+
+        if let Some(ca_bundle) = &self.ca_bundle {
+            let pem_bundle = fs::read(ca_bundle)
+                .map_err(|e| Error::Io {
+                    path: ca_bundle.to_string_lossy().into_owned(),
+                    message: e.to_string(),
+                })?;
+
+            let certificates = reqwest::Certificate::from_pem_bundle(&pem_bundle)
+                .map_err(|e| Error::config(format!("Unable to read certificates from CA bundle {:?}: {}", ca_bundle, e)))?;
+
+            if certificates.is_empty() {
+                Err(Error::config(format!("CA bundle {:?} contains no certificates", ca_bundle)))?
+            }
+
+            for certificate in certificates {
+                builder = builder.add_root_certificate(certificate);
+            }
+        }
+
+        builder.build()
+            .map_err(|e| Error::Transport {
+                operation: "create an HTTP client".to_string(),
+                message: e.to_string(),
+            })
+    }
+}
+
 pub struct GitHubClient {
-    github_api_base_url: String,
-    github_app_id: u64,
-    github_app_installation_id: u64,
-    github_app_private_key: EncodingKey,
+    github_endpoints: GitHubEndpoints,
+    github_authentication: Authentication,
     github_access_token: Mutex<Option<AccessToken>>,
+    github_app_installation_id: OnceCell<u64>,
     github_repo: GitHubRepo,
+    http_client: OnceCell<reqwest::blocking::Client>,
+    http_client_options: HttpClientOptions,
+    retry_policy: RetryPolicy,
 }
 
 #[derive(Debug, Serialize)]
@@ -48,7 +231,7 @@ struct Claims {
     iss: String,
 }
 
-mod custom_header {
+pub(crate) mod custom_header {
     use once_cell::sync::Lazy;
     use reqwest::header::HeaderName;
 
@@ -56,69 +239,133 @@ mod custom_header {
     pub static X_GITHUB_API_VERSION: Lazy<HeaderName> = Lazy::new(|| {
         HeaderName::from_static("x-github-api-version")
     });
+    pub static X_RATELIMIT_REMAINING: Lazy<HeaderName> = Lazy::new(|| {
+        HeaderName::from_static("x-ratelimit-remaining")
+    });
+    pub static X_RATELIMIT_RESET: Lazy<HeaderName> = Lazy::new(|| {
+        HeaderName::from_static("x-ratelimit-reset")
+    });
 }
 
+#[derive(Clone, Copy)]
 enum AuthorizationTokenType {
     AccessToken,
     Jwt,
 }
 
+/// Whether a request is safe to send again after a transient failure
+///
+/// - Creating blobs, trees, and commits is safe since a duplicate only leaves
+///   behind an unreferenced object
+/// - Moving a reference or creating a commit on a branch is not since the
+///   first attempt may have succeeded without its response arriving
+#[derive(Clone, Copy)]
+enum Retry {
+    Allowed,
+    Disallowed,
+}
+
 impl GitHubClient {
-    pub fn new(github_app_id: u64, github_app_installation_id: u64, github_app_private_key: EncodingKey, github_repo: GitHubRepo) -> GitHubClient {
+    pub fn new(github_authentication: Authentication, github_endpoints: GitHubEndpoints, github_repo: GitHubRepo) -> GitHubClient {
         GitHubClient {
-            github_api_base_url: "https://api.github.com".to_owned(),
-            github_app_id: github_app_id,
-            github_app_installation_id: github_app_installation_id,
-            github_app_private_key: github_app_private_key,
+            github_endpoints: github_endpoints,
+            github_authentication: github_authentication,
             github_access_token: Mutex::new(None),
+            github_app_installation_id: OnceCell::new(),
             github_repo: github_repo,
+            http_client: OnceCell::new(),
+            http_client_options: HttpClientOptions::default(),
+            retry_policy: RetryPolicy::default(),
         }
     }
 
-    fn unix_epoch_second_now() -> Result<usize, String> {
+    pub fn with_retry_policy(mut self, retry_policy: RetryPolicy) -> GitHubClient {
+        self.retry_policy = retry_policy;
+        self
+    }
+
+    pub fn with_http_client_options(mut self, http_client_options: HttpClientOptions) -> GitHubClient {
+        self.http_client_options = http_client_options;
+        self.http_client = OnceCell::new();
+        self
+    }
+
+    pub fn with_timeout(mut self, timeout: Duration) -> GitHubClient {
+        self.http_client_options.timeout = timeout;
+        self.http_client = OnceCell::new();
+        self
+    }
+
+    pub fn with_proxy(mut self, proxy: impl Into<String>) -> GitHubClient {
+        self.http_client_options.proxy = Some(proxy.into());
+        self.http_client = OnceCell::new();
+        self
+    }
+
+    pub fn with_ca_bundle(mut self, ca_bundle: impl Into<PathBuf>) -> GitHubClient {
+        self.http_client_options.ca_bundle = Some(ca_bundle.into());
+        self.http_client = OnceCell::new();
+        self
+    }
+
+    pub fn with_user_agent(mut self, user_agent: impl Into<String>) -> GitHubClient {
+        self.http_client_options.user_agent = user_agent.into();
+        self.http_client = OnceCell::new();
+        self
+    }
+
+    fn unix_epoch_second_now() -> Result<usize> {
         let now = SystemTime::now();
 
         match now.duration_since(UNIX_EPOCH) {
             Ok(duration) => Ok(duration.as_secs() as usize),
-            Err(e) => Err(format!("Impossible duration from Unix epoch to now: {} nanoseconds", e.duration().as_nanos())),
+            Err(e) => Err(Error::internal(format!("Impossible duration from Unix epoch to now: {} nanoseconds", e.duration().as_nanos()))),
         }
     }
 
-    fn get_http_client(&self, maybe_timeout_seconds: Option<u64>) -> Result<reqwest::blocking::Client, String> {
-        let timeout_seconds = maybe_timeout_seconds.unwrap_or(60);
-
-        let maybe_client = reqwest::blocking::Client::builder().timeout(Duration::from_secs(timeout_seconds)).build();
-
-        match maybe_client {
-            Ok(client) => Ok(client),
-            Err(_) => Err("Unable to create HTTP client".to_string()),
-        }
+    /// Returns the HTTP client every request shares, building it on first use
+    fn get_http_client(&self) -> Result<&reqwest::blocking::Client> {
+        self.http_client.get_or_try_init(|| self.http_client_options.build())
     }
 
     /// [Generating a JSON Web Token (JWT) for a GitHub App](https://docs.github.com/en/apps/creating-github-apps/authenticating-with-a-github-app/generating-a-json-web-token-jwt-for-a-github-app)
-    fn get_jwt(&self) -> Result<String, String> {
+    fn get_jwt(&self) -> Result<String> {
+        let (app_id, private_key) = match &self.github_authentication {
+            Authentication::GitHubApp { app_id, private_key, .. } => (app_id, private_key),
+            Authentication::Token(_) => Err(Error::auth("A JWT can only be created when authenticating as a GitHub App"))?,
+        };
+
         let now = Self::unix_epoch_second_now()?;
         let ten_minutes_from_now = now + (10 * 60);
 
         if ten_minutes_from_now < now {
-            return Err(format!("Adding ten minutes to now in seconds ({}) resulted in time that is less than now ({})", now, ten_minutes_from_now));
+            return Err(Error::internal(format!("Adding ten minutes to now in seconds ({}) resulted in time that is less than now ({})", now, ten_minutes_from_now)));
         }
 
         let claims = Claims {
             iat: now,
             exp: ten_minutes_from_now,
-            iss: self.github_app_id.to_string(),
+            iss: app_id.to_string(),
         };
 
-        let maybe_jwt = jsonwebtoken::encode(&Header::new(Algorithm::RS256), &claims, &self.github_app_private_key);
+        let maybe_jwt = jsonwebtoken::encode(&Header::new(Algorithm::RS256), &claims, private_key);
 
         match maybe_jwt {
             Ok(jwt) => Ok(jwt),
-            Err(e) => Err(format!("Unable to create JWT: {}", e)),
+            Err(e) => Err(Error::auth(format!("Unable to create JWT: {}", e))),
         }
     }
 
-    pub fn get_access_token(&self, force_token_renewal: bool) -> Result<Arc<String>, String> {
+    /// Returns the token to authenticate API requests with
+    ///
+    /// When authenticating as a GitHub App, an installation access token is
+    /// created and cached until it expires soon or `force_token_renewal` is
+    /// set. Otherwise, the configured token is returned as-is.
+    pub fn get_access_token(&self, force_token_renewal: bool) -> Result<Arc<String>> {
+        if let Authentication::Token(token) = &self.github_authentication {
+            return Ok(Arc::clone(token))
+        }
+
         match self.github_access_token.lock() {
             Ok(mut access_token_guard) => {
                 let should_update = force_token_renewal || match &*access_token_guard {
@@ -142,11 +389,11 @@ impl GitHubClient {
                 } else {
                     match &*access_token_guard {
                         Some(access_token) => Ok(Arc::clone(&access_token.token)),
-                        None => Err("Unexpected state: Access token is None".to_string()),
+                        None => Err(Error::internal("Unexpected state: Access token is None")),
                     }
                 }
             },
-            Err(e) => Err(format!("Mutex poisoned unexpectedly: {}", e)),
+            Err(e) => Err(Error::internal(format!("Mutex poisoned unexpectedly: {}", e))),
         }
     }
 
@@ -154,9 +401,10 @@ impl GitHubClient {
     ///
     /// - `Accept`
     /// - `Authorization`
-    /// - `User-Agent`
     /// - `X-GitHub-Api-Version` (if using the REST API)
-    fn base_headers(&self, auth_token_type: AuthorizationTokenType) -> Result<HeaderMap, String> {
+    ///
+    /// `User-Agent` is set on the HTTP client instead.
+    fn base_headers(&self, auth_token_type: AuthorizationTokenType) -> Result<HeaderMap> {
         let token = match auth_token_type {
             AuthorizationTokenType::AccessToken => self.get_access_token(false)?,
             // - Creating an `Arc` is generally cheaper than cloning a `String`,
@@ -166,7 +414,7 @@ impl GitHubClient {
 
         let auth_header_value = match HeaderValue::from_str(&format!("Bearer {}", token)) {
             Ok(value) => value,
-            Err(_) => Err("Unable to create header value with JWT string")?,
+            Err(_) => Err(Error::auth("Unable to create an Authorization header value from the token"))?,
         };
 
         let mut headers = HeaderMap::new();
@@ -179,12 +427,6 @@ impl GitHubClient {
             header::AUTHORIZATION,
             auth_header_value,
         );
-        headers.insert(
-            // - GitHub requires the User-Agent header
-            //   - https://docs.github.com/en/rest/overview/resources-in-the-rest-api#user-agent-required
-            header::USER_AGENT,
-            HeaderValue::from_static("ghommit")
-        );
         headers.insert(
             &*custom_header::X_GITHUB_API_VERSION,
             HeaderValue::from_static("2022-11-28"),
@@ -193,57 +435,176 @@ impl GitHubClient {
         Ok(headers)
     }
 
-    fn make_api_request<T: Serialize + ?Sized>(&self, http_method: reqwest::Method, path: &str, json: Option<&T>, auth_token_type: Option<AuthorizationTokenType>) -> Result<Response, String> {
-        let url = format!("{}{}", self.github_api_base_url, path);
+    fn rest_api_url(&self, path: &str) -> String {
+        format!("{}{}", self.github_endpoints.rest_api_base_url, path)
+    }
 
+    /// Sends a request, retrying transient failures according to the retry
+    /// policy if `retry` allows it
+    ///
+    /// Once the attempts run out, the last response is returned as-is so that
+    /// the caller reports its status code like any other unexpected one.
+    fn make_api_request<T: Serialize + ?Sized>(&self, http_method: reqwest::Method, url: &str, json: Option<&T>, auth_token_type: Option<AuthorizationTokenType>, retry: Retry) -> Result<Response> {
         let auth_token_type = match auth_token_type {
             Some(auth_token_type) => auth_token_type,
             None => AuthorizationTokenType::AccessToken,
         };
 
-        let http_client = self.get_http_client(None)?;
-        let headers = self.base_headers(auth_token_type)?;
-        let request = http_client.request(http_method, url).headers(headers);
+        self.send_with_retries(&http_method, url, retry, |http_client| {
+            // - The headers are rebuilt on every attempt since the access
+            //   token may need renewing after a long wait
+            let headers = self.base_headers(auth_token_type)?;
+            let request = http_client.request(http_method.clone(), url).headers(headers);
 
-        let request = match json {
-            Some(json) => request.json(&json),
-            None => request
-        };
-        let response = request.send();
+            let request = match json {
+                Some(json) => request.json(&json),
+                None => request
+            };
+
+            Ok(request)
+        })
+    }
+
+    /// Sends the request `build_request` builds, building it again for every
+    /// attempt, and retrying transient failures according to the retry policy
+    /// if `retry` allows it
+    fn send_with_retries(&self, http_method: &reqwest::Method, url: &str, retry: Retry, build_request: impl Fn(&reqwest::blocking::Client) -> Result<RequestBuilder>) -> Result<Response> {
+        let http_client = self.get_http_client()?;
+        let mut attempts_made = 0;
+
+        loop {
+            let request = build_request(http_client)?;
+
+            attempts_made += 1;
+
+            let (failure, outcome) = match request.send() {
+                Ok(response) => {
+                    let now = Self::unix_epoch_second_now()? as u64;
+                    let (response, body) = Self::with_forbidden_body(http_method, url, response)?;
+
+                    match retryable_response(response.status(), response.headers(), body.as_deref(), now) {
+                        Some(failure) => (failure, Ok(response)),
+                        None => return Ok(response),
+                    }
+                },
+                // - A request that couldn't be built will never succeed
+                Err(e) if e.is_builder() => return Err(Self::transport_error(http_method, url, e)),
+                Err(e) => {
+                    let failure = RetryableFailure {
+                        reason: e.to_string(),
+                        requested_wait: None,
+                        fallback_wait: None,
+                    };
+
+                    (failure, Err(Self::transport_error(http_method, url, e)))
+                },
+            };
+
+            let maybe_wait = match retry {
+                Retry::Allowed => self.retry_policy.wait_before_retrying(attempts_made, &failure),
+                Retry::Disallowed => None,
+            };
+
+            match maybe_wait {
+                Some(wait) => {
+                    print_retry(&format!("{} {}", http_method, url), &failure.reason, wait, attempts_made + 1, self.retry_policy.max_attempts);
+                    std::thread::sleep(wait);
+                },
+                None => return outcome,
+            }
+        }
+    }
 
-        match response {
-            Ok(response) => Ok(response),
-            Err(e) => Err(format!("Request failed: {}", e)),
+    /// Reads the body of a 403 so that a secondary rate limit that only its
+    /// body reports can be recognized, and returns the response rebuilt around
+    /// the body that was read
+    fn with_forbidden_body(http_method: &reqwest::Method, url: &str, response: Response) -> Result<(Response, Option<String>)> {
+        if response.status() != StatusCode::FORBIDDEN {
+            return Ok((response, None))
         }
+
+        let status_code = response.status();
+        let version = response.version();
+        let headers = response.headers().clone();
+        let bytes = response.bytes()
+            .map_err(|e| Self::transport_error(http_method, url, e))?;
+        let body = String::from_utf8_lossy(&bytes).into_owned();
+
+        let mut rebuilt = http::Response::new(bytes);
+        *rebuilt.status_mut() = status_code;
+        *rebuilt.version_mut() = version;
+        *rebuilt.headers_mut() = headers;
+
+        Ok((Response::from(rebuilt), Some(body)))
     }
 
-    fn get_api_request(&self, path: &str, auth_token_type: Option<AuthorizationTokenType>) -> Result<Response, String> {
+    fn get_api_request(&self, path: &str, auth_token_type: Option<AuthorizationTokenType>) -> Result<Response> {
         // - The unit type turbofish is necessary to satisfy the type checker
-        self.make_api_request::<()>(reqwest::Method::GET, path, None, auth_token_type)
+        self.make_api_request::<()>(reqwest::Method::GET, &self.rest_api_url(path), None, auth_token_type, Retry::Allowed)
+    }
+
+    fn post_api_request<T: Serialize + ?Sized>(&self, path: &str, json: Option<&T>, auth_token_type: Option<AuthorizationTokenType>, retry: Retry) -> Result<Response> {
+        self.make_api_request(reqwest::Method::POST, &self.rest_api_url(path), json, auth_token_type, retry)
     }
 
-    fn post_api_request<T: Serialize + ?Sized>(&self, path: &str, json: Option<&T>, auth_token_type: Option<AuthorizationTokenType>) -> Result<Response, String> {
-        self.make_api_request(reqwest::Method::POST, path, json, auth_token_type)
+    fn patch_api_request<T: Serialize + ?Sized>(&self, path: &str, json: Option<&T>, auth_token_type: Option<AuthorizationTokenType>, retry: Retry) -> Result<Response> {
+        self.make_api_request(reqwest::Method::PATCH, &self.rest_api_url(path), json, auth_token_type, retry)
     }
 
-    fn patch_api_request<T: Serialize + ?Sized>(&self, path: &str, json: Option<&T>, auth_token_type: Option<AuthorizationTokenType>) -> Result<Response, String> {
-        self.make_api_request(reqwest::Method::PATCH, path, json, auth_token_type)
+    fn delete_api_request(&self, path: &str, auth_token_type: Option<AuthorizationTokenType>, retry: Retry) -> Result<Response> {
+        // - The unit type turbofish is necessary to satisfy the type checker
+        self.make_api_request::<()>(reqwest::Method::DELETE, &self.rest_api_url(path), None, auth_token_type, retry)
+    }
+
+    fn transport_error(http_method: &reqwest::Method, url: &str, e: reqwest::Error) -> Error {
+        Error::Transport {
+            operation: format!("send {} {}", http_method, url),
+            message: e.to_string(),
+        }
     }
 
-    fn unexpected_status_code_error_message(response: Response, operation: &str) -> String {
+    /// Classifies a response with an unexpected status code, telling rate
+    /// limits and rejected credentials apart from other API errors
+    fn unexpected_status_code_error(response: Response, operation: &str) -> Error {
         let status_code = response.status();
+        let headers = response.headers().clone();
 
-        match &response.text() {
-            Ok(text) => format!("Unexpected status code {} while {}: {}", status_code, operation, text),
-            Err(_) => format!("Unexpected status code {} while {}; body could not be decoded as text", status_code, operation),
+        let body = match response.text() {
+            Ok(text) => text,
+            Err(_) => "(body could not be decoded as text)".to_string(),
+        };
+
+        let rate_limit_wait = match status_code {
+            StatusCode::TOO_MANY_REQUESTS | StatusCode::FORBIDDEN => {
+                let now = Self::unix_epoch_second_now().unwrap_or_default() as u64;
+
+                retryable_response(status_code, &headers, Some(&body), now)
+                    .map(|failure| failure.requested_wait)
+            },
+            _ => None,
+        };
+
+        match (status_code, rate_limit_wait) {
+            (_, Some(retry_after)) => Error::RateLimited {
+                operation: operation.to_string(),
+                status: status_code.as_u16(),
+                body: body,
+                retry_after: retry_after,
+            },
+            (StatusCode::UNAUTHORIZED, _) => Error::auth(format!("Credentials were rejected while trying to {}: {}", operation, body)),
+            _ => Error::Api {
+                operation: operation.to_string(),
+                status: status_code.as_u16(),
+                body: body,
+            },
         }
     }
 
-    fn deserialize_expected_response<R: DeserializeOwned>(response: Response, expected_status_code: &StatusCode, operation: &str) -> Result<R, String> {
+    fn deserialize_expected_response<R: DeserializeOwned>(response: Response, expected_status_code: &StatusCode, operation: &str) -> Result<R> {
         let status_code = response.status();
 
         if &status_code != expected_status_code {
-            return Err(Self::unexpected_status_code_error_message(response, operation))
+            return Err(Self::unexpected_status_code_error(response, operation))
         }
 
         // - Read as text before deserializing to a struct since `.text()` and
@@ -251,79 +612,204 @@ impl GitHubClient {
         //   succeed
         let text = match response.text() {
             Ok(text) => text,
-            Err(e) => Err(format!("Error occurred while reading response body as text while trying to {}: {}", operation, e))?,
+            Err(e) => Err(Error::InvalidResponse {
+                operation: operation.to_string(),
+                message: format!("Unable to read body as text: {}", e),
+                body: String::new(),
+            })?,
         };
 
         let data = match serde_json::from_str::<R>(&text) {
             Ok(typed_result) => typed_result,
             Err(e) => {
-                let err_str = e.to_string();
                 let type_str = std::any::type_name::<R>();
-                Err(format!("Error occurred while deserializing response to {} while trying to {}: {}: {}", type_str, operation, err_str, text))?
+                Err(Error::InvalidResponse {
+                    operation: operation.to_string(),
+                    message: format!("Unable to deserialize to {}: {}", type_str, e),
+                    body: text,
+                })?
             }
         };
 
         Ok(data)
     }
 
+    /// [Compare two commits](https://docs.github.com/en/rest/commits/commits?apiVersion=2022-11-28#compare-two-commits)
+    pub fn compare_two_commits(&self, base: &str, head: &str) -> Result<compare_two_commits::ResponseBody> {
+        print_intent("Comparing two commits", &format!("{}...{}", base, head));
+
+        let path = format!("/repos/{}/{}/compare/{}...{}", self.github_repo.owner, self.github_repo.name, base, head);
+        let response = self.get_api_request(&path, None)?;
+        let ret = Self::deserialize_expected_response(response, &StatusCode::OK, "compare two commits")?;
+
+        print_success_and_return("Commits compared", ret)
+    }
+
     /// [Create a blob](https://docs.github.com/en/rest/git/blobs?apiVersion=2022-11-28#create-a-blob)
-    pub fn create_a_blob(&self, payload: &create_a_blob::RequestBody) -> Result<create_a_blob::ResponseBody, String> {
+    pub fn create_a_blob(&self, payload: &create_a_blob::RequestBody) -> Result<create_a_blob::ResponseBody> {
         print_intent("Creating a blob", &payload);
 
         let path = format!("/repos/{}/{}/git/blobs", self.github_repo.owner, self.github_repo.name);
-        let response = self.post_api_request(&path, Some(&payload), None)?;
+        let response = self.post_api_request(&path, Some(&payload), None, Retry::Allowed)?;
         let ret = Self::deserialize_expected_response(response, &StatusCode::CREATED, "create a blob")?;
 
         print_success_and_return("Blob created", ret)
     }
 
     /// [Create a tree](https://docs.github.com/en/rest/git/trees?apiVersion=2022-11-28#create-a-tree)
-    pub fn create_a_tree(&self, payload: &create_a_tree::RequestBody) -> Result<create_a_tree::ResponseBody, String> {
+    pub fn create_a_tree(&self, payload: &create_a_tree::RequestBody) -> Result<create_a_tree::ResponseBody> {
         print_intent("Creating a tree", &payload);
 
         let path = format!("/repos/{}/{}/git/trees", self.github_repo.owner, self.github_repo.name);
-        let response = self.post_api_request(&path, Some(&payload), None)?;
+        let response = self.post_api_request(&path, Some(&payload), None, Retry::Allowed)?;
         let ret = Self::deserialize_expected_response(response, &StatusCode::CREATED, "create a tree")?;
 
         print_success_and_return("Tree created", ret)
     }
 
     /// [Create a commit](https://docs.github.com/en/rest/git/commits?apiVersion=2022-11-28#create-a-commit)
-    pub fn create_a_commit(&self, payload: &create_a_commit::RequestBody) -> Result<create_a_commit::ResponseBody, String> {
+    pub fn create_a_commit(&self, payload: &create_a_commit::RequestBody) -> Result<create_a_commit::ResponseBody> {
         print_intent("Creating a commit", &payload);
 
         let path = format!("/repos/{}/{}/git/commits", self.github_repo.owner, self.github_repo.name);
-        let response = self.post_api_request(&path, Some(&payload), None)?;
+        let response = self.post_api_request(&path, Some(&payload), None, Retry::Allowed)?;
         let ret = Self::deserialize_expected_response(response, &StatusCode::CREATED, "create a commit")?;
 
         print_success_and_return("Commit created", ret)
     }
 
     /// [Create a reference](https://docs.github.com/en/rest/git/refs?apiVersion=2022-11-28#create-a-reference)
-    pub fn create_a_reference(&self, payload: &create_a_reference::RequestBody) -> Result<create_a_reference::ResponseBody, String> {
+    pub fn create_a_reference(&self, payload: &create_a_reference::RequestBody) -> Result<create_a_reference::ResponseBody> {
         print_intent("Creating reference", &payload);
 
         let path = format!("/repos/{}/{}/git/refs", self.github_repo.owner, self.github_repo.name);
-        let response = self.post_api_request(&path, Some(&payload), None)?;
-        let ret = Self::deserialize_expected_response(response, &StatusCode::CREATED, "create a reference")?;
+        let response = self.post_api_request(&path, Some(&payload), None, Retry::Disallowed)?;
+
+        let operation = "create a reference";
+
+        let status_code = response.status();
+
+        match status_code {
+            StatusCode::CREATED => {
+                let success_body = Self::deserialize_expected_response(response, &status_code, operation)?;
+
+                let ret = create_a_reference::ResponseBody::Ok(success_body);
+                print_success_and_return("Reference created", ret)
+            },
+            // - Returned when the reference already exists
+            StatusCode::UNPROCESSABLE_ENTITY => {
+                let failure_body = Self::deserialize_expected_response(response, &status_code, operation)?;
+
+                Ok(create_a_reference::ResponseBody::UnprocessableEntity(failure_body))
+            },
+            _ => Err(Self::unexpected_status_code_error(response, operation)),
+        }
+    }
 
-        print_success_and_return("Reference created", ret)
+    /// Returns the configured installation ID, or looks up the installation
+    /// for the repository if none was configured
+    ///
+    /// The lookup only happens once per client since an app has at most one
+    /// installation per repository.
+    fn get_installation_id(&self) -> Result<u64> {
+        match &self.github_authentication {
+            Authentication::GitHubApp { installation_id: Some(installation_id), .. } => Ok(*installation_id),
+            Authentication::GitHubApp { installation_id: None, .. } => {
+                self.github_app_installation_id
+                    .get_or_try_init(|| self.get_a_repository_installation().map(|installation| installation.id))
+                    .copied()
+            },
+            Authentication::Token(_) => Err(Error::auth("An installation access token can only be created when authenticating as a GitHub App")),
+        }
     }
 
     /// [Create an installation access token for an app](https://docs.github.com/en/rest/apps/apps?apiVersion=2022-11-28#create-an-installation-access-token-for-an-app)
-    pub fn create_an_installation_access_token(&self) -> Result<create_an_installation_access_token::ResponseBody, String> {
+    pub fn create_an_installation_access_token(&self) -> Result<create_an_installation_access_token::ResponseBody> {
         print_intent_plain("Creating an installation access token");
 
-        let path = format!("/app/installations/{}/access_tokens", self.github_app_installation_id);
-        let response = self.post_api_request::<()>(&path, None, Some(AuthorizationTokenType::Jwt))?;
+        let installation_id = self.get_installation_id()?;
+
+        let path = format!("/app/installations/{}/access_tokens", installation_id);
+        let response = self.post_api_request::<()>(&path, None, Some(AuthorizationTokenType::Jwt), Retry::Allowed)?;
         let ret = Self::deserialize_expected_response(response, &StatusCode::CREATED, "acquire an access token")?;
 
         print_success_plain("Created an installation access token");
         Ok(ret)
     }
 
+    /// [createCommitOnBranch](https://docs.github.com/en/graphql/reference/mutations#createcommitonbranch)
+    ///
+    /// GraphQL errors, such as the branch having moved past
+    /// `expectedHeadOid`, are returned as part of the response rather than as
+    /// an `Err` so that they can be told apart
+    pub fn create_commit_on_branch(&self, input: &create_commit_on_branch::Input) -> Result<create_commit_on_branch::ResponseBody> {
+        print_intent("Creating a commit on a branch", &input);
+
+        let payload = graphql_api::shared::RequestBody {
+            query: create_commit_on_branch::QUERY,
+            variables: create_commit_on_branch::Variables {
+                input: input,
+            },
+        };
+
+        let response = self.make_api_request(reqwest::Method::POST, &self.github_endpoints.graphql_api_url, Some(&payload), None, Retry::Disallowed)?;
+        let ret = Self::deserialize_expected_response(response, &StatusCode::OK, "create a commit on a branch")?;
+
+        print_success_and_return("Commit on a branch created", ret)
+    }
+
+    /// [Delete a reference](https://docs.github.com/en/rest/git/refs?apiVersion=2022-11-28#delete-a-reference)
+    pub fn delete_a_reference(&self, partially_qualified_reference_name: &str) -> Result<()> {
+        print_intent("Deleting a reference", &partially_qualified_reference_name);
+
+        let path = format!("/repos/{}/{}/git/refs/{}", self.github_repo.owner, self.github_repo.name, partially_qualified_reference_name);
+        // - A retried deletion that had already succeeded would fail since the
+        //   reference no longer exists
+        let response = self.delete_api_request(&path, None, Retry::Disallowed)?;
+
+        if response.status() != StatusCode::NO_CONTENT {
+            return Err(Self::unexpected_status_code_error(response, "delete a reference"))
+        }
+
+        print_success_plain(&format!("Reference {:?} deleted", partially_qualified_reference_name));
+
+        Ok(())
+    }
+
+    /// [Get a repository installation for the authenticated app](https://docs.github.com/en/rest/apps/apps?apiVersion=2022-11-28#get-a-repository-installation-for-the-authenticated-app)
+    pub fn get_a_repository_installation(&self) -> Result<get_a_repository_installation::ResponseBody> {
+        print_intent("Getting the app installation for repository", &format!("{}/{}", self.github_repo.owner, self.github_repo.name));
+
+        let path = format!("/repos/{}/{}/installation", self.github_repo.owner, self.github_repo.name);
+        let response = self.get_api_request(&path, Some(AuthorizationTokenType::Jwt))?;
+
+        if response.status() == StatusCode::NOT_FOUND {
+            let app_id = match &self.github_authentication {
+                Authentication::GitHubApp { app_id, .. } => app_id.to_string(),
+                Authentication::Token(_) => "(unknown)".to_string(),
+            };
+
+            return Err(Error::auth(format!("GitHub App {} is not installed on repository {}/{}; install it or set GHOMMIT_GITHUB_APP_INSTALLATION_ID", app_id, self.github_repo.owner, self.github_repo.name)))
+        }
+
+        let ret = Self::deserialize_expected_response(response, &StatusCode::OK, "get a repository installation")?;
+
+        print_success_and_return("App installation retrieved", ret)
+    }
+
+    /// [Get a commit object](https://docs.github.com/en/rest/git/commits?apiVersion=2022-11-28#get-a-commit-object)
+    pub fn get_a_commit_object(&self, commit_sha: &str) -> Result<get_a_commit_object::ResponseBody> {
+        print_intent("Getting a commit object", &commit_sha);
+
+        let path = format!("/repos/{}/{}/git/commits/{}", self.github_repo.owner, self.github_repo.name, commit_sha);
+        let response = self.get_api_request(&path, None)?;
+        let ret = Self::deserialize_expected_response(response, &StatusCode::OK, "get a commit object")?;
+
+        print_success_and_return("Commit object retrieved", ret)
+    }
+
     /// [Get a reference](https://docs.github.com/en/rest/git/refs?apiVersion=2022-11-28#get-a-reference)
-    pub fn get_a_reference(&self, partially_qualified_reference_name: &str) -> Result<get_a_reference::ResponseBody, String> {
+    pub fn get_a_reference(&self, partially_qualified_reference_name: &str) -> Result<get_a_reference::ResponseBody> {
         print_intent("Getting a reference", &partially_qualified_reference_name);
 
         let path = format!("/repos/{}/{}/git/refs/{}", self.github_repo.owner, self.github_repo.name, partially_qualified_reference_name);
@@ -346,325 +832,1407 @@ impl GitHubClient {
                 let ret = get_a_reference::ResponseBody::NotFound(failure_body);
                 print_success_and_return("Reference retrieved", ret)
             },
-            _ => Err(Self::unexpected_status_code_error_message(response, operation)),
+            _ => Err(Self::unexpected_status_code_error(response, operation)),
         }
     }
 
     /// [Update a reference](https://docs.github.com/en/rest/git/refs?apiVersion=2022-11-28#update-a-reference)
-    pub fn update_a_reference(&self, partially_qualified_reference_name: &str, payload: &update_a_reference::RequestBody) -> Result<update_a_reference::ResponseBody, String> {
+    pub fn update_a_reference(&self, partially_qualified_reference_name: &str, payload: &update_a_reference::RequestBody) -> Result<update_a_reference::ResponseBody> {
         print_intent(&format!("Updating reference {:?}", partially_qualified_reference_name), &payload);
 
         let path = format!("/repos/{}/{}/git/refs/{}", self.github_repo.owner, self.github_repo.name, partially_qualified_reference_name);
-        let response = self.patch_api_request(&path, Some(&payload), None)?;
-        let ret = Self::deserialize_expected_response(response, &StatusCode::OK, "update a reference")?;
+        let response = self.patch_api_request(&path, Some(&payload), None, Retry::Disallowed)?;
 
-        print_success_and_return(&format!("Reference {:?} updated", partially_qualified_reference_name), ret)
-    }
-}
+        let operation = "update a reference";
 
-pub mod rest_api {
-    /// [Create a blob](https://docs.github.com/en/rest/git/blobs?apiVersion=2022-11-28#create-a-blob)
-    pub mod create_a_blob {
-        use serde::{Deserialize, Serialize};
+        let status_code = response.status();
 
-        #[derive(Debug, Serialize)]
-        pub enum Encoding {
-            #[serde(rename = "base64")]
-            Base64,
-            #[serde(rename = "utf-8")]
-            Utf8,
-        }
+        match status_code {
+            StatusCode::OK => {
+                let success_body = Self::deserialize_expected_response(response, &status_code, operation)?;
 
-        #[derive(Debug, Serialize)]
-        pub struct RequestBody<'a> {
-            pub content: &'a str,
-            pub encoding: Encoding,
-        }
+                let ret = update_a_reference::ResponseBody::Ok(success_body);
+                print_success_and_return(&format!("Reference {:?} updated", partially_qualified_reference_name), ret)
+            },
+            // - Returned when a non-forced update is not a fast-forward
+            StatusCode::UNPROCESSABLE_ENTITY => {
+                let failure_body = Self::deserialize_expected_response(response, &status_code, operation)?;
 
-        #[derive(Debug, Deserialize, Serialize)]
-        pub struct ResponseBody {
-            pub url: String,
-            pub sha: String,
+                Ok(update_a_reference::ResponseBody::UnprocessableEntity(failure_body))
+            },
+            _ => Err(Self::unexpected_status_code_error(response, operation)),
         }
     }
 
-    /// [Create a commit](https://docs.github.com/en/rest/git/commits?apiVersion=2022-11-28#create-a-commit)
-    pub mod create_a_commit {
-        use serde::{Deserialize, Serialize};
-
-        /// Abbreviated representation of the response body
-        #[derive(Debug, Deserialize, Serialize)]
-        pub struct RequestBody {
-            pub message: String,
-            pub parents: Vec<String>,
-            pub tree: String,
-        }
+    /// [Add labels to an issue](https://docs.github.com/en/rest/issues/labels?apiVersion=2022-11-28#add-labels-to-an-issue)
+    ///
+    /// Pull requests are issues as far as labels are concerned
+    pub fn add_labels_to_an_issue(&self, issue_number: u64, payload: &add_labels_to_an_issue::RequestBody) -> Result<add_labels_to_an_issue::ResponseBody> {
+        print_intent(&format!("Adding labels to issue #{}", issue_number), &payload);
 
-        #[derive(Debug, Deserialize, Serialize)]
-        pub struct ResponseBody {
-            pub sha: String,
-            pub html_url: String,
-            pub verification: Verification,
-        }
+        let path = format!("/repos/{}/{}/issues/{}/labels", self.github_repo.owner, self.github_repo.name, issue_number);
+        // - Adding labels that are already present is a no-op
+        let response = self.post_api_request(&path, Some(&payload), None, Retry::Allowed)?;
+        let ret = Self::deserialize_expected_response(response, &StatusCode::OK, "add labels to an issue")?;
 
-        #[derive(Debug, Deserialize, Serialize)]
-        pub struct Verification {
-            pub verified: bool,
-        }
+        print_success_and_return("Labels added", ret)
     }
 
+    /// [Create a pull request](https://docs.github.com/en/rest/pulls/pulls?apiVersion=2022-11-28#create-a-pull-request)
+    pub fn create_a_pull_request(&self, payload: &create_a_pull_request::RequestBody) -> Result<create_a_pull_request::ResponseBody> {
+        print_intent("Creating a pull request", &payload);
 
-    /// [Create a reference](https://docs.github.com/en/rest/git/refs?apiVersion=2022-11-28#create-a-reference)
-    pub mod create_a_reference {
-        use serde::{Deserialize, Serialize};
+        let path = format!("/repos/{}/{}/pulls", self.github_repo.owner, self.github_repo.name);
+        let response = self.post_api_request(&path, Some(&payload), None, Retry::Disallowed)?;
+        let ret = Self::deserialize_expected_response(response, &StatusCode::CREATED, "create a pull request")?;
 
-        use super::shared;
+        print_success_and_return("Pull request created", ret)
+    }
 
-        #[derive(Debug, Deserialize, Serialize)]
-        pub struct RequestBody {
-            #[serde(rename = "ref")]
-            pub reference: String,
-            pub sha: String,
-        }
+    /// [enablePullRequestAutoMerge](https://docs.github.com/en/graphql/reference/mutations#enablepullrequestautomerge)
+    pub fn enable_pull_request_auto_merge(&self, input: &enable_pull_request_auto_merge::Input) -> Result<enable_pull_request_auto_merge::ResponseBody> {
+        print_intent("Enabling auto-merge", &input);
 
-        pub type ResponseBody = shared::ReferenceResponseBody;
-        pub type Object = shared::ReferenceResponseBodyObject;
-    }
+        let payload = graphql_api::shared::RequestBody {
+            query: enable_pull_request_auto_merge::QUERY,
+            variables: enable_pull_request_auto_merge::Variables {
+                input: input,
+            },
+        };
 
-    /// [Create an installation access token for an app](https://docs.github.com/en/rest/apps/apps?apiVersion=2022-11-28#create-an-installation-access-token-for-an-app)
-    pub mod create_an_installation_access_token {
-        use serde::{Deserialize, Deserializer};
-        use time::OffsetDateTime;
-        use time::format_description::well_known::Rfc3339;
+        // - Enabling auto-merge again is harmless
+        let response = self.make_api_request(reqwest::Method::POST, &self.github_endpoints.graphql_api_url, Some(&payload), None, Retry::Allowed)?;
+        let ret = Self::deserialize_expected_response(response, &StatusCode::OK, "enable auto-merge")?;
 
-        /// Abbreviated representation of the response body
-        #[derive(Debug, Deserialize)]
-        pub struct ResponseBody {
-            pub token: String,
-            #[serde(deserialize_with = "deserialize_datetime")]
-            pub expires_at: OffsetDateTime,
-        }
+        print_success_and_return("Auto-merge request sent", ret)
+    }
 
-        fn deserialize_datetime<'de, D>(deserializer: D) -> Result<OffsetDateTime, D::Error>
-        where
-            D: Deserializer<'de>,
-        {
-            let s = String::deserialize(deserializer)?;
+    /// [Get a repository](https://docs.github.com/en/rest/repos/repos?apiVersion=2022-11-28#get-a-repository)
+    pub fn get_a_repository(&self) -> Result<get_a_repository::ResponseBody> {
+        print_intent("Getting repository", &format!("{}/{}", self.github_repo.owner, self.github_repo.name));
 
-            OffsetDateTime::parse(&s, &Rfc3339)
-                .map_err(serde::de::Error::custom)
-                .map(|dt| dt.into())
-        }
+        let path = format!("/repos/{}/{}", self.github_repo.owner, self.github_repo.name);
+        let response = self.get_api_request(&path, None)?;
+        let ret = Self::deserialize_expected_response(response, &StatusCode::OK, "get a repository")?;
+
+        print_success_and_return("Repository retrieved", ret)
     }
 
-    /// [Create a tree](https://docs.github.com/en/rest/git/trees?apiVersion=2022-11-28#create-a-tree)
+    /// [List pull requests](https://docs.github.com/en/rest/pulls/pulls?apiVersion=2022-11-28#list-pull-requests)
     ///
-    /// The entirety of GitHub's trees API uses snake case, so serde
-    /// renaming is only necessary for enum variants that derive `Serialize`
-    pub mod create_a_tree {
+    /// Only lists the open pull requests whose head is `branch_name` in this
+    /// repository
+    pub fn list_pull_requests(&self, branch_name: &str) -> Result<list_pull_requests::ResponseBody> {
+        print_intent("Listing open pull requests for branch", &branch_name);
+
+        let head = format!("{}:{}", self.github_repo.owner, branch_name);
+        let path = format!("/repos/{}/{}/pulls?state=open&head={}", self.github_repo.owner, self.github_repo.name, percent_encode_query_value(&head));
+        let response = self.get_api_request(&path, None)?;
+        let ret = Self::deserialize_expected_response(response, &StatusCode::OK, "list pull requests")?;
+
+        print_success_and_return("Pull requests listed", ret)
+    }
+
+    /// [Request reviewers for a pull request](https://docs.github.com/en/rest/pulls/review-requests?apiVersion=2022-11-28#request-reviewers-for-a-pull-request)
+    pub fn request_reviewers_for_a_pull_request(&self, pull_number: u64, payload: &request_reviewers_for_a_pull_request::RequestBody) -> Result<request_reviewers_for_a_pull_request::ResponseBody> {
+        print_intent(&format!("Requesting reviewers for pull request #{}", pull_number), &payload);
+
+        let path = format!("/repos/{}/{}/pulls/{}/requested_reviewers", self.github_repo.owner, self.github_repo.name, pull_number);
+        // - Requesting the same reviewers again is a no-op
+        let response = self.post_api_request(&path, Some(&payload), None, Retry::Allowed)?;
+        let ret = Self::deserialize_expected_response(response, &StatusCode::CREATED, "request reviewers for a pull request")?;
+
+        print_success_and_return("Reviewers requested", ret)
+    }
+
+    /// [Update a pull request](https://docs.github.com/en/rest/pulls/pulls?apiVersion=2022-11-28#update-a-pull-request)
+    pub fn update_a_pull_request(&self, pull_number: u64, payload: &update_a_pull_request::RequestBody) -> Result<update_a_pull_request::ResponseBody> {
+        print_intent(&format!("Updating pull request #{}", pull_number), &payload);
+
+        let path = format!("/repos/{}/{}/pulls/{}", self.github_repo.owner, self.github_repo.name, pull_number);
+        // - Setting the same title, body, and base again is harmless
+        let response = self.patch_api_request(&path, Some(&payload), None, Retry::Allowed)?;
+        let ret = Self::deserialize_expected_response(response, &StatusCode::OK, "update a pull request")?;
+
+        print_success_and_return("Pull request updated", ret)
+    }
+
+    /// URL of the repository's Git LFS API, which is what Git LFS defaults to
+    /// for a remote on GitHub
+    pub fn lfs_api_url(&self) -> String {
+        format!("{}/{}/{}.git/info/lfs", self.github_endpoints.server_url, self.github_repo.owner, self.github_repo.name)
+    }
+
+    /// Headers for Git LFS's API, which authenticates with the access token
+    /// as a password like git does over HTTPS
+    fn lfs_headers(&self) -> Result<HeaderMap> {
+        let token = self.get_access_token(false)?;
+        let credentials = base64::engine::general_purpose::STANDARD.encode(format!("x-access-token:{}", token));
+
+        let auth_header_value = match HeaderValue::from_str(&format!("Basic {}", credentials)) {
+            Ok(value) => value,
+            Err(_) => Err(Error::auth("Unable to create an Authorization header value from the token"))?,
+        };
+
+        let mut headers = HeaderMap::new();
+
+        headers.insert(header::ACCEPT, HeaderValue::from_static(lfs_api::MEDIA_TYPE));
+        headers.insert(header::CONTENT_TYPE, HeaderValue::from_static(lfs_api::MEDIA_TYPE));
+        headers.insert(header::AUTHORIZATION, auth_header_value);
+
+        Ok(headers)
+    }
+
+    /// The headers an LFS action says to send, which replace the ones for
+    /// GitHub's APIs since the action may point to other storage
+    fn lfs_action_headers(action: &lfs_api::batch::Action) -> Result<HeaderMap> {
+        let mut headers = HeaderMap::new();
+
+        for (name, value) in &action.header {
+            let name = header::HeaderName::from_bytes(name.as_bytes())
+                .map_err(|_| Error::InvalidResponse {
+                    operation: "read a Git LFS action".to_string(),
+                    message: format!("Invalid header name {:?}", name),
+                    body: String::new(),
+                })?;
+            let value = HeaderValue::from_str(value)
+                .map_err(|_| Error::InvalidResponse {
+                    operation: "read a Git LFS action".to_string(),
+                    message: format!("Invalid value for header {:?}", name),
+                    body: String::new(),
+                })?;
+
+            headers.insert(name, value);
+        }
+
+        Ok(headers)
+    }
+
+    /// [Git LFS batch API](https://github.com/git-lfs/git-lfs/blob/main/docs/api/batch.md)
+    pub fn lfs_batch(&self, payload: &lfs_api::batch::RequestBody) -> Result<lfs_api::batch::ResponseBody> {
+        print_intent("Requesting Git LFS transfers", &payload);
+
+        let url = format!("{}/objects/batch", self.lfs_api_url());
+
+        let response = self.send_with_retries(&reqwest::Method::POST, &url, Retry::Allowed, |http_client| {
+            Ok(http_client.post(&url).headers(self.lfs_headers()?).json(payload))
+        })?;
+        let ret = Self::deserialize_expected_response(response, &StatusCode::OK, "request Git LFS transfers")?;
+
+        print_success_and_return("Git LFS transfers requested", ret)
+    }
+
+    /// [Git LFS basic transfer upload](https://github.com/git-lfs/git-lfs/blob/main/docs/api/basic-transfers.md#uploads)
+    /// of the object at `path`, as the batch API's `upload` action says to
+    ///
+    /// - The file is streamed rather than read into memory since LFS objects
+    ///   tend to be large
+    pub fn upload_lfs_object(&self, action: &lfs_api::batch::Action, object: &lfs_api::batch::ObjectSpec, path: &Path) -> Result<()> {
+        print_intent("Uploading a Git LFS object", &object);
+
+        let headers = Self::lfs_action_headers(action)?;
+
+        let response = self.send_with_retries(&reqwest::Method::PUT, &action.href, Retry::Allowed, |http_client| {
+            let file = fs::File::open(path)
+                .map_err(|e| Error::Io {
+                    path: path.to_string_lossy().into_owned(),
+                    message: e.to_string(),
+                })?;
+
+            Ok(http_client.put(&action.href).headers(headers.clone()).header(header::CONTENT_TYPE, "application/octet-stream").body(file))
+        })?;
+
+        if !response.status().is_success() {
+            return Err(Self::unexpected_status_code_error(response, "upload a Git LFS object"))
+        }
+
+        print_success_plain("Git LFS object uploaded");
+
+        Ok(())
+    }
+
+    /// [Git LFS basic transfer verification](https://github.com/git-lfs/git-lfs/blob/main/docs/api/basic-transfers.md#verification)
+    /// of an uploaded object, as the batch API's `verify` action says to
+    pub fn verify_lfs_object(&self, action: &lfs_api::batch::Action, object: &lfs_api::batch::ObjectSpec) -> Result<()> {
+        print_intent("Verifying a Git LFS object", &object);
+
+        let mut headers = Self::lfs_action_headers(action)?;
+        headers.insert(header::ACCEPT, HeaderValue::from_static(lfs_api::MEDIA_TYPE));
+        headers.insert(header::CONTENT_TYPE, HeaderValue::from_static(lfs_api::MEDIA_TYPE));
+
+        let response = self.send_with_retries(&reqwest::Method::POST, &action.href, Retry::Allowed, |http_client| {
+            Ok(http_client.post(&action.href).headers(headers.clone()).json(object))
+        })?;
+
+        if !response.status().is_success() {
+            return Err(Self::unexpected_status_code_error(response, "verify a Git LFS object"))
+        }
+
+        print_success_plain("Git LFS object verified");
+
+        Ok(())
+    }
+}
+
+/// Percent-encodes everything but unreserved characters so that a value can be
+/// used in a query string
+///
+/// - https://datatracker.ietf.org/doc/html/rfc3986#section-2.3
+fn percent_encode_query_value(value: &str) -> String {
+    let mut encoded = String::with_capacity(value.len());
+
+    for byte in value.bytes() {
+        match byte {
+            b'A'..=b'Z' | b'a'..=b'z' | b'0'..=b'9' | b'-' | b'.' | b'_' | b'~' => encoded.push(byte as char),
+            _ => encoded.push_str(&format!("%{:02X}", byte)),
+        }
+    }
+
+    encoded
+}
+
+pub mod graphql_api {
+    /// [createCommitOnBranch](https://docs.github.com/en/graphql/reference/mutations#createcommitonbranch)
+    ///
+    /// GraphQL uses camel case, so structs that derive `Serialize` or
+    /// `Deserialize` are renamed accordingly
+    pub mod create_commit_on_branch {
         use std::fmt;
 
-        use serde::{Deserialize, Serialize, Serializer};
-        use serde::ser::SerializeStruct;
+        use serde::{Deserialize, Serialize};
+
+        use super::shared;
+
+        pub const QUERY: &str = "mutation($input: CreateCommitOnBranchInput!) { createCommitOnBranch(input: $input) { commit { oid url tree { oid } signature { isValid } } } }";
 
         #[derive(Debug, Serialize)]
-        pub struct RequestBody {
-            pub base_tree: String,
-            pub tree: Vec<TreeNode>,
+        pub struct Variables<'a> {
+            pub input: &'a Input,
         }
 
         #[derive(Debug, Serialize)]
-        pub enum FileMode {
-            #[serde(rename = "100644")]
-            Blob,
-            #[serde(rename = "100755")]
-            BlobExecutable,
-            #[serde(rename = "160000")]
-            Commit,
-            #[serde(rename = "120000")]
-            Link,
-            #[serde(rename = "040000")]
-            Tree,
+        #[serde(rename_all = "camelCase")]
+        pub struct Input {
+            pub branch: CommittableBranch,
+            pub expected_head_oid: String,
+            pub file_changes: FileChanges,
+            pub message: CommitMessage,
         }
 
         #[derive(Debug, Serialize)]
-        #[serde(rename_all = "lowercase")]
-        pub enum NodeType {
-            Blob,
-            Commit,
-            Tree,
+        #[serde(rename_all = "camelCase")]
+        pub struct CommittableBranch {
+            pub repository_name_with_owner: String,
+            pub branch_name: String,
         }
 
-        // - Since `TreeNode` needs to manually implement serialization for
-        //   this type, there is no need for anything serde-related
-        pub enum ShaOrContent {
-            Content(String),
-            Sha(Option<String>),
+        #[derive(Debug, Serialize)]
+        pub struct CommitMessage {
+            pub headline: String,
+            #[serde(skip_serializing_if = "Option::is_none")]
+            pub body: Option<String>,
+        }
+
+        #[derive(Debug, Default, Serialize)]
+        pub struct FileChanges {
+            pub additions: Vec<FileAddition>,
+            pub deletions: Vec<FileDeletion>,
+        }
+
+        #[derive(Serialize)]
+        pub struct FileAddition {
+            pub path: String,
+            /// Base64-encoded file contents
+            pub contents: String,
         }
 
         // - Rather than printing out the full contents which may be large or
-        //   contain sensitive information if some were committed, accidentally
-        //   or otherwise, just print the size
-        impl fmt::Debug for ShaOrContent {
+        //   contain sensitive information, just print the size
+        impl fmt::Debug for FileAddition {
             fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
-                match self {
-                    ShaOrContent::Content(s) => {
-                        f.debug_tuple("Content")
-                            .field(&format_args!("(content size: {})", s.len()))
-                            .finish()
-                    },
-                    ShaOrContent::Sha(option) => {
-                        f.debug_tuple("Sha")
-                            .field(option)
-                            .finish()
-                    },
-                }
+                f.debug_struct("FileAddition")
+                    .field("path", &self.path)
+                    .field("contents", &format_args!("(content size: {})", self.contents.len()))
+                    .finish()
             }
         }
 
-        #[derive(Debug)]
-        pub struct TreeNode {
+        #[derive(Debug, Serialize)]
+        pub struct FileDeletion {
             pub path: String,
-            pub file_mode: FileMode,
-            pub node_type: NodeType,
-            pub sha_or_content: ShaOrContent,
         }
 
-        // - Since `sha_or_content` needs to serialize to one and only one
-        //   of `sha` or `content`, which serde doesn't support, serialize
-        //   must be implemented manually
-        impl Serialize for TreeNode {
-            fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
-            where
-                S: Serializer
-            {
-                let mut state = serializer.serialize_struct("TreeNode", 4)?;
+        /// Abbreviated representation of the response data
+        #[derive(Debug, Deserialize, Serialize)]
+        #[serde(rename_all = "camelCase")]
+        pub struct ResponseData {
+            /// `None` when the mutation fails
+            pub create_commit_on_branch: Option<Payload>,
+        }
 
-                state.serialize_field("path", &self.path)?;
-                state.serialize_field("mode", &self.file_mode)?;
-                state.serialize_field("type", &self.node_type)?;
+        #[derive(Debug, Deserialize, Serialize)]
+        pub struct Payload {
+            pub commit: Commit,
+        }
 
-                match &self.sha_or_content {
-                    ShaOrContent::Sha(sha) => state.serialize_field("sha", sha)?,
-                    ShaOrContent::Content(content) => state.serialize_field("content", content)?,
-                };
+        #[derive(Debug, Deserialize, Serialize)]
+        pub struct Commit {
+            pub oid: String,
+            pub url: String,
+            pub tree: Tree,
+            /// `None` when the commit isn't signed
+            pub signature: Option<Signature>,
+        }
 
-                state.end()
-            }
+        #[derive(Debug, Deserialize, Serialize)]
+        pub struct Tree {
+            pub oid: String,
         }
 
-        /// Abbreviated representation of the response body
         #[derive(Debug, Deserialize, Serialize)]
-        pub struct ResponseBody {
-            pub sha: String,
-            pub truncated: bool,
-            pub url: String,
+        #[serde(rename_all = "camelCase")]
+        pub struct Signature {
+            pub is_valid: bool,
         }
+
+        pub type ResponseBody = shared::ResponseBody<ResponseData>;
     }
 
-    /// [Get a reference](https://docs.github.com/en/rest/git/refs?apiVersion=2022-11-28#get-a-reference)
-    pub mod get_a_reference {
+    /// [enablePullRequestAutoMerge](https://docs.github.com/en/graphql/reference/mutations#enablepullrequestautomerge)
+    pub mod enable_pull_request_auto_merge {
         use serde::{Deserialize, Serialize};
 
         use super::shared;
 
-        pub type ResponseBodyOk = shared::ReferenceResponseBody;
-        pub type Object = shared::ReferenceResponseBodyObject;
+        pub const QUERY: &str = "mutation($input: EnablePullRequestAutoMergeInput!) { enablePullRequestAutoMerge(input: $input) { pullRequest { number } } }";
+
+        #[derive(Debug, Serialize)]
+        pub struct Variables<'a> {
+            pub input: &'a Input,
+        }
+
+        #[derive(Debug, Serialize)]
+        #[serde(rename_all = "camelCase")]
+        pub struct Input {
+            pub pull_request_id: String,
+            pub merge_method: MergeMethod,
+        }
+
+        #[derive(Clone, Copy, Debug, Eq, PartialEq, Serialize, clap::ValueEnum)]
+        #[serde(rename_all = "UPPERCASE")]
+        pub enum MergeMethod {
+            Merge,
+            Squash,
+            Rebase,
+        }
+
+        #[derive(Debug, Deserialize, Serialize)]
+        #[serde(rename_all = "camelCase")]
+        pub struct ResponseData {
+            /// `None` when the mutation fails
+            pub enable_pull_request_auto_merge: Option<Payload>,
+        }
+
+        #[derive(Debug, Deserialize, Serialize)]
+        #[serde(rename_all = "camelCase")]
+        pub struct Payload {
+            pub pull_request: PullRequest,
+        }
+
+        #[derive(Debug, Deserialize, Serialize)]
+        pub struct PullRequest {
+            pub number: u64,
+        }
+
+        pub type ResponseBody = shared::ResponseBody<ResponseData>;
+    }
+
+    pub mod shared {
+        use serde::{Deserialize, Serialize};
+
+        #[derive(Debug, Serialize)]
+        pub struct RequestBody<'a, V> {
+            pub query: &'a str,
+            pub variables: V,
+        }
+
+        /// [Error type returned when the branch has moved past `expectedHeadOid`](https://docs.github.com/en/graphql/reference/input-objects#createcommitonbranchinput)
+        pub const STALE_DATA_ERROR_TYPE: &str = "STALE_DATA";
+
+        #[derive(Debug, Deserialize, Serialize)]
+        pub struct ResponseBody<D> {
+            pub data: Option<D>,
+            #[serde(default)]
+            pub errors: Vec<Error>,
+        }
+
+        /// Abbreviated representation of a GraphQL error
+        #[derive(Debug, Deserialize, Serialize)]
+        pub struct Error {
+            pub message: String,
+            #[serde(rename = "type", default, skip_serializing_if = "Option::is_none")]
+            pub error_type: Option<String>,
+        }
+    }
+}
+
+pub mod rest_api {
+    /// [Compare two commits](https://docs.github.com/en/rest/commits/commits?apiVersion=2022-11-28#compare-two-commits)
+    pub mod compare_two_commits {
+        use serde::{Deserialize, Serialize};
+
+        #[derive(Clone, Copy, Debug, Deserialize, Eq, PartialEq, Serialize)]
+        #[serde(rename_all = "lowercase")]
+        pub enum Status {
+            Ahead,
+            Behind,
+            Diverged,
+            Identical,
+        }
 
         /// Abbreviated representation of the response body
+        ///
+        /// GitHub includes at most 300 files, so `files` may be incomplete
         #[derive(Debug, Deserialize, Serialize)]
-        pub struct ResponseBodyNotFound {}
+        pub struct ResponseBody {
+            pub status: Status,
+            pub ahead_by: u64,
+            pub behind_by: u64,
+            #[serde(default)]
+            pub files: Vec<File>,
+        }
 
-        #[derive(Debug)]
-        pub enum ResponseBody {
-            Ok(ResponseBodyOk),
-            NotFound(ResponseBodyNotFound),
+        /// Abbreviated representation of a changed file
+        #[derive(Debug, Deserialize, Serialize)]
+        pub struct File {
+            pub filename: String,
+            pub status: String,
+            #[serde(default, skip_serializing_if = "Option::is_none")]
+            pub previous_filename: Option<String>,
         }
+
+        /// The maximum number of files GitHub includes in the response
+        pub const MAX_FILES: usize = 300;
     }
 
-    /// [Update a reference](https://docs.github.com/en/rest/git/refs?apiVersion=2022-11-28#update-a-reference)
-    pub mod update_a_reference {
-        use serde::Serialize;
+    /// [Create a blob](https://docs.github.com/en/rest/git/blobs?apiVersion=2022-11-28#create-a-blob)
+    pub mod create_a_blob {
+        use serde::{Deserialize, Serialize};
 
-        use super::shared;
+        #[derive(Debug, Serialize)]
+        pub enum Encoding {
+            #[serde(rename = "base64")]
+            Base64,
+            #[serde(rename = "utf-8")]
+            Utf8,
+        }
 
         #[derive(Debug, Serialize)]
+        pub struct RequestBody<'a> {
+            pub content: &'a str,
+            pub encoding: Encoding,
+        }
+
+        #[derive(Debug, Deserialize, Serialize)]
+        pub struct ResponseBody {
+            pub url: String,
+            pub sha: String,
+        }
+    }
+
+    /// [Create a commit](https://docs.github.com/en/rest/git/commits?apiVersion=2022-11-28#create-a-commit)
+    pub mod create_a_commit {
+        use serde::{Deserialize, Serialize};
+
+        /// Abbreviated representation of the response body
+        #[derive(Debug, Deserialize, Serialize)]
         pub struct RequestBody {
+            pub message: String,
+            pub parents: Vec<String>,
+            pub tree: String,
+            /// Defaults to the authenticated app or user at the current time
+            #[serde(skip_serializing_if = "Option::is_none")]
+            pub author: Option<Signature>,
+            /// Defaults to the author
+            #[serde(skip_serializing_if = "Option::is_none")]
+            pub committer: Option<Signature>,
+        }
+
+        #[derive(Clone, Debug, Deserialize, Serialize)]
+        pub struct Signature {
+            pub name: String,
+            pub email: String,
+            /// ISO 8601, such as `2014-11-07T22:01:45Z`; defaults to the
+            /// current time
+            #[serde(skip_serializing_if = "Option::is_none")]
+            pub date: Option<String>,
+        }
+
+        #[derive(Debug, Deserialize, Serialize)]
+        pub struct ResponseBody {
             pub sha: String,
-            pub force: bool,
+            pub html_url: String,
+            pub tree: Tree,
+            pub verification: Verification,
         }
 
-        pub type ResponseBody = shared::ReferenceResponseBody;
-        pub type Object = shared::ReferenceResponseBodyObject;
+        #[derive(Debug, Deserialize, Serialize)]
+        pub struct Tree {
+            pub sha: String,
+        }
+
+        #[derive(Debug, Deserialize, Serialize)]
+        pub struct Verification {
+            pub verified: bool,
+        }
+    }
+
+
+    /// [Create a reference](https://docs.github.com/en/rest/git/refs?apiVersion=2022-11-28#create-a-reference)
+    pub mod create_a_reference {
+        use serde::{Deserialize, Serialize};
+
+        use super::shared;
+
+        #[derive(Debug, Deserialize, Serialize)]
+        pub struct RequestBody {
+            #[serde(rename = "ref")]
+            pub reference: String,
+            pub sha: String,
+        }
+
+        pub type ResponseBodyOk = shared::ReferenceResponseBody;
+        pub type ResponseBodyUnprocessableEntity = shared::ErrorResponseBody;
+        pub type Object = shared::ReferenceResponseBodyObject;
+
+        #[derive(Debug)]
+        pub enum ResponseBody {
+            Ok(ResponseBodyOk),
+            UnprocessableEntity(ResponseBodyUnprocessableEntity),
+        }
+    }
+
+    /// [Create an installation access token for an app](https://docs.github.com/en/rest/apps/apps?apiVersion=2022-11-28#create-an-installation-access-token-for-an-app)
+    pub mod create_an_installation_access_token {
+        use serde::{Deserialize, Deserializer};
+        use time::OffsetDateTime;
+        use time::format_description::well_known::Rfc3339;
+
+        /// Abbreviated representation of the response body
+        #[derive(Debug, Deserialize)]
+        pub struct ResponseBody {
+            pub token: String,
+            #[serde(deserialize_with = "deserialize_datetime")]
+            pub expires_at: OffsetDateTime,
+        }
+
+        #[allow(clippy::useless_conversion)]
+        fn deserialize_datetime<'de, D>(deserializer: D) -> Result<OffsetDateTime, D::Error>
+        where
+            D: Deserializer<'de>,
+        {
+            let s = String::deserialize(deserializer)?;
+
+            OffsetDateTime::parse(&s, &Rfc3339)
+                .map_err(serde::de::Error::custom)
+                .map(|dt| dt.into())
+        }
+    }
+
+    /// [Create a tree](https://docs.github.com/en/rest/git/trees?apiVersion=2022-11-28#create-a-tree)
+    ///
+    /// The entirety of GitHub's trees API uses snake case, so serde
+    /// renaming is only necessary for enum variants that derive `Serialize`
+    pub mod create_a_tree {
+        use std::fmt;
+
+        use serde::{Deserialize, Serialize, Serializer};
+        use serde::ser::SerializeStruct;
+
+        #[derive(Debug, Serialize)]
+        pub struct RequestBody {
+            pub base_tree: String,
+            pub tree: Vec<TreeNode>,
+        }
+
+        #[derive(Debug, Serialize)]
+        pub enum FileMode {
+            #[serde(rename = "100644")]
+            Blob,
+            #[serde(rename = "100755")]
+            BlobExecutable,
+            #[serde(rename = "160000")]
+            Commit,
+            #[serde(rename = "120000")]
+            Link,
+            #[serde(rename = "040000")]
+            Tree,
+        }
+
+        #[derive(Debug, Serialize)]
+        #[serde(rename_all = "lowercase")]
+        pub enum NodeType {
+            Blob,
+            Commit,
+            Tree,
+        }
+
+        // - Since `TreeNode` needs to manually implement serialization for
+        //   this type, there is no need for anything serde-related
+        pub enum ShaOrContent {
+            Content(String),
+            Sha(Option<String>),
+        }
+
+        // - Rather than printing out the full contents which may be large or
+        //   contain sensitive information if some were committed, accidentally
+        //   or otherwise, just print the size
+        impl fmt::Debug for ShaOrContent {
+            fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+                match self {
+                    ShaOrContent::Content(s) => {
+                        f.debug_tuple("Content")
+                            .field(&format_args!("(content size: {})", s.len()))
+                            .finish()
+                    },
+                    ShaOrContent::Sha(option) => {
+                        f.debug_tuple("Sha")
+                            .field(option)
+                            .finish()
+                    },
+                }
+            }
+        }
+
+        #[derive(Debug)]
+        pub struct TreeNode {
+            pub path: String,
+            pub file_mode: FileMode,
+            pub node_type: NodeType,
+            pub sha_or_content: ShaOrContent,
+        }
+
+        // - Since `sha_or_content` needs to serialize to one and only one
+        //   of `sha` or `content`, which serde doesn't support, serialize
+        //   must be implemented manually
+        impl Serialize for TreeNode {
+            fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
+            where
+                S: Serializer
+            {
+                let mut state = serializer.serialize_struct("TreeNode", 4)?;
+
+                state.serialize_field("path", &self.path)?;
+                state.serialize_field("mode", &self.file_mode)?;
+                state.serialize_field("type", &self.node_type)?;
+
+                match &self.sha_or_content {
+                    ShaOrContent::Sha(sha) => state.serialize_field("sha", sha)?,
+                    ShaOrContent::Content(content) => state.serialize_field("content", content)?,
+                };
+
+                state.end()
+            }
+        }
+
+        /// Abbreviated representation of the response body
+        #[derive(Debug, Deserialize, Serialize)]
+        pub struct ResponseBody {
+            pub sha: String,
+            pub truncated: bool,
+            pub url: String,
+        }
+    }
+
+    /// [Get a repository installation for the authenticated app](https://docs.github.com/en/rest/apps/apps?apiVersion=2022-11-28#get-a-repository-installation-for-the-authenticated-app)
+    pub mod get_a_repository_installation {
+        use serde::{Deserialize, Serialize};
+
+        /// Abbreviated representation of the response body
+        #[derive(Debug, Deserialize, Serialize)]
+        pub struct ResponseBody {
+            pub id: u64,
+            pub app_id: u64,
+        }
+    }
+
+    /// [Get a commit object](https://docs.github.com/en/rest/git/commits?apiVersion=2022-11-28#get-a-commit-object)
+    pub mod get_a_commit_object {
+        use serde::{Deserialize, Serialize};
+
+        use super::create_a_commit::{Signature, Tree};
+
+        /// Abbreviated representation of the response body
+        #[derive(Debug, Deserialize, Serialize)]
+        pub struct ResponseBody {
+            pub sha: String,
+            pub tree: Tree,
+            pub parents: Vec<Parent>,
+            pub author: Signature,
+            pub committer: Signature,
+            pub message: String,
+            pub verification: Verification,
+        }
+
+        #[derive(Debug, Deserialize, Serialize)]
+        pub struct Parent {
+            pub sha: String,
+        }
+
+        #[derive(Debug, Deserialize, Serialize)]
+        pub struct Verification {
+            pub verified: bool,
+            /// The commit's signature, if it's signed
+            pub signature: Option<String>,
+            /// The commit object without its signature header, which is what
+            /// was signed
+            pub payload: Option<String>,
+        }
+    }
+
+    /// [Get a reference](https://docs.github.com/en/rest/git/refs?apiVersion=2022-11-28#get-a-reference)
+    pub mod get_a_reference {
+        use serde::{Deserialize, Serialize};
+
+        use super::shared;
+
+        pub type ResponseBodyOk = shared::ReferenceResponseBody;
+        pub type Object = shared::ReferenceResponseBodyObject;
+
+        /// Abbreviated representation of the response body
+        #[derive(Debug, Deserialize, Serialize)]
+        pub struct ResponseBodyNotFound {}
+
+        #[derive(Debug)]
+        pub enum ResponseBody {
+            Ok(ResponseBodyOk),
+            NotFound(ResponseBodyNotFound),
+        }
+    }
+
+    /// [Update a reference](https://docs.github.com/en/rest/git/refs?apiVersion=2022-11-28#update-a-reference)
+    pub mod update_a_reference {
+        use serde::Serialize;
+
+        use super::shared;
+
+        #[derive(Debug, Serialize)]
+        pub struct RequestBody {
+            pub sha: String,
+            pub force: bool,
+        }
+
+        pub type ResponseBodyOk = shared::ReferenceResponseBody;
+        pub type ResponseBodyUnprocessableEntity = shared::ErrorResponseBody;
+        pub type Object = shared::ReferenceResponseBodyObject;
+
+        #[derive(Debug)]
+        pub enum ResponseBody {
+            Ok(ResponseBodyOk),
+            UnprocessableEntity(ResponseBodyUnprocessableEntity),
+        }
+    }
+
+    /// [Add labels to an issue](https://docs.github.com/en/rest/issues/labels?apiVersion=2022-11-28#add-labels-to-an-issue)
+    pub mod add_labels_to_an_issue {
+        use serde::{Deserialize, Serialize};
+
+        #[derive(Debug, Serialize)]
+        pub struct RequestBody {
+            pub labels: Vec<String>,
+        }
+
+        /// Abbreviated representation of the response body
+        #[derive(Debug, Deserialize, Serialize)]
+        pub struct Label {
+            pub name: String,
+        }
+
+        pub type ResponseBody = Vec<Label>;
+    }
+
+    /// [Create a pull request](https://docs.github.com/en/rest/pulls/pulls?apiVersion=2022-11-28#create-a-pull-request)
+    pub mod create_a_pull_request {
+        use serde::Serialize;
+
+        use super::shared;
+
+        #[derive(Debug, Serialize)]
+        pub struct RequestBody {
+            pub title: String,
+            pub head: String,
+            pub base: String,
+            #[serde(skip_serializing_if = "Option::is_none")]
+            pub body: Option<String>,
+            pub draft: bool,
+        }
+
+        pub type ResponseBody = shared::PullRequestResponseBody;
+    }
+
+    /// [Get a repository](https://docs.github.com/en/rest/repos/repos?apiVersion=2022-11-28#get-a-repository)
+    pub mod get_a_repository {
+        use serde::{Deserialize, Serialize};
+
+        /// Abbreviated representation of the response body
+        #[derive(Debug, Deserialize, Serialize)]
+        pub struct ResponseBody {
+            pub default_branch: String,
+        }
+    }
+
+    /// [List pull requests](https://docs.github.com/en/rest/pulls/pulls?apiVersion=2022-11-28#list-pull-requests)
+    pub mod list_pull_requests {
+        use super::shared;
+
+        pub type ResponseBody = Vec<shared::PullRequestResponseBody>;
+    }
+
+    /// [Request reviewers for a pull request](https://docs.github.com/en/rest/pulls/review-requests?apiVersion=2022-11-28#request-reviewers-for-a-pull-request)
+    pub mod request_reviewers_for_a_pull_request {
+        use serde::Serialize;
+
+        use super::shared;
+
+        #[derive(Debug, Serialize)]
+        pub struct RequestBody {
+            pub reviewers: Vec<String>,
+            pub team_reviewers: Vec<String>,
+        }
+
+        pub type ResponseBody = shared::PullRequestResponseBody;
+    }
+
+    /// [Update a pull request](https://docs.github.com/en/rest/pulls/pulls?apiVersion=2022-11-28#update-a-pull-request)
+    pub mod update_a_pull_request {
+        use serde::Serialize;
+
+        use super::shared;
+
+        /// Only the fields that are set are changed
+        #[derive(Debug, Serialize)]
+        pub struct RequestBody {
+            #[serde(skip_serializing_if = "Option::is_none")]
+            pub title: Option<String>,
+            #[serde(skip_serializing_if = "Option::is_none")]
+            pub body: Option<String>,
+            #[serde(skip_serializing_if = "Option::is_none")]
+            pub base: Option<String>,
+        }
+
+        pub type ResponseBody = shared::PullRequestResponseBody;
+    }
+
+    pub mod shared {
+        use serde::{Deserialize, Serialize};
+
+        /// Abbreviated representation of GitHub's error response body
+        #[derive(Debug, Deserialize, Serialize)]
+        pub struct ErrorResponseBody {
+            pub message: String,
+        }
+
+        /// Abbreviated representation of a pull request
+        #[derive(Debug, Deserialize, Serialize)]
+        pub struct PullRequestResponseBody {
+            pub number: u64,
+            pub html_url: String,
+            pub node_id: String,
+        }
+
+        #[derive(Debug, Deserialize, Serialize)]
+        pub struct ReferenceResponseBody {
+            #[serde(rename = "ref")]
+            pub reference: String,
+            pub node_id: String,
+            pub url: String,
+            pub object: ReferenceResponseBodyObject,
+        }
+
+        #[derive(Debug, Deserialize, Serialize)]
+        pub struct ReferenceResponseBodyObject {
+            #[serde(rename = "type")]
+            pub object_type: String,
+            pub sha: String,
+            pub url: String,
+        }
+    }
+}
+
+/// [Git LFS's API](https://github.com/git-lfs/git-lfs/tree/main/docs/api),
+/// which GitHub serves under each repository's URL rather than its REST API
+pub mod lfs_api {
+    pub const MEDIA_TYPE: &str = "application/vnd.git-lfs+json";
+
+    /// [Batch API](https://github.com/git-lfs/git-lfs/blob/main/docs/api/batch.md)
+    pub mod batch {
+        use std::collections::HashMap;
+        use std::fmt;
+
+        use serde::{Deserialize, Serialize};
+
+        /// The most objects to ask about in one request, as recommended by the
+        /// specification
+        pub const MAX_OBJECTS_PER_REQUEST: usize = 100;
+
+        #[derive(Debug, Serialize)]
+        #[serde(rename_all = "lowercase")]
+        pub enum Operation {
+            Upload,
+        }
+
+        #[derive(Debug, Serialize)]
+        pub struct RequestBody {
+            pub operation: Operation,
+            pub transfers: Vec<String>,
+            #[serde(rename = "ref", skip_serializing_if = "Option::is_none")]
+            pub reference: Option<Reference>,
+            pub objects: Vec<ObjectSpec>,
+        }
+
+        #[derive(Debug, Serialize)]
+        pub struct Reference {
+            pub name: String,
+        }
+
+        #[derive(Clone, Debug, Deserialize, Eq, PartialEq, Serialize)]
+        pub struct ObjectSpec {
+            pub oid: String,
+            pub size: u64,
+        }
+
+        /// Abbreviated representation of the response body
+        #[derive(Debug, Deserialize, Serialize)]
+        pub struct ResponseBody {
+            pub objects: Vec<Object>,
+        }
+
+        #[derive(Debug, Deserialize, Serialize)]
+        pub struct Object {
+            pub oid: String,
+            pub size: u64,
+            /// Absent when the server already has the object
+            #[serde(default, skip_serializing_if = "Option::is_none")]
+            pub actions: Option<Actions>,
+            #[serde(default, skip_serializing_if = "Option::is_none")]
+            pub error: Option<ObjectError>,
+        }
+
+        #[derive(Debug, Deserialize, Serialize)]
+        pub struct Actions {
+            #[serde(default, skip_serializing_if = "Option::is_none")]
+            pub upload: Option<Action>,
+            #[serde(default, skip_serializing_if = "Option::is_none")]
+            pub verify: Option<Action>,
+        }
+
+        #[derive(Deserialize, Serialize)]
+        pub struct Action {
+            pub href: String,
+            #[serde(default)]
+            pub header: HashMap<String, String>,
+        }
+
+        // - The headers usually hold credentials for the storage the object
+        //   goes to, so only print their names
+        impl fmt::Debug for Action {
+            fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+                let mut header_names: Vec<&String> = self.header.keys().collect();
+                header_names.sort();
+
+                f.debug_struct("Action")
+                    .field("href", &self.href)
+                    .field("header", &format_args!("(names: {:?})", header_names))
+                    .finish()
+            }
+        }
+
+        #[derive(Debug, Deserialize, Serialize)]
+        pub struct ObjectError {
+            pub code: u16,
+            pub message: String,
+        }
+    }
+}
+
+#[cfg(test)]
+#[allow(clippy::needless_borrow)]
+mod test_util {
+    use serde_json::Value;
+
+    /// Serialized strings may not have the same order, so deserialize and
+    /// compare
+    pub fn assert_eq_deserialized(a: &str, b: &str) {
+        let a_deserialized: Value = serde_json::from_str(&a).unwrap();
+        let b_deserialized: Value = serde_json::from_str(&b).unwrap();
+
+        assert_eq!(a_deserialized, b_deserialized);
+    }
+
+    pub fn quote(s: &str) -> String {
+        format!("\"{}\"", s)
+    }
+
+}
+
+#[cfg(test)]
+mod access_token_tests {
+    use std::sync::Arc;
+
+    use time::{Duration, OffsetDateTime};
+
+    use super::AccessToken;
+
+    #[test]
+    fn expires_soon() {
+        let access_token = AccessToken {
+            token: Arc::new("".to_string()),
+            expires_at: OffsetDateTime::now_utc(),
+        };
+
+        assert!(access_token.expires_soon())
+    }
+
+    #[test]
+    fn does_not_expire_soon() {
+        let access_token = AccessToken {
+            token: Arc::new("".to_string()),
+            expires_at: OffsetDateTime::now_utc() + Duration::minutes(5),
+        };
+
+        assert!(!access_token.expires_soon())
+    }
+}
+
+#[cfg(test)]
+mod authentication_tests {
+    use std::sync::Arc;
+
+    use super::{Authentication, GitHubClient, GitHubEndpoints, GitHubRepo};
+
+    fn token_github_client(token: &str) -> GitHubClient {
+        let github_repo = GitHubRepo {
+            owner: "gemini-oss".to_string(),
+            name: "ghommit".to_string(),
+        };
+
+        GitHubClient::new(Authentication::Token(Arc::new(token.to_string())), GitHubEndpoints::default(), github_repo)
+    }
+
+    #[test]
+    fn token_is_used_as_is() {
+        let github_client = token_github_client("ghp_example");
+
+        let access_token = github_client.get_access_token(true).unwrap();
+
+        assert_eq!(*access_token, "ghp_example");
+    }
+
+    #[test]
+    fn token_does_not_create_jwts() {
+        let github_client = token_github_client("ghp_example");
+
+        assert!(github_client.get_jwt().is_err());
+    }
+
+    #[test]
+    fn token_debug_representation() {
+        let token = "ghp_example";
+        let debug_output = format!("{:?}", Authentication::Token(Arc::new(token.to_string())));
+
+        assert!(!debug_output.contains(token));
+    }
+}
+
+#[cfg(test)]
+mod github_endpoints_tests {
+    use super::GitHubEndpoints;
+
+    #[test]
+    fn github_dot_com() {
+        let endpoints = GitHubEndpoints::for_host("github.com");
+
+        assert_eq!(endpoints.rest_api_base_url, "https://api.github.com");
+        assert_eq!(endpoints.graphql_api_url, "https://api.github.com/graphql");
+        assert_eq!(endpoints.server_url, "https://github.com");
+    }
+
+    #[test]
+    fn github_enterprise_server() {
+        let endpoints = GitHubEndpoints::for_host("github.example.com");
+
+        assert_eq!(endpoints.rest_api_base_url, "https://github.example.com/api/v3");
+        assert_eq!(endpoints.graphql_api_url, "https://github.example.com/api/graphql");
+        assert_eq!(endpoints.server_url, "https://github.example.com");
+    }
+
+    #[test]
+    fn github_enterprise_server_rest_api_base_url() {
+        let endpoints = GitHubEndpoints::for_rest_api_base_url("https://github.example.com:8443/api/v3/");
+
+        assert_eq!(endpoints.rest_api_base_url, "https://github.example.com:8443/api/v3");
+        assert_eq!(endpoints.graphql_api_url, "https://github.example.com:8443/api/graphql");
+        assert_eq!(endpoints.server_url, "https://github.example.com:8443");
+    }
+
+    #[test]
+    fn other_rest_api_base_url() {
+        let endpoints = GitHubEndpoints::for_rest_api_base_url("http://127.0.0.1:8080");
+
+        assert_eq!(endpoints.rest_api_base_url, "http://127.0.0.1:8080");
+        assert_eq!(endpoints.graphql_api_url, "http://127.0.0.1:8080/graphql");
+        assert_eq!(endpoints.server_url, "http://127.0.0.1:8080");
+    }
+
+    #[test]
+    fn github_dot_com_rest_api_base_url() {
+        let endpoints = GitHubEndpoints::for_rest_api_base_url("https://api.github.com");
+
+        assert_eq!(endpoints, GitHubEndpoints::for_host("github.com"));
+    }
+}
+
+#[cfg(test)]
+mod http_client_options_tests {
+    use std::fs;
+
+    use crate::error::Error;
+    use crate::test_utils::test_utils::github_client;
+
+    use super::HttpClientOptions;
+
+    #[test]
+    fn http_client_is_built_once() {
+        let github_client = github_client()
+            .with_user_agent("ghommit-test");
+
+        let first = github_client.get_http_client().unwrap() as *const reqwest::blocking::Client;
+        let second = github_client.get_http_client().unwrap() as *const reqwest::blocking::Client;
+
+        assert_eq!(first, second);
+    }
+
+    #[test]
+    fn invalid_proxy_is_a_config_error() {
+        let options = HttpClientOptions {
+            proxy: Some("not a url".to_string()),
+            ..HttpClientOptions::default()
+        };
+
+        assert!(matches!(options.build(), Err(Error::Config { .. })));
     }
 
-    pub mod shared {
-        use serde::{Deserialize, Serialize};
+    #[test]
+    fn ca_bundle_must_contain_certificates() {
+        let directory = tempfile::tempdir().unwrap();
 
-        #[derive(Debug, Deserialize, Serialize)]
-        pub struct ReferenceResponseBody {
-            #[serde(rename = "ref")]
-            pub reference: String,
-            pub node_id: String,
-            pub url: String,
-            pub object: ReferenceResponseBodyObject,
-        }
+        let missing = HttpClientOptions {
+            ca_bundle: Some(directory.path().join("missing.pem")),
+            ..HttpClientOptions::default()
+        };
 
-        #[derive(Debug, Deserialize, Serialize)]
-        pub struct ReferenceResponseBodyObject {
-            #[serde(rename = "type")]
-            pub object_type: String,
-            pub sha: String,
-            pub url: String,
-        }
+        assert!(matches!(missing.build(), Err(Error::Io { .. })));
+
+        let empty_path = directory.path().join("empty.pem");
+        fs::write(&empty_path, "").unwrap();
+
+        let empty = HttpClientOptions {
+            ca_bundle: Some(empty_path),
+            ..HttpClientOptions::default()
+        };
+
+        assert!(matches!(empty.build(), Err(Error::Config { .. })));
     }
 }
 
 #[cfg(test)]
-mod test_util {
-    use serde_json::Value;
+mod compare_two_commits_tests {
+    use super::rest_api::compare_two_commits::{File, ResponseBody, Status};
+    use super::test_util::assert_eq_deserialized;
 
-    /// Serialized strings may not have the same order, so deserialize and
-    /// compare
-    pub fn assert_eq_deserialized(a: &str, b: &str) {
-        let a_deserialized: Value = serde_json::from_str(&a).unwrap();
-        let b_deserialized: Value = serde_json::from_str(&b).unwrap();
+    #[test]
+    fn compare_two_commits_deserialization_with_github_example_payload() {
+        let actual = {
+            // From the docs: https://docs.github.com/en/rest/commits/commits?apiVersion=2022-11-28#compare-two-commits
+            let original = r#"
+                {
+                  "url": "https://api.github.com/repos/octocat/Hello-World/compare/master...topic",
+                  "html_url": "https://github.com/octocat/Hello-World/compare/master...topic",
+                  "status": "behind",
+                  "ahead_by": 1,
+                  "behind_by": 2,
+                  "total_commits": 1,
+                  "files": [
+                    {
+                      "sha": "bbcd538c8e72b8c175046e27cc8f907076331401",
+                      "filename": "file1.txt",
+                      "status": "added",
+                      "additions": 103,
+                      "deletions": 21,
+                      "changes": 124,
+                      "blob_url": "https://github.com/octocat/Hello-World/blob/6dcb09b5b57875f334f61aebed695e2e4193db5e/file1.txt",
+                      "raw_url": "https://github.com/octocat/Hello-World/raw/6dcb09b5b57875f334f61aebed695e2e4193db5e/file1.txt",
+                      "contents_url": "https://api.github.com/repos/octocat/Hello-World/contents/file1.txt?ref=6dcb09b5b57875f334f61aebed695e2e4193db5e",
+                      "patch": "@@ -132,7 +132,7 @@ module Test @@ -1000,7 +1000,7 @@ module Test"
+                    }
+                  ]
+                }
+            "#;
 
-        assert_eq!(a_deserialized, b_deserialized);
-    }
+            let actual_deserialized = serde_json::from_str::<ResponseBody>(original).unwrap();
 
-    pub fn quote(s: &str) -> String {
-        format!("\"{}\"", s)
-    }
+            serde_json::to_string(&actual_deserialized).unwrap()
+        };
+
+        let expected = {
+            let expected_deserialized = ResponseBody {
+                status: Status::Behind,
+                ahead_by: 1,
+                behind_by: 2,
+                files: vec![
+                    File {
+                        filename: "file1.txt".to_string(),
+                        status: "added".to_string(),
+                        previous_filename: None,
+                    },
+                ],
+            };
+
+            serde_json::to_string(&expected_deserialized).unwrap()
+        };
 
+        assert_eq_deserialized(&actual, &expected);
+    }
 }
 
 #[cfg(test)]
-mod access_token_tests {
-    use std::sync::Arc;
+mod create_commit_on_branch_tests {
+    use super::graphql_api::create_commit_on_branch::{CommitMessage, CommittableBranch, FileAddition, FileChanges, FileDeletion, Input, ResponseBody};
+    use super::test_util::assert_eq_deserialized;
 
-    use time::{Duration, OffsetDateTime};
+    #[test]
+    fn create_commit_on_branch_input_serialization_with_github_example_payload() {
+        // From the docs: https://docs.github.com/en/graphql/reference/input-objects#filechanges
+        let expected = r#"
+            {
+              "branch": {
+                "repositoryNameWithOwner": "octocat/Hello-World",
+                "branchName": "main"
+              },
+              "expectedHeadOid": "7638417db6d59f3c431d3e1f261cc637155684cd",
+              "fileChanges": {
+                "additions": [
+                  {
+                    "path": "docs/README.txt",
+                    "contents": "SGVsbG8gV29ybGQK"
+                  }
+                ],
+                "deletions": [
+                  {
+                    "path": "docs/EXAMPLE.txt"
+                  }
+                ]
+              },
+              "message": {
+                "headline": "my commit message"
+              }
+            }
+        "#;
 
-    use super::AccessToken;
+        let input = Input {
+            branch: CommittableBranch {
+                repository_name_with_owner: "octocat/Hello-World".to_string(),
+                branch_name: "main".to_string(),
+            },
+            expected_head_oid: "7638417db6d59f3c431d3e1f261cc637155684cd".to_string(),
+            file_changes: FileChanges {
+                additions: vec![
+                    FileAddition {
+                        path: "docs/README.txt".to_string(),
+                        contents: "SGVsbG8gV29ybGQK".to_string(),
+                    },
+                ],
+                deletions: vec![
+                    FileDeletion {
+                        path: "docs/EXAMPLE.txt".to_string(),
+                    },
+                ],
+            },
+            message: CommitMessage {
+                headline: "my commit message".to_string(),
+                body: None,
+            },
+        };
+
+        let actual = serde_json::to_string(&input).unwrap();
+
+        assert_eq_deserialized(&actual, expected);
+    }
 
     #[test]
-    fn expires_soon() {
-        let access_token = AccessToken {
-            token: Arc::new("".to_string()),
-            expires_at: OffsetDateTime::now_utc(),
+    fn file_addition_debug_representation() {
+        let contents = "c2Vuc2l0aXZlX2luZm9ybWF0aW9u";
+        let file_addition = FileAddition {
+            path: "foo".to_string(),
+            contents: contents.to_string(),
         };
+        let debug_output = format!("{:?}", file_addition);
 
-        assert!(access_token.expires_soon())
+        assert!(!debug_output.contains(contents));
     }
 
     #[test]
-    fn does_not_expire_soon() {
-        let access_token = AccessToken {
-            token: Arc::new("".to_string()),
-            expires_at: OffsetDateTime::now_utc() + Duration::minutes(5),
-        };
+    fn stale_data_error_deserialization() {
+        let original = r#"
+            {
+              "data": {
+                "createCommitOnBranch": null
+              },
+              "errors": [
+                {
+                  "type": "STALE_DATA",
+                  "path": ["createCommitOnBranch"],
+                  "locations": [{"line": 1, "column": 53}],
+                  "message": "Expected branch to point to \"7638417db6d59f3c431d3e1f261cc637155684cd\" but it did not. Pull and try again."
+                }
+              ]
+            }
+        "#;
 
-        assert!(!access_token.expires_soon())
+        let response = serde_json::from_str::<ResponseBody>(original).unwrap();
+
+        assert!(response.data.unwrap().create_commit_on_branch.is_none());
+        assert_eq!(response.errors.len(), 1);
+        assert_eq!(response.errors[0].error_type.as_deref(), Some("STALE_DATA"));
+    }
+
+    #[test]
+    fn commit_deserialization() {
+        let original = r#"
+            {
+              "data": {
+                "createCommitOnBranch": {
+                  "commit": {
+                    "oid": "7638417db6d59f3c431d3e1f261cc637155684cd",
+                    "url": "https://github.com/octocat/Hello-World/commit/7638417db6d59f3c431d3e1f261cc637155684cd",
+                    "tree": {
+                      "oid": "827efc6d56897b048c772eb4087f854f46256132"
+                    },
+                    "signature": {
+                      "isValid": true
+                    }
+                  }
+                }
+              }
+            }
+        "#;
+
+        let response = serde_json::from_str::<ResponseBody>(original).unwrap();
+        let commit = response.data.unwrap().create_commit_on_branch.unwrap().commit;
+
+        assert!(response.errors.is_empty());
+        assert_eq!(commit.tree.oid, "827efc6d56897b048c772eb4087f854f46256132");
+        assert!(commit.signature.unwrap().is_valid);
     }
 }
 
@@ -730,11 +2298,12 @@ mod create_a_blob_tests {
 }
 
 #[cfg(test)]
+#[allow(clippy::needless_borrow)]
 mod create_a_commit_tests {
     use crate::github::rest_api::create_a_commit::RequestBody;
     use crate::github::test_util::assert_eq_deserialized;
 
-    use super::rest_api::create_a_commit::{ResponseBody, Verification};
+    use super::rest_api::create_a_commit::{ResponseBody, Signature, Tree, Verification};
 
     #[test]
     fn create_a_commit_serialization_with_github_example_payload() {
@@ -754,6 +2323,12 @@ mod create_a_commit_tests {
                 message: "my commit message".to_string(),
                 parents: vec!["7d1b31e74ee336d15cbd21741bc88a537ed063a0".to_string()],
                 tree: "827efc6d56897b048c772eb4087f854f46256132".to_string(),
+                author: Some(Signature {
+                    name: "Mona Octocat".to_string(),
+                    email: "octocat@github.com".to_string(),
+                    date: Some("2008-07-09T16:13:30+12:00".to_string()),
+                }),
+                committer: None,
             };
 
             serde_json::to_string(&expected_deserialized).unwrap()
@@ -762,6 +2337,25 @@ mod create_a_commit_tests {
         assert_eq_deserialized(&actual, &expected);
     }
 
+    #[test]
+    fn create_a_commit_serialization_without_identities() {
+        let actual = {
+            let actual_deserialized = RequestBody {
+                message: "my commit message".to_string(),
+                parents: vec!["7d1b31e74ee336d15cbd21741bc88a537ed063a0".to_string()],
+                tree: "827efc6d56897b048c772eb4087f854f46256132".to_string(),
+                author: None,
+                committer: None,
+            };
+
+            serde_json::to_string(&actual_deserialized).unwrap()
+        };
+
+        let expected = r#"{"message":"my commit message","parents":["7d1b31e74ee336d15cbd21741bc88a537ed063a0"],"tree":"827efc6d56897b048c772eb4087f854f46256132"}"#;
+
+        assert_eq!(actual, expected);
+    }
+
     #[test]
     fn create_a_commit_deserialization_with_github_example_payload() {
         // From the docs: https://docs.github.com/en/rest/git/commits?apiVersion=2022-11-28#create-a-commit
@@ -812,6 +2406,9 @@ mod create_a_commit_tests {
             let expected_serialized = ResponseBody {
                 sha: "7638417db6d59f3c431d3e1f261cc637155684cd".to_string(),
                 html_url: "https://github.com/octocat/Hello-World/commit/7638417db6d59f3c431d3e1f261cc637155684cd".to_string(),
+                tree: Tree {
+                    sha: "827efc6d56897b048c772eb4087f854f46256132".to_string(),
+                },
                 verification: Verification {
                     verified: false,
                 },
@@ -825,6 +2422,7 @@ mod create_a_commit_tests {
 }
 
 #[cfg(test)]
+#[allow(clippy::needless_borrow)]
 mod create_a_reference_tests {
     use crate::github::rest_api::create_a_reference::RequestBody;
     use crate::github::test_util::assert_eq_deserialized;
@@ -845,6 +2443,7 @@ mod create_a_reference_tests {
 }
 
 #[cfg(test)]
+#[allow(clippy::needless_borrow)]
 mod create_a_tree_tests {
     use super::rest_api::create_a_tree::{FileMode, NodeType, RequestBody, ResponseBody, ShaOrContent, TreeNode};
     use super::test_util::{assert_eq_deserialized, quote};
@@ -1104,6 +2703,149 @@ mod create_a_tree_tests {
 }
 
 #[cfg(test)]
+mod get_a_repository_installation_tests {
+    use super::rest_api::get_a_repository_installation::ResponseBody;
+    use super::test_util::assert_eq_deserialized;
+
+    #[test]
+    fn deserialization_with_github_example_payload() {
+        let actual = {
+            // From the docs, abbreviated: https://docs.github.com/en/rest/apps/apps?apiVersion=2022-11-28#get-a-repository-installation-for-the-authenticated-app
+            let original = r#"
+                {
+                  "id": 1,
+                  "account": {
+                    "login": "octocat",
+                    "id": 1
+                  },
+                  "access_tokens_url": "https://api.github.com/app/installations/1/access_tokens",
+                  "repositories_url": "https://api.github.com/installation/repositories",
+                  "html_url": "https://github.com/organizations/github/settings/installations/1",
+                  "app_id": 1,
+                  "target_id": 1,
+                  "target_type": "Organization",
+                  "permissions": {
+                    "checks": "write",
+                    "metadata": "read",
+                    "contents": "read"
+                  },
+                  "events": [
+                    "push",
+                    "pull_request"
+                  ],
+                  "created_at": "2018-02-09T20:51:14Z",
+                  "updated_at": "2018-02-09T20:51:14Z",
+                  "single_file_name": null,
+                  "repository_selection": "selected",
+                  "app_slug": "github-actions"
+                }
+            "#;
+
+            let actual_deserialized = serde_json::from_str::<ResponseBody>(original).unwrap();
+
+            serde_json::to_string(&actual_deserialized).unwrap()
+        };
+
+        let expected = {
+            let expected_deserialized = ResponseBody {
+                id: 1,
+                app_id: 1,
+            };
+
+            serde_json::to_string(&expected_deserialized).unwrap()
+        };
+
+        assert_eq_deserialized(&actual, &expected);
+    }
+}
+
+#[cfg(test)]
+mod get_a_commit_object_tests {
+    use super::rest_api::create_a_commit::{Signature, Tree};
+    use super::rest_api::get_a_commit_object::{Parent, ResponseBody, Verification};
+    use super::test_util::assert_eq_deserialized;
+
+    #[test]
+    fn deserialization_with_github_example_payload() {
+        let actual = {
+            // From the docs, abbreviated: https://docs.github.com/en/rest/git/commits?apiVersion=2022-11-28#get-a-commit-object
+            let original = r#"
+                {
+                  "sha": "7638417db6d59f3c431d3e1f261cc637155684cd",
+                  "node_id": "MDY6Q29tbWl0NmRjYjA5YjViNTc4NzVmMzM0ZjYxYWViZWQ2OTVlMmU0MTkzZGI1ZQ==",
+                  "url": "https://api.github.com/repos/octocat/Hello-World/git/commits/7638417db6d59f3c431d3e1f261cc637155684cd",
+                  "html_url": "https://github.com/octocat/Hello-World/commit/7638417db6d59f3c431d3e1f261cc637155684cd",
+                  "author": {
+                    "date": "2014-11-07T22:01:45Z",
+                    "name": "Monalisa Octocat",
+                    "email": "octocat@github.com"
+                  },
+                  "committer": {
+                    "date": "2014-11-07T22:01:45Z",
+                    "name": "Monalisa Octocat",
+                    "email": "octocat@github.com"
+                  },
+                  "message": "added readme, because im a good github citizen",
+                  "tree": {
+                    "url": "https://api.github.com/repos/octocat/Hello-World/git/trees/691272480426f78a0138979dd3ce63b77f706feb",
+                    "sha": "691272480426f78a0138979dd3ce63b77f706feb"
+                  },
+                  "parents": [
+                    {
+                      "url": "https://api.github.com/repos/octocat/Hello-World/git/commits/1acc419d4d6a9ce985db7be48c6349a0475975b5",
+                      "sha": "1acc419d4d6a9ce985db7be48c6349a0475975b5",
+                      "html_url": "https://github.com/octocat/Hello-World/commit/7638417db6d59f3c431d3e1f261cc637155684cd"
+                    }
+                  ],
+                  "verification": {
+                    "verified": false,
+                    "reason": "unsigned",
+                    "signature": null,
+                    "payload": null,
+                    "verified_at": null
+                  }
+                }
+            "#;
+
+            let actual_deserialized = serde_json::from_str::<ResponseBody>(original).unwrap();
+
+            serde_json::to_string(&actual_deserialized).unwrap()
+        };
+
+        let expected = {
+            let signature = || Signature {
+                name: "Monalisa Octocat".to_string(),
+                email: "octocat@github.com".to_string(),
+                date: Some("2014-11-07T22:01:45Z".to_string()),
+            };
+
+            let expected_deserialized = ResponseBody {
+                sha: "7638417db6d59f3c431d3e1f261cc637155684cd".to_string(),
+                tree: Tree {
+                    sha: "691272480426f78a0138979dd3ce63b77f706feb".to_string(),
+                },
+                parents: vec![Parent {
+                    sha: "1acc419d4d6a9ce985db7be48c6349a0475975b5".to_string(),
+                }],
+                author: signature(),
+                committer: signature(),
+                message: "added readme, because im a good github citizen".to_string(),
+                verification: Verification {
+                    verified: false,
+                    signature: None,
+                    payload: None,
+                },
+            };
+
+            serde_json::to_string(&expected_deserialized).unwrap()
+        };
+
+        assert_eq_deserialized(&actual, &expected);
+    }
+}
+
+#[cfg(test)]
+#[allow(clippy::needless_borrow)]
 mod get_a_reference_tests {
     use super::rest_api::get_a_reference::ResponseBodyNotFound;
     use super::test_util::assert_eq_deserialized;
@@ -1128,6 +2870,89 @@ mod get_a_reference_tests {
     }
 }
 
+#[cfg(test)]
+mod create_a_pull_request_tests {
+    use super::rest_api::create_a_pull_request::RequestBody;
+    use super::test_util::assert_eq_deserialized;
+
+    #[test]
+    fn serialization_with_github_example_payload() {
+        // From the docs: https://docs.github.com/en/rest/pulls/pulls?apiVersion=2022-11-28#create-a-pull-request
+        let expected = r#"{"title":"Amazing new feature","head":"octocat:new-feature","base":"master","body":"Please pull these awesome changes in!","draft":false}"#;
+
+        let actual = {
+            let actual_deserialized = RequestBody {
+                title: "Amazing new feature".to_string(),
+                head: "octocat:new-feature".to_string(),
+                base: "master".to_string(),
+                body: Some("Please pull these awesome changes in!".to_string()),
+                draft: false,
+            };
+
+            serde_json::to_string(&actual_deserialized).unwrap()
+        };
+
+        assert_eq_deserialized(&actual, expected);
+    }
+}
+
+#[cfg(test)]
+mod enable_pull_request_auto_merge_tests {
+    use super::graphql_api::enable_pull_request_auto_merge::{Input, MergeMethod, QUERY, Variables};
+    use super::graphql_api::shared::RequestBody;
+    use super::test_util::{assert_eq_deserialized, quote};
+
+    #[test]
+    fn serialization() {
+        let input = Input {
+            pull_request_id: "PR_kwDOAAABc84AAAAB".to_string(),
+            merge_method: MergeMethod::Squash,
+        };
+
+        let actual = {
+            let actual_deserialized = RequestBody {
+                query: QUERY,
+                variables: Variables {
+                    input: &input,
+                },
+            };
+
+            serde_json::to_string(&actual_deserialized).unwrap()
+        };
+
+        let expected = format!(r#"{{"query":{},"variables":{{"input":{{"pullRequestId":"PR_kwDOAAABc84AAAAB","mergeMethod":"SQUASH"}}}}}}"#, quote(QUERY));
+
+        assert_eq_deserialized(&actual, &expected);
+    }
+}
+
+#[cfg(test)]
+mod list_pull_requests_tests {
+    use super::percent_encode_query_value;
+
+    #[test]
+    fn head_is_percent_encoded() {
+        assert_eq!(percent_encode_query_value("octocat:feature/a+b"), "octocat%3Afeature%2Fa%2Bb");
+        assert_eq!(percent_encode_query_value("my-branch_1.0~x"), "my-branch_1.0~x");
+    }
+}
+
+#[cfg(test)]
+mod update_a_pull_request_tests {
+    use super::rest_api::update_a_pull_request::RequestBody;
+
+    #[test]
+    fn serialization_skips_unset_fields() {
+        let payload = RequestBody {
+            title: None,
+            body: None,
+            base: Some("main".to_string()),
+        };
+
+        assert_eq!(serde_json::to_string(&payload).unwrap(), r#"{"base":"main"}"#);
+    }
+}
+
 #[cfg(test)]
 mod update_a_reference_tests {
     use super::rest_api::update_a_reference::RequestBody;
@@ -1153,9 +2978,56 @@ mod update_a_reference_tests {
 
 #[cfg(test)]
 mod shared_tests {
-    use super::rest_api::shared::{ReferenceResponseBody, ReferenceResponseBodyObject};
+    use super::rest_api::shared::{PullRequestResponseBody, ReferenceResponseBody, ReferenceResponseBodyObject};
     use super::test_util::assert_eq_deserialized;
 
+    #[test]
+    fn pull_request_deserialization_with_github_example_payload() {
+        let actual = {
+            // From the docs, abbreviated: https://docs.github.com/en/rest/pulls/pulls?apiVersion=2022-11-28#get-a-pull-request
+            let original = r#"
+                {
+                  "url": "https://api.github.com/repos/octocat/Hello-World/pulls/1347",
+                  "id": 1,
+                  "node_id": "MDExOlB1bGxSZXF1ZXN0MQ==",
+                  "html_url": "https://github.com/octocat/Hello-World/pull/1347",
+                  "number": 1347,
+                  "state": "open",
+                  "locked": true,
+                  "title": "Amazing new feature",
+                  "body": "Please pull these awesome changes in!",
+                  "head": {
+                    "label": "octocat:new-topic",
+                    "ref": "new-topic",
+                    "sha": "6dcb09b5b57875f334f61aebed695e2e4193db5e"
+                  },
+                  "base": {
+                    "label": "octocat:master",
+                    "ref": "master",
+                    "sha": "6dcb09b5b57875f334f61aebed695e2e4193db5e"
+                  },
+                  "draft": false
+                }
+            "#;
+
+            let actual_deserialized = serde_json::from_str::<PullRequestResponseBody>(original).unwrap();
+
+            serde_json::to_string(&actual_deserialized).unwrap()
+        };
+
+        let expected = {
+            let expected_deserialized = PullRequestResponseBody {
+                number: 1347,
+                html_url: "https://github.com/octocat/Hello-World/pull/1347".to_string(),
+                node_id: "MDExOlB1bGxSZXF1ZXN0MQ==".to_string(),
+            };
+
+            serde_json::to_string(&expected_deserialized).unwrap()
+        };
+
+        assert_eq_deserialized(&actual, &expected);
+    }
+
     #[test]
     fn reference_deserialization_with_github_example_payload() {
         // From the docs:
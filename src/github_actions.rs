@@ -0,0 +1,296 @@
+use std::env;
+use std::fs::OpenOptions;
+use std::io::Write;
+use std::path::Path;
+
+use crate::error::{Error, Result};
+use crate::git_status::PathStatus;
+use crate::output::{CommitOutput, ErrorOutput};
+
+/// Whether ghommit is running as part of a GitHub Actions workflow
+///
+/// - [Default environment variables](https://docs.github.com/en/actions/reference/variables-reference#default-environment-variables)
+pub fn is_github_actions() -> bool {
+    env::var("GITHUB_ACTIONS").is_ok_and(|value| value == "true")
+}
+
+/// Formats a step output in the `name=value` form, or in the multiline form if
+/// the value spans several lines
+///
+/// - [Setting an output parameter](https://docs.github.com/en/actions/reference/workflow-commands-for-github-actions#setting-an-output-parameter)
+fn format_step_output(name: &str, value: &str) -> String {
+    if !value.contains('\n') {
+        return format!("{}={}\n", name, value)
+    }
+
+    let mut delimiter = "ghommit_delimiter".to_string();
+
+    while value.contains(&delimiter) {
+        delimiter.push('_');
+    }
+
+    format!("{}<<{}\n{}\n{}\n", name, delimiter, value, delimiter)
+}
+
+fn step_outputs(output: &CommitOutput) -> String {
+    let mut outputs = [
+        format_step_output("commit_sha", &output.commit_sha),
+        format_step_output("commit_url", &output.commit_url),
+        format_step_output("tree_sha", &output.tree_sha),
+        format_step_output("branch", &output.branch),
+        format_step_output("ref_action", output.ref_action.as_str()),
+    ].concat();
+
+    if let Some(pr_url) = &output.pr_url {
+        outputs.push_str(&format_step_output("pr_url", pr_url));
+    }
+
+    if let Some(pr_error) = &output.pr_error {
+        outputs.push_str(&format_step_output("pr_error", &pr_error.message));
+    }
+
+    if let Some(sync_error) = &output.sync_error {
+        outputs.push_str(&format_step_output("sync_error", &sync_error.message));
+    }
+
+    outputs
+}
+
+fn error_step_outputs(output: &ErrorOutput) -> String {
+    let mut outputs = [
+        format_step_output("error_kind", output.error.kind),
+        format_step_output("error_message", &output.error.message),
+    ].concat();
+
+    if let Some(status) = output.error.status {
+        outputs.push_str(&format_step_output("error_status", &status.to_string()));
+    }
+
+    outputs
+}
+
+/// Escapes text for a Markdown table cell
+fn escape_markdown(text: &str) -> String {
+    let mut escaped = String::with_capacity(text.len());
+
+    for c in text.chars() {
+        if matches!(c, '\\' | '`' | '*' | '_' | '[' | ']' | '<' | '>' | '|') {
+            escaped.push('\\');
+        }
+
+        escaped.push(c);
+    }
+
+    escaped
+}
+
+fn describe_delta(path_status: &PathStatus) -> String {
+    match (path_status.delta, &path_status.original_path) {
+        (git2::Delta::Renamed, Some(original_path)) => format!("Renamed from {}", escape_markdown(original_path)),
+        (git2::Delta::Copied, Some(original_path)) => format!("Copied from {}", escape_markdown(original_path)),
+        (git2::Delta::Added, _) => "Added".to_string(),
+        (git2::Delta::Deleted, _) => "Deleted".to_string(),
+        (git2::Delta::Modified, _) => "Modified".to_string(),
+        (git2::Delta::Typechange, _) => "Type changed".to_string(),
+        (delta, _) => format!("{:?}", delta),
+    }
+}
+
+/// - `changed_paths` is `None` when the changes aren't known up front, such as
+///   when replaying commits
+fn step_summary(output: &CommitOutput, changed_paths: Option<&[PathStatus]>) -> String {
+    let short_sha: String = output.commit_sha.chars().take(7).collect();
+
+    let verified = match output.verified {
+        true => "Verified",
+        false => "Unverified",
+    };
+
+    let mut summary = String::new();
+
+    summary.push_str("### ghommit\n\n");
+    summary.push_str(&format!("- Commit: [{}]({}) ({})\n", short_sha, output.commit_url, verified));
+    summary.push_str(&format!("- Branch: {} ({})\n", escape_markdown(&output.branch), output.ref_action.as_str()));
+    summary.push_str(&format!("- Tree: {}\n", output.tree_sha));
+
+    if let Some(pr_url) = &output.pr_url {
+        summary.push_str(&format!("- Pull request: {}\n", pr_url));
+    }
+
+    if let Some(pr_error) = &output.pr_error {
+        summary.push_str(&format!("- Pull request failed: {}\n", escape_markdown(&pr_error.message)));
+    }
+
+    if let Some(sync_error) = &output.sync_error {
+        summary.push_str(&format!("- Local sync failed: {}\n", escape_markdown(&sync_error.message)));
+    }
+
+    if let Some(changed_paths) = changed_paths {
+        summary.push_str(&format!("\n#### Changed paths ({})\n\n", changed_paths.len()));
+        summary.push_str("| Path | Change |\n");
+        summary.push_str("| --- | --- |\n");
+
+        for path_status in changed_paths {
+            summary.push_str(&format!("| {} | {} |\n", escape_markdown(&path_status.path), describe_delta(path_status)));
+        }
+    }
+
+    summary.push('\n');
+    summary
+}
+
+fn error_step_summary(output: &ErrorOutput) -> String {
+    let mut summary = String::new();
+
+    summary.push_str("### ghommit\n\n");
+    summary.push_str(&format!("- Failed: {}\n", output.error.kind));
+
+    if let Some(status) = output.error.status {
+        summary.push_str(&format!("- Status: {}\n", status));
+    }
+
+    summary.push('\n');
+
+    for line in output.error.message.lines() {
+        summary.push_str(&format!("> {}\n", escape_markdown(line)));
+    }
+
+    summary.push('\n');
+    summary
+}
+
+fn append_to_file(path: &Path, contents: &str) -> Result<()> {
+    let io_error = |e: std::io::Error| Error::Io {
+        path: path.to_string_lossy().into_owned(),
+        message: e.to_string(),
+    };
+
+    let mut file = OpenOptions::new().create(true).append(true).open(path)
+        .map_err(io_error)?;
+
+    file.write_all(contents.as_bytes())
+        .map_err(io_error)
+}
+
+fn write_environment_files(step_outputs: &str, step_summary: &str) -> Result<()> {
+    if !is_github_actions() {
+        return Ok(())
+    }
+
+    if let Ok(path) = env::var("GITHUB_OUTPUT") {
+        append_to_file(Path::new(&path), step_outputs)?;
+    }
+
+    if let Ok(path) = env::var("GITHUB_STEP_SUMMARY") {
+        append_to_file(Path::new(&path), step_summary)?;
+    }
+
+    Ok(())
+}
+
+/// Writes the commit to the step outputs and the job summary, if ghommit is
+/// running as part of a GitHub Actions workflow
+///
+/// - [Environment files](https://docs.github.com/en/actions/reference/workflow-commands-for-github-actions#environment-files)
+pub fn report(output: &CommitOutput, changed_paths: Option<&[PathStatus]>) -> Result<()> {
+    write_environment_files(&step_outputs(output), &step_summary(output, changed_paths))
+}
+
+/// Writes the error to the step outputs and the job summary, if ghommit is
+/// running as part of a GitHub Actions workflow, so that later steps can
+/// branch on `error_kind`
+pub fn report_error(output: &ErrorOutput) -> Result<()> {
+    write_environment_files(&error_step_outputs(output), &error_step_summary(output))
+}
+
+#[cfg(test)]
+mod github_actions_tests {
+    use crate::backend::RefAction;
+    use crate::git_status::git_status;
+    use crate::error::Error;
+    use crate::output::{CommitOutput, ErrorOutput};
+    use crate::test_utils::test_utils::TempGitRepo;
+
+    use super::{error_step_outputs, error_step_summary, format_step_output, step_outputs, step_summary};
+
+    fn commit_output() -> CommitOutput {
+        CommitOutput {
+            commit_sha: "7638417db6d59f3c431d3e1f261cc637155684cd".to_string(),
+            commit_url: "https://github.com/octocat/Hello-World/commit/7638417db6d59f3c431d3e1f261cc637155684cd".to_string(),
+            tree_sha: "827efc6d56897b048c772eb4087f854f46256132".to_string(),
+            verified: true,
+            branch: "main".to_string(),
+            ref_action: RefAction::Updated,
+            pr_url: None,
+            pr_error: None,
+            sync_error: None,
+        }
+    }
+
+    #[test]
+    fn step_outputs_format() {
+        let expected = "\
+commit_sha=7638417db6d59f3c431d3e1f261cc637155684cd
+commit_url=https://github.com/octocat/Hello-World/commit/7638417db6d59f3c431d3e1f261cc637155684cd
+tree_sha=827efc6d56897b048c772eb4087f854f46256132
+branch=main
+ref_action=updated
+";
+
+        assert_eq!(step_outputs(&commit_output()), expected);
+    }
+
+    #[test]
+    fn multiline_step_output_format() {
+        let actual = format_step_output("message", "foo\nbar");
+
+        assert_eq!(actual, "message<<ghommit_delimiter\nfoo\nbar\nghommit_delimiter\n");
+    }
+
+    #[test]
+    fn step_summary_lists_changed_paths() {
+        let repo = TempGitRepo::new();
+
+        let foo = repo.create_or_replace_blob_file("foo", "foo\n".as_bytes());
+        repo.git_add(&foo);
+        repo.git_commit("Add foo");
+
+        let bar = repo.create_or_replace_blob_file("bar|baz", "bar\n".as_bytes());
+        repo.git_add(&bar);
+        repo.git_rm(&foo);
+
+        let status = git_status(&repo.repo)
+            .expect("Unable to get a git status");
+
+        let summary = step_summary(&commit_output(), Some(&status));
+
+        assert!(summary.contains("[7638417](https://github.com/octocat/Hello-World/commit/7638417db6d59f3c431d3e1f261cc637155684cd) (Verified)"));
+        assert!(summary.contains("#### Changed paths (2)"));
+        assert!(summary.contains("| bar\\|baz | Added |"));
+        assert!(summary.contains("| foo | Deleted |"));
+    }
+
+    #[test]
+    fn error_step_outputs_format() {
+        let error = Error::Api {
+            operation: "create a tree".to_string(),
+            status: 422,
+            body: "BadObjectState".to_string(),
+        };
+
+        let actual = error_step_outputs(&ErrorOutput::from(&error));
+
+        assert!(actual.starts_with("error_kind=api\nerror_message="));
+        assert!(actual.ends_with("error_status=422\n"));
+    }
+
+    #[test]
+    fn error_step_summary_quotes_the_message() {
+        let error = Error::unsupported("foo\nbar|baz");
+
+        let summary = error_step_summary(&ErrorOutput::from(&error));
+
+        assert!(summary.contains("- Failed: unsupported\n"));
+        assert!(summary.contains("> foo\n> bar\\|baz\n"));
+    }
+}
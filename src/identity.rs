@@ -0,0 +1,330 @@
+use std::{env, fmt};
+
+use time::OffsetDateTime;
+use time::format_description::well_known::Rfc3339;
+
+use crate::error::{Error, Result};
+use crate::github::rest_api::create_a_commit;
+use crate::log::print_warning;
+
+/// A name and email address, as in `Name <email>`
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct Identity {
+    pub name: String,
+    pub email: String,
+}
+
+impl Identity {
+    /// Parses an identity in the `Name <email>` form that git uses for
+    /// authors and `Co-authored-by:` trailers
+    pub fn parse(s: &str) -> std::result::Result<Identity, String> {
+        let invalid = || format!("Expected an identity like \"Name <email>\": {:?}", s);
+
+        let (name, rest) = s.trim().split_once('<').ok_or_else(invalid)?;
+        let email = rest.strip_suffix('>').ok_or_else(invalid)?;
+
+        let name = name.trim();
+        let email = email.trim();
+
+        if name.is_empty() || email.is_empty() || email.contains(['<', '>']) {
+            return Err(invalid())
+        }
+
+        Ok(Identity {
+            name: name.to_string(),
+            email: email.to_string(),
+        })
+    }
+}
+
+impl fmt::Display for Identity {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{} <{}>", self.name, self.email)
+    }
+}
+
+/// Parses a date in the ISO 8601 form GitHub takes, such as
+/// `2014-11-07T22:01:45Z` or `2014-11-07T23:01:45+01:00`
+pub fn parse_date(s: &str) -> std::result::Result<OffsetDateTime, String> {
+    OffsetDateTime::parse(s, &Rfc3339)
+        .map_err(|e| format!("Expected a date like \"2014-11-07T22:01:45Z\": {:?}: {}", s, e))
+}
+
+/// Who a created commit is attributed to
+///
+/// Everything is optional since GitHub attributes commits to the authenticated
+/// app or user at the current time by default.
+#[derive(Clone, Debug, Default)]
+pub struct CommitIdentityOptions {
+    pub author: Option<Identity>,
+    pub author_date: Option<OffsetDateTime>,
+    pub committer: Option<Identity>,
+    pub committer_date: Option<OffsetDateTime>,
+    /// Use the author from git: `user.name` and `user.email` for staged
+    /// changes, and each commit's own author when replaying commits. The
+    /// committer comes from git too, the way `git commit` picks it.
+    pub use_git_identity: bool,
+    pub co_authors: Vec<Identity>,
+}
+
+impl CommitIdentityOptions {
+    pub fn is_custom(&self) -> bool {
+        self.author.is_some() || self.committer.is_some() || self.use_git_identity
+    }
+}
+
+/// The `author` and `committer` to create a commit with
+#[derive(Debug, Default)]
+pub struct CommitSignatures {
+    pub author: Option<create_a_commit::Signature>,
+    pub committer: Option<create_a_commit::Signature>,
+}
+
+fn format_date(date: &OffsetDateTime) -> Result<String> {
+    date.format(&Rfc3339)
+        .map_err(|e| Error::internal(format!("Unable to format date {:?}: {}", date, e)))
+}
+
+fn signature(identity: Identity, date: Option<&OffsetDateTime>) -> Result<create_a_commit::Signature> {
+    Ok(create_a_commit::Signature {
+        name: identity.name,
+        email: identity.email,
+        date: date.map(format_date).transpose()?,
+    })
+}
+
+fn git_signature_identity(signature: &git2::Signature, source: &str) -> Result<Identity> {
+    match (signature.name(), signature.email()) {
+        (Some(name), Some(email)) => Ok(Identity {
+            name: name.to_string(),
+            email: email.to_string(),
+        }),
+        _ => Err(Error::unsupported(format!("The author of {} is not valid UTF-8", source))),
+    }
+}
+
+fn git_time(time: git2::Time) -> Result<OffsetDateTime> {
+    let offset = time::UtcOffset::from_whole_seconds(time.offset_minutes() * 60)
+        .map_err(|e| Error::internal(format!("Invalid time zone offset {} minutes: {}", time.offset_minutes(), e)))?;
+
+    let date = OffsetDateTime::from_unix_timestamp(time.seconds())
+        .map_err(|e| Error::internal(format!("Invalid timestamp {}: {}", time.seconds(), e)))?;
+
+    Ok(date.to_offset(offset))
+}
+
+/// The committer `git commit` would use: `GIT_COMMITTER_NAME` and
+/// `GIT_COMMITTER_EMAIL`, each falling back to `user.name` and `user.email`
+///
+/// - `None` if git doesn't have a complete identity either, in which case
+///   GitHub's default is used
+fn git_committer_identity(repo: &git2::Repository) -> Result<Option<Identity>> {
+    let from_env = |name: &str| env::var(name).ok().filter(|value| !value.trim().is_empty());
+
+    let from_config = match repo.signature() {
+        Ok(git_signature) => Some(git_signature_identity(&git_signature, "the git config")?),
+        Err(_) => None,
+    };
+
+    let name = from_env("GIT_COMMITTER_NAME").or_else(|| from_config.as_ref().map(|identity| identity.name.clone()));
+    let email = from_env("GIT_COMMITTER_EMAIL").or_else(|| from_config.map(|identity| identity.email));
+
+    match (name, email) {
+        (Some(name), Some(email)) => Ok(Some(Identity { name: name, email: email })),
+        _ => Ok(None),
+    }
+}
+
+/// Resolves who a commit is attributed to
+///
+/// - `local_commit` is the commit being replayed, if any, whose author and
+///   author date are used with `use_git_identity`
+/// - Flags win over git: `--author` over `use_git_identity`, `--date` over
+///   the replayed commit's author date, and `--committer` over the committer
+///   git would use
+pub fn commit_signatures(options: &CommitIdentityOptions, repo: &git2::Repository, local_commit: Option<&git2::Commit>) -> Result<CommitSignatures> {
+    let author = match (&options.author, options.use_git_identity, local_commit) {
+        (Some(author), _, _) => Some(signature(author.clone(), options.author_date.as_ref())?),
+        (None, true, Some(commit)) => {
+            let author = commit.author();
+            let identity = git_signature_identity(&author, &format!("commit {}", commit.id()))?;
+            let date = match options.author_date {
+                Some(date) => date,
+                None => git_time(author.when())?,
+            };
+
+            Some(signature(identity, Some(&date))?)
+        },
+        (None, true, None) => {
+            let git_signature = repo.signature()
+                .map_err(|e| Error::config(format!("Git identity is not configured; set user.name and user.email or pass --author instead: {}", e.message())))?;
+            let identity = git_signature_identity(&git_signature, "the git config")?;
+
+            Some(signature(identity, options.author_date.as_ref())?)
+        },
+        (None, false, _) => None,
+    };
+
+    let committer = match (&options.committer, options.use_git_identity) {
+        (Some(committer), _) => Some(signature(committer.clone(), options.committer_date.as_ref())?),
+        (None, true) => git_committer_identity(repo)?.map(|identity| signature(identity, None)).transpose()?,
+        (None, false) => None,
+    };
+
+    Ok(CommitSignatures {
+        author: author,
+        committer: committer,
+    })
+}
+
+/// GitHub only signs commits that it commits itself, and the committer
+/// defaults to the author, so setting either makes the commit unverified
+pub fn warn_if_unverified(options: &CommitIdentityOptions) {
+    if options.is_custom() {
+        print_warning("A custom author or committer was set, so GitHub won't sign the commit and will show it as unverified");
+    }
+}
+
+/// Whether a line is a git trailer such as `Signed-off-by: Name <email>`
+fn is_trailer(line: &str) -> bool {
+    match line.split_once(": ") {
+        Some((key, _)) => !key.is_empty() && key.chars().all(|c| c.is_ascii_alphanumeric() || c == '-'),
+        None => false,
+    }
+}
+
+/// Appends a `Co-authored-by:` trailer for each co-author that isn't credited
+/// yet, joining the message's existing trailers if it ends with some
+///
+/// - [Creating a commit with multiple authors](https://docs.github.com/en/pull-requests/committing-changes-to-your-project/creating-and-editing-commits/creating-a-commit-with-multiple-authors)
+pub fn with_co_author_trailers(message: &str, co_authors: &[Identity]) -> String {
+    let trimmed = message.trim_end();
+
+    let new_trailers: Vec<String> = co_authors.iter()
+        .map(|co_author| format!("Co-authored-by: {}", co_author))
+        .filter(|trailer| !trimmed.lines().any(|line| line.trim().eq_ignore_ascii_case(trailer)))
+        .collect();
+
+    if new_trailers.is_empty() {
+        return message.to_string()
+    }
+
+    let last_paragraph = trimmed.rsplit("\n\n").next().unwrap_or_default();
+    let ends_with_trailers = trimmed.contains("\n\n") && last_paragraph.lines().all(is_trailer);
+
+    let separator = match ends_with_trailers {
+        true => "\n",
+        false => "\n\n",
+    };
+
+    format!("{}{}{}\n", trimmed, separator, new_trailers.join("\n"))
+}
+
+#[cfg(test)]
+mod identity_tests {
+    use crate::test_utils::test_utils::TempGitRepo;
+
+    use super::{CommitIdentityOptions, Identity, commit_signatures, parse_date, with_co_author_trailers};
+
+    fn octocat() -> Identity {
+        Identity {
+            name: "Mona Octocat".to_string(),
+            email: "octocat@github.com".to_string(),
+        }
+    }
+
+    #[test]
+    fn identity_parsing() {
+        assert_eq!(Identity::parse("Mona Octocat <octocat@github.com>"), Ok(octocat()));
+        assert_eq!(Identity::parse(" Mona Octocat<octocat@github.com> "), Ok(octocat()));
+        assert!(Identity::parse("octocat@github.com").is_err());
+        assert!(Identity::parse("<octocat@github.com>").is_err());
+    }
+
+    #[test]
+    fn co_author_trailers() {
+        let co_authors = vec![octocat()];
+
+        assert_eq!(with_co_author_trailers("Add foo", &co_authors), "Add foo\n\nCo-authored-by: Mona Octocat <octocat@github.com>\n");
+        assert_eq!(with_co_author_trailers("Add foo\n\nSigned-off-by: A <a@b.c>\n", &co_authors), "Add foo\n\nSigned-off-by: A <a@b.c>\nCo-authored-by: Mona Octocat <octocat@github.com>\n");
+        assert_eq!(with_co_author_trailers("Add foo\n\nBar needs it.", &co_authors), "Add foo\n\nBar needs it.\n\nCo-authored-by: Mona Octocat <octocat@github.com>\n");
+
+        let already_credited = "Add foo\n\nCo-authored-by: Mona Octocat <octocat@github.com>\n";
+        assert_eq!(with_co_author_trailers(already_credited, &co_authors), already_credited);
+    }
+
+    #[test]
+    fn flags_win_over_git_identity() {
+        let repo = TempGitRepo::new();
+
+        let options = CommitIdentityOptions {
+            author: Some(octocat()),
+            author_date: Some(parse_date("2014-11-07T23:01:45+01:00").unwrap()),
+            use_git_identity: true,
+            ..CommitIdentityOptions::default()
+        };
+
+        let mut config = repo.repo.config().unwrap();
+        config.set_str("user.name", "Hubot").unwrap();
+        config.set_str("user.email", "hubot@example.com").unwrap();
+
+        let signatures = commit_signatures(&options, &repo.repo, None).unwrap();
+        let author = signatures.author.unwrap();
+
+        assert_eq!(author.name, "Mona Octocat");
+        assert_eq!(author.date.as_deref(), Some("2014-11-07T23:01:45+01:00"));
+        // - The committer still comes from git, since only --author was given
+        assert_eq!(signatures.committer.unwrap().name, "Hubot");
+    }
+
+    #[test]
+    fn committer_falls_back_to_git_config() {
+        let repo = TempGitRepo::new();
+
+        let mut config = repo.repo.config().unwrap();
+        config.set_str("user.name", "Hubot").unwrap();
+        config.set_str("user.email", "hubot@example.com").unwrap();
+
+        let options = CommitIdentityOptions {
+            use_git_identity: true,
+            ..CommitIdentityOptions::default()
+        };
+
+        let committer = commit_signatures(&options, &repo.repo, None).unwrap().committer.unwrap();
+
+        assert_eq!(committer.name, "Hubot");
+        assert_eq!(committer.email, "hubot@example.com");
+        assert!(committer.date.is_none());
+
+        let options = CommitIdentityOptions {
+            committer: Some(octocat()),
+            ..options
+        };
+
+        let committer = commit_signatures(&options, &repo.repo, None).unwrap().committer.unwrap();
+
+        assert_eq!(committer.name, "Mona Octocat");
+    }
+
+    #[test]
+    fn replayed_commit_keeps_its_author() {
+        let repo = TempGitRepo::new();
+
+        let foo = repo.create_or_replace_blob_file("foo", "foo\n".as_bytes());
+        repo.git_add(&foo);
+        let commit_id = repo.git_commit("Add foo");
+        let commit = repo.repo.find_commit(commit_id).unwrap();
+
+        let options = CommitIdentityOptions {
+            use_git_identity: true,
+            ..CommitIdentityOptions::default()
+        };
+
+        let signatures = commit_signatures(&options, &repo.repo, Some(&commit)).unwrap();
+        let author = signatures.author.unwrap();
+
+        assert_eq!(author.name, commit.author().name().unwrap());
+        assert_eq!(author.email, commit.author().email().unwrap());
+        assert!(author.date.is_some());
+    }
+}
@@ -0,0 +1,366 @@
+//! Git LFS support
+//!
+//! - Paths that `.gitattributes` tracks with Git LFS are staged as pointer
+//!   files, which are committed as they are, so the objects they point to are
+//!   uploaded to GitHub's LFS storage before the commit references them
+//! - [Git LFS specification](https://github.com/git-lfs/git-lfs/blob/main/docs/spec.md)
+
+use std::collections::HashSet;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use crate::create_a_tree_prep::{GitCommitAction, delta_to_actions, upload_concurrently};
+use crate::error::{Error, Result};
+use crate::git_status::PathStatus;
+use crate::github::GitHubClient;
+use crate::github::lfs_api::batch;
+
+/// Git LFS doesn't treat anything larger as a pointer file
+const MAX_POINTER_SIZE: usize = 1024;
+
+const POINTER_VERSION: &str = "https://git-lfs.github.com/spec/v1";
+
+/// An LFS object that a staged pointer file points to
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct LfsObject {
+    /// Path of the pointer file
+    pub path: String,
+    /// SHA-256 of the object's contents, as lowercase hex
+    pub oid: String,
+    pub size: u64,
+}
+
+/// Parses a [pointer file](https://github.com/git-lfs/git-lfs/blob/main/docs/spec.md#the-pointer)
+/// into its object's ID and size
+///
+/// - Keys other than `oid` and `size` belong to extensions, which don't
+///   change which object is pointed to
+fn parse_pointer(contents: &[u8]) -> Option<(String, u64)> {
+    if contents.len() > MAX_POINTER_SIZE {
+        return None
+    }
+
+    let text = std::str::from_utf8(contents).ok()?;
+    let mut lines = text.lines();
+
+    if lines.next()?.strip_prefix("version ")? != POINTER_VERSION {
+        return None
+    }
+
+    let mut oid = None;
+    let mut size = None;
+
+    for line in lines {
+        let (key, value) = line.split_once(' ')?;
+
+        match key {
+            "oid" => {
+                oid = value.strip_prefix("sha256:")
+                    .filter(|hex| hex.len() == 64 && hex.bytes().all(|byte| matches!(byte, b'0'..=b'9' | b'a'..=b'f')))
+                    .map(str::to_owned);
+            },
+            "size" => size = value.parse::<u64>().ok(),
+            _ => {},
+        }
+    }
+
+    Some((oid?, size?))
+}
+
+/// Whether `.gitattributes` tracks `path` with Git LFS (`filter=lfs`),
+/// preferring the staged `.gitattributes` files since those are what's being
+/// committed
+fn is_lfs_tracked(repo: &git2::Repository, path: &str) -> Result<bool> {
+    let filter = repo.get_attr(Path::new(path), "filter", git2::AttrCheckFlags::INDEX_THEN_FILE)
+        .map_err(|e| Error::git_path("read the filter attribute", path, e))?;
+
+    Ok(filter == Some("lfs"))
+}
+
+/// Finds the LFS objects that the pointer files among the paths `git_status`
+/// adds point to, without making any network calls
+///
+/// - Every path that `.gitattributes` tracks with Git LFS must be staged as a
+///   pointer file, which `git add` does when Git LFS is installed, and every
+///   one that isn't is reported
+pub fn lfs_objects(repo: &git2::Repository, git_status: &[PathStatus]) -> Result<Vec<LfsObject>> {
+    let mut objects = Vec::new();
+    let mut failures = Vec::new();
+
+    for path_status in git_status {
+        let is_added = delta_to_actions(path_status.delta).contains(&GitCommitAction::AddPath);
+        let is_file = matches!(path_status.file_mode, git2::FileMode::Blob | git2::FileMode::BlobExecutable);
+
+        if !is_added || !is_file || !is_lfs_tracked(repo, &path_status.path)? {
+            continue
+        }
+
+        let blob = repo.find_blob(path_status.object_id)
+            .map_err(|e| Error::git_path(format!("find blob {}", path_status.object_id), &path_status.path, e))?;
+
+        match parse_pointer(blob.content()) {
+            Some((oid, size)) => objects.push(LfsObject {
+                path: path_status.path.clone(),
+                oid: oid,
+                size: size,
+            }),
+            None => {
+                let message = "Tracked by Git LFS in .gitattributes, but staged with its contents rather than as an LFS pointer, which would commit the contents to the repository itself; install Git LFS with `git lfs install` and stage the path again with `git add`";
+
+                failures.push((path_status.path.clone(), Error::unsupported_path(&path_status.path, message)));
+            },
+        }
+    }
+
+    match failures.len() {
+        0 => Ok(objects),
+        1 => Err(failures.remove(0).1),
+        _ => Err(Error::Multiple {
+            operation: "commit files tracked by Git LFS".to_string(),
+            failures: failures,
+        }),
+    }
+}
+
+/// Refuses to upload anywhere but GitHub's own LFS storage for the
+/// repository, since that's the only LFS server the GitHub token is for
+///
+/// - `lfs.url` and `remote.origin.lfsurl` are checked in the repository's
+///   config and in `.lfsconfig`, which is where Git LFS reads them from
+fn ensure_github_lfs_server(repo: &git2::Repository, github_client: &GitHubClient) -> Result<()> {
+    let expected_url = github_client.lfs_api_url();
+
+    let mut configs = vec![("git config", repo.config())];
+
+    if let Some(lfsconfig_path) = repo.workdir().map(|workdir| workdir.join(".lfsconfig")).filter(|path| path.is_file()) {
+        configs.push((".lfsconfig", git2::Config::open(&lfsconfig_path)));
+    }
+
+    for (source, config) in configs {
+        let config = config
+            .map_err(|e| Error::git(format!("read {}", source), e))?;
+
+        for name in ["lfs.url", "remote.origin.lfsurl"] {
+            let url = match config.get_string(name) {
+                Ok(url) => url,
+                Err(_) => continue,
+            };
+
+            if url.trim_end_matches('/') != expected_url {
+                Err(Error::unsupported(format!("Git LFS objects can only be uploaded to GitHub's LFS storage at {}, but {} sets {} to {}", expected_url, source, name, url)))?
+            }
+        }
+    }
+
+    Ok(())
+}
+
+/// Where Git LFS keeps the objects it has locally: `lfs/objects` in the git
+/// directory, unless `lfs.storage` says otherwise
+fn lfs_storage_directory(repo: &git2::Repository) -> Result<PathBuf> {
+    let config = repo.config()
+        .map_err(|e| Error::git("read git config", e))?;
+
+    // - Linked worktrees share the main git directory's objects
+    let storage = match config.get_path("lfs.storage") {
+        Ok(storage) => repo.commondir().join(storage),
+        Err(_) => repo.commondir().join("lfs"),
+    };
+
+    Ok(storage.join("objects"))
+}
+
+/// `<storage>/ab/cd/abcd...`, like Git LFS lays its objects out
+fn lfs_object_path(storage_directory: &Path, oid: &str) -> PathBuf {
+    storage_directory.join(&oid[0..2]).join(&oid[2..4]).join(oid)
+}
+
+/// Checks that the object is stored locally in full before it's uploaded
+fn local_lfs_object(storage_directory: &Path, object: &LfsObject) -> Result<PathBuf> {
+    let path = lfs_object_path(storage_directory, &object.oid);
+
+    match fs::metadata(&path) {
+        Ok(metadata) if metadata.len() == object.size => Ok(path),
+        Ok(metadata) => Err(Error::unsupported_path(&object.path, format!("The Git LFS object {} in {:?} is {} bytes, but the pointer says it's {} bytes", object.oid, path, metadata.len(), object.size))),
+        Err(_) => Err(Error::unsupported_path(&object.path, format!("The Git LFS object {} isn't in {:?} and GitHub doesn't have it either, so there's nothing to upload; fetch it with `git lfs fetch` or stage the file again with `git add`", object.oid, storage_directory))),
+    }
+}
+
+/// Uploads the LFS objects that the pointer files among `git_status` point to,
+/// skipping the ones GitHub already has, so that the commit doesn't end up
+/// with pointers to objects that don't exist
+///
+/// - Objects are uploaded with up to `max_concurrent_uploads` requests in
+///   flight, and every failed upload is reported, each with its path
+pub fn upload_lfs_objects(repo: &git2::Repository, git_status: &[PathStatus], branch_name: &str, max_concurrent_uploads: usize, github_client: &GitHubClient) -> Result<()> {
+    let mut objects = lfs_objects(repo, git_status)?;
+
+    if objects.is_empty() {
+        return Ok(())
+    }
+
+    ensure_github_lfs_server(repo, github_client)?;
+
+    // - The same object may be pointed to from several paths
+    let mut seen_oids = HashSet::new();
+    objects.retain(|object| seen_oids.insert(object.oid.clone()));
+
+    let storage_directory = lfs_storage_directory(repo)?;
+
+    let mut uploads: Vec<(&LfsObject, batch::Action, Option<batch::Action>)> = Vec::new();
+    let mut failures = Vec::new();
+
+    for chunk in objects.chunks(batch::MAX_OBJECTS_PER_REQUEST) {
+        let request_body = batch::RequestBody {
+            operation: batch::Operation::Upload,
+            transfers: vec!["basic".to_string()],
+            reference: Some(batch::Reference {
+                name: format!("refs/heads/{}", branch_name),
+            }),
+            objects: chunk.iter()
+                .map(|object| batch::ObjectSpec {
+                    oid: object.oid.clone(),
+                    size: object.size,
+                })
+                .collect(),
+        };
+
+        let response = github_client.lfs_batch(&request_body)?;
+
+        for response_object in response.objects {
+            let Some(object) = chunk.iter().find(|object| object.oid == response_object.oid) else {
+                continue
+            };
+
+            match (response_object.error, response_object.actions) {
+                (Some(error), _) => failures.push((object.path.clone(), Error::Api {
+                    operation: format!("upload Git LFS object {}", object.oid),
+                    status: error.code,
+                    body: error.message,
+                })),
+                (None, Some(batch::Actions { upload: Some(upload), verify })) => uploads.push((object, upload, verify)),
+                // - Without an upload action, GitHub already has the object
+                (None, _) => {},
+            }
+        }
+    }
+
+    let results = upload_concurrently(&uploads, max_concurrent_uploads, |(object, upload, verify)| {
+        let local_path = local_lfs_object(&storage_directory, object)?;
+
+        let object_spec = batch::ObjectSpec {
+            oid: object.oid.clone(),
+            size: object.size,
+        };
+
+        github_client.upload_lfs_object(upload, &object_spec, &local_path)?;
+
+        match verify {
+            Some(verify) => github_client.verify_lfs_object(verify, &object_spec),
+            None => Ok(()),
+        }
+    });
+
+    for ((object, _, _), result) in uploads.iter().zip(results) {
+        if let Err(e) = result {
+            failures.push((object.path.clone(), e));
+        }
+    }
+
+    match failures.len() {
+        0 => Ok(()),
+        1 => Err(failures.remove(0).1),
+        _ => Err(Error::Multiple {
+            operation: "upload a Git LFS object".to_string(),
+            failures: failures,
+        }),
+    }
+}
+
+#[cfg(test)]
+mod lfs_tests {
+    use crate::git_status::git_status;
+    use crate::test_utils::test_utils::TempGitRepo;
+
+    use super::{LfsObject, lfs_object_path, lfs_objects, parse_pointer};
+
+    const OID: &str = "4d7a214614ab2935c943f9e0ff69d22eadbb8f32b1258daaa5e2ca24d17e2393";
+
+    fn pointer(oid: &str, size: u64) -> String {
+        format!("version https://git-lfs.github.com/spec/v1\noid sha256:{}\nsize {}\n", oid, size)
+    }
+
+    #[test]
+    fn pointer_parsing() {
+        assert_eq!(parse_pointer(pointer(OID, 12345).as_bytes()), Some((OID.to_string(), 12345)));
+    }
+
+    #[test]
+    fn pointer_parsing_with_extensions() {
+        let contents = format!("version https://git-lfs.github.com/spec/v1\next-0-foo sha256:{}\noid sha256:{}\nsize 3\n", OID, OID);
+
+        assert_eq!(parse_pointer(contents.as_bytes()), Some((OID.to_string(), 3)));
+    }
+
+    #[test]
+    fn pointer_parsing_rejects_other_contents() {
+        assert_eq!(parse_pointer(b"just a file\n"), None);
+        assert_eq!(parse_pointer(pointer(&OID[1..], 3).as_bytes()), None);
+        assert_eq!(parse_pointer(pointer(&OID.to_uppercase(), 3).as_bytes()), None);
+        assert_eq!(parse_pointer(format!("version https://git-lfs.github.com/spec/v1\noid sha256:{}\n", OID).as_bytes()), None);
+        assert_eq!(parse_pointer(format!("version https://example.com/spec/v2\noid sha256:{}\nsize 3\n", OID).as_bytes()), None);
+    }
+
+    #[test]
+    fn object_path_layout() {
+        let path = lfs_object_path(std::path::Path::new(".git/lfs/objects"), OID);
+
+        assert_eq!(path, std::path::Path::new(".git/lfs/objects/4d/7a").join(OID));
+    }
+
+    #[test]
+    fn lfs_tracked_pointers_are_found() {
+        let repo = TempGitRepo::new();
+
+        let gitattributes = repo.create_or_replace_blob_file(".gitattributes", "*.bin filter=lfs diff=lfs merge=lfs -text\n".as_bytes());
+        let image = repo.create_or_replace_blob_file("image.bin", pointer(OID, 3).as_bytes());
+        // - Not tracked, so committed as is even though it looks like one
+        let text = repo.create_or_replace_blob_file("pointer.txt", pointer(OID, 3).as_bytes());
+
+        repo.git_add(&gitattributes);
+        repo.git_add(&image);
+        repo.git_add(&text);
+
+        let status = git_status(&repo.repo)
+            .expect("Unable to get a git status");
+
+        let objects = lfs_objects(&repo.repo, &status)
+            .expect("Unable to find the LFS objects");
+
+        assert_eq!(objects, vec![LfsObject {
+            path: "image.bin".to_string(),
+            oid: OID.to_string(),
+            size: 3,
+        }]);
+    }
+
+    #[test]
+    fn lfs_tracked_contents_are_rejected() {
+        let repo = TempGitRepo::new();
+
+        let gitattributes = repo.create_or_replace_blob_file(".gitattributes", "*.bin filter=lfs diff=lfs merge=lfs -text\n".as_bytes());
+        let image = repo.create_or_replace_blob_file("image.bin", &[0x00, 0x80, 0xff]);
+
+        repo.git_add(&gitattributes);
+        repo.git_add(&image);
+
+        let status = git_status(&repo.repo)
+            .expect("Unable to get a git status");
+
+        let error = lfs_objects(&repo.repo, &status)
+            .expect_err("Expected LFS-tracked contents to be rejected");
+
+        assert_eq!(error.kind(), "unsupported");
+        assert!(error.to_string().contains("image.bin"), "Unexpected error: {}", error);
+    }
+}
@@ -1,8 +1,22 @@
 #![allow(clippy::redundant_field_names)]
 
+pub mod backend;
+pub mod commit_message;
 pub mod config;
 pub mod create_a_tree_prep;
+pub mod create_commit_on_branch_prep;
+pub mod dry_run;
+pub mod error;
+pub mod git_log;
 pub mod git_status;
 pub mod github;
+pub mod github_actions;
+pub mod identity;
+pub mod lfs;
 pub mod log;
+pub mod output;
+pub mod private_key;
+pub mod pull_request;
+pub mod retry;
+pub mod sync_local;
 pub mod test_utils;
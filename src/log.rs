@@ -1,4 +1,5 @@
 use std::fmt::Debug;
+use std::time::Duration;
 
 use colored::Colorize;
 
@@ -21,3 +22,13 @@ pub fn print_success_and_return<T: Debug, R>(title: &str, debuggable: T) -> Resu
 pub fn print_success_plain(title: &str) {
     eprintln!("{}", title.bold().green());
 }
+
+pub fn print_warning(title: &str) {
+    eprintln!("{}", title.bold().yellow());
+}
+
+pub fn print_retry(title: &str, reason: &str, wait: Duration, next_attempt: u32, max_attempts: u32) {
+    let s = format!("{}: {}; retrying in {:.1}s (attempt {} of {})", title.bold(), reason, wait.as_secs_f64(), next_attempt, max_attempts);
+
+    eprintln!("{}", s.yellow());
+}
@@ -1,123 +1,150 @@
 #![allow(clippy::redundant_field_names)]
 
 use colored::Colorize;
+use ghommit::backend::new_backend;
 use ghommit::config::Config;
-use ghommit::create_a_tree_prep;
-use ghommit::git_status::git_status;
+use ghommit::dry_run::{print_commit_plan, print_replay_plan};
+use ghommit::git_status::git_status_with;
 use ghommit::github::{GitHubClient, GitHubRepo};
-use ghommit::github::rest_api::{create_a_commit, create_a_reference, get_a_reference, update_a_reference};
+use ghommit::github_actions;
+use ghommit::error::{Error, Result};
+use ghommit::output::{CommitOutput, ErrorDetails, ErrorOutput};
+use ghommit::pull_request::{apply_pull_request_options, open_or_update_pull_request};
+use ghommit::sync_local::sync_local;
+
+fn create_github_client(config: &Config) -> Result<GitHubClient> {
+    // - Credentials are only skipped for a dry run, which returns before any
+    //   client is needed
+    let github_authentication = config.github_authentication.clone()
+        .ok_or_else(|| Error::auth("No GitHub credentials were resolved"))?;
 
-fn create_github_client(config: &Config) -> GitHubClient {
     let github_repo = GitHubRepo {
         owner: config.github_repo_owner.clone(),
         name: config.github_repo_name.clone(),
     };
 
-    GitHubClient::new(
-        config.github_app_id,
-        config.github_app_installation_id,
-        config.github_app_private_key.clone(),
+    let github_client = GitHubClient::new(
+        github_authentication,
+        config.github_endpoints.clone(),
         github_repo,
     )
-}
-
-fn generate_create_a_commit_body(config: &Config, tree_sha: &str) -> create_a_commit::RequestBody {
-    create_a_commit::RequestBody {
-        message: config.commit_message.to_string(),
-        parents: vec![config.git_head_object_id.to_string()],
-        tree: tree_sha.to_string(),
-    }
-}
+    .with_retry_policy(config.github_retry_policy)
+    .with_http_client_options(config.github_http_client_options.clone());
 
-fn fully_qualify_branch_name(unqualified_name: &str) -> String {
-    format!("refs/heads/{}", unqualified_name)
+    Ok(github_client)
 }
 
-fn partially_qualify_branch_name(unqualified_name: &str) -> String {
-    format!("heads/{}", unqualified_name)
-}
+/// Returns the commit the branch was moved to, or `None` for a dry run
+fn ghommit() -> Result<Option<CommitOutput>> {
+    let maybe_repo = git2::Repository::open(".");
+    let config = Config::gather(maybe_repo)?;
 
-fn branch_exists(github_client: &GitHubClient, config: &Config) -> Result<bool, String> {
-    let reference_name = partially_qualify_branch_name(&config.git_branch_name);
+    let staged_status = match config.git_should_replay_commits {
+        true => None,
+        false => {
+            let base_commit_id = git2::Oid::from_str(&config.git_base_object_id)
+                .map_err(|e| Error::git(format!("parse {:?} as a git object ID", config.git_base_object_id), e))?;
+            let status = git_status_with(&config.git_repo, base_commit_id, &config.git_status_options)?;
 
-    let get_a_reference_response = github_client.get_a_reference(&reference_name)?;
+            if status.is_empty() {
+                return Err(Error::NoChanges)
+            }
 
-    let exists = match get_a_reference_response {
-        get_a_reference::ResponseBody::Ok(_) => true,
-        get_a_reference::ResponseBody::NotFound(_) => false,
+            Some(status)
+        },
     };
 
-    Ok(exists)
-}
+    if config.should_dry_run {
+        match &staged_status {
+            Some(status) => print_commit_plan(&config, status),
+            None => print_replay_plan(&config),
+        }?;
 
-fn update_a_reference(config: &Config, github_client: &GitHubClient, commit_sha: &str) -> Result<update_a_reference::ResponseBody, String> {
-    let payload = update_a_reference::RequestBody {
-        sha: commit_sha.to_string(),
-        force: config.git_should_force_push,
-    };
+        return Ok(None)
+    }
 
-    let reference_name = partially_qualify_branch_name(&config.git_branch_name);
+    let github_client = create_github_client(&config)?;
+    let backend = new_backend(config.commit_backend, &github_client);
 
-    github_client.update_a_reference(&reference_name, &payload)
-}
+    let commit = match &staged_status {
+        Some(status) => backend.commit_staged_changes(&config, status),
+        None => backend.replay_unpushed_commits(&config),
+    }?;
 
-fn create_a_reference(config: &Config, github_client: &GitHubClient, commit_sha: &str) -> Result<create_a_reference::ResponseBody, String> {
-    let payload = create_a_reference::RequestBody {
-        reference: fully_qualify_branch_name(&config.git_branch_name),
-        sha: commit_sha.to_string(),
+    let sync_result = match config.git_should_sync_local {
+        true => sync_local(&config, &github_client, &commit, staged_status.as_deref()),
+        false => Ok(()),
     };
 
-    github_client.create_a_reference(&payload)
-}
+    let mut output = CommitOutput::new(&config.git_branch_name, commit);
 
-fn ghommit() -> Result<String, String> {
-    let maybe_repo = git2::Repository::open(".");
-    let config = Config::gather(maybe_repo)?;
-
-    let status = git_status(&config.git_repo)?;
+    // - The branch has already moved on GitHub, so a local repository that
+    //   couldn't be synced doesn't make the run a failure
+    if let Err(e) = sync_result {
+        eprintln!("::warning::The commit landed, but the local repository couldn't be synced: {}", e);
 
-    if status.is_empty() {
-        return Err("No changes to commit".to_string())
+        output.sync_error = Some(ErrorDetails::from(&e));
     }
 
-    let github_client = create_github_client(&config);
-
-    // - Create the tree, creating the blobs if necessary implicitly
-
-    let tree_payload = create_a_tree_prep::generate_request_body(&config, &config.git_repo, &status, &github_client)?;
-    let tree = github_client.create_a_tree(&tree_payload)?;
+    // - Like syncing, a pull request that couldn't be opened or set up doesn't
+    //   undo the commit, so it's reported alongside it
+    if let Some(options) = &config.pull_request {
+        let pull_request_result = open_or_update_pull_request(&config, options, &github_client)
+            .and_then(|pull_request| {
+                output.pr_url = Some(pull_request.html_url.clone());
 
-    // - Create the commit
+                apply_pull_request_options(&pull_request, options, &github_client)
+            });
 
-    let commit_payload = generate_create_a_commit_body(&config, &tree.sha);
-    let commit = github_client.create_a_commit(&commit_payload)?;
+        if let Err(e) = pull_request_result {
+            eprintln!("::warning::The commit landed, but the pull request failed: {}", e);
 
-    // - If branch exists, update it, else create it
+            output.pr_error = Some(ErrorDetails::from(&e));
+        }
+    }
 
-    match branch_exists(&github_client, &config)? {
-        true => update_a_reference(&config, &github_client, &commit.sha),
-        false => create_a_reference(&config, &github_client, &commit.sha),
-    }?;
+    // - The commit has already landed, so failing to report it to the
+    //   workflow is only worth a warning
+    if let Err(e) = github_actions::report(&output, staged_status.as_deref()) {
+        // - The runner picks up workflow commands from stderr as well, which
+        //   keeps stdout to the JSON output
+        eprintln!("::warning::{}", e);
+    }
 
-    Ok(commit.html_url)
+    Ok(Some(output))
 }
 
-fn main() -> Result<(), String> {
-    // Match so that Strings in an Err can be pulled out and printed without
-    // the Err wrapping so newlines aren't escaped
+fn main() {
     match ghommit() {
-        Ok(commit_url) => {
-            let output = format!(r#"{{"commit_url": "{}"}}"#, commit_url);
-            println!("{}", output);
-            Ok(())
+        Ok(Some(output)) => {
+            match serde_json::to_string(&output) {
+                Ok(json) => println!("{}", json),
+                Err(e) => {
+                    eprintln!("{}", format!("Unable to serialize output {:?}: {}", output, e).red());
+                    std::process::exit(1)
+                },
+            }
         }
+        Ok(None) => {},
+        // - Help, version, and usage text is printed the way clap formats it
+        //   rather than as an error to report as JSON
+        Err(Error::Usage(e)) => e.exit(),
         Err(e) => {
-            if e.starts_with("ghommit: GitHub commit") {
-                eprintln!("{}", e)
-            } else {
-                eprintln!("{}", e.red());
+            // - Print the message itself rather than the `Debug` form so
+            //   newlines aren't escaped
+            eprintln!("{}", e.to_string().red());
+
+            let output = ErrorOutput::from(&e);
+
+            if let Err(report_error) = github_actions::report_error(&output) {
+                eprintln!("::warning::{}", report_error);
             }
-            std::process::exit(1)
+
+            if let Ok(json) = serde_json::to_string(&output) {
+                println!("{}", json);
+            }
+
+            std::process::exit(e.exit_code())
         }
     }
 }
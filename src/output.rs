@@ -0,0 +1,175 @@
+use serde::Serialize;
+
+use crate::backend::{CreatedCommit, RefAction};
+use crate::error::Error;
+
+/// What's printed to stdout once the branch has been moved
+#[derive(Debug, Serialize)]
+pub struct CommitOutput {
+    pub commit_sha: String,
+    pub commit_url: String,
+    pub tree_sha: String,
+    pub verified: bool,
+    pub branch: String,
+    pub ref_action: RefAction,
+    /// The pull request that was opened or updated, if one was asked for
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub pr_url: Option<String>,
+    /// Why the pull request couldn't be opened, updated, or set up, if that
+    /// failed after the branch was moved
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub pr_error: Option<ErrorDetails>,
+    /// Why the local repository couldn't be synced, if `--sync-local` failed
+    /// after the branch was moved
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub sync_error: Option<ErrorDetails>,
+}
+
+impl CommitOutput {
+    pub fn new(branch: &str, commit: CreatedCommit) -> CommitOutput {
+        CommitOutput {
+            commit_sha: commit.sha,
+            commit_url: commit.html_url,
+            tree_sha: commit.tree_sha,
+            verified: commit.verified,
+            branch: branch.to_string(),
+            ref_action: commit.ref_action,
+            pr_url: None,
+            pr_error: None,
+            sync_error: None,
+        }
+    }
+}
+
+#[derive(Debug, Serialize)]
+pub struct ErrorDetails {
+    /// `Error::kind`, so that scripts can tell failures apart without
+    /// matching on messages
+    pub kind: &'static str,
+    pub message: String,
+    /// The HTTP status code, for errors returned by GitHub's APIs
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub status: Option<u16>,
+}
+
+/// What's printed to stdout when ghommit fails
+#[derive(Debug, Serialize)]
+pub struct ErrorOutput {
+    pub error: ErrorDetails,
+}
+
+impl From<&Error> for ErrorDetails {
+    fn from(error: &Error) -> ErrorDetails {
+        let status = match error {
+            Error::Api { status, .. } | Error::RateLimited { status, .. } => Some(*status),
+            _ => None,
+        };
+
+        ErrorDetails {
+            kind: error.kind(),
+            message: error.to_string(),
+            status: status,
+        }
+    }
+}
+
+impl From<&Error> for ErrorOutput {
+    fn from(error: &Error) -> ErrorOutput {
+        ErrorOutput {
+            error: ErrorDetails::from(error),
+        }
+    }
+}
+
+#[cfg(test)]
+mod output_tests {
+    use crate::backend::RefAction;
+    use crate::error::Error;
+
+    use super::{CommitOutput, ErrorDetails, ErrorOutput};
+
+    #[test]
+    fn commit_output_serialization() {
+        let output = CommitOutput {
+            commit_sha: "7638417db6d59f3c431d3e1f261cc637155684cd".to_string(),
+            commit_url: "https://github.com/octocat/Hello-World/commit/7638417db6d59f3c431d3e1f261cc637155684cd".to_string(),
+            tree_sha: "827efc6d56897b048c772eb4087f854f46256132".to_string(),
+            verified: true,
+            branch: "feature/\"quoted\"".to_string(),
+            ref_action: RefAction::Created,
+            pr_url: None,
+            pr_error: None,
+            sync_error: None,
+        };
+
+        let actual = serde_json::to_string(&output).unwrap();
+        let expected = r#"{"commit_sha":"7638417db6d59f3c431d3e1f261cc637155684cd","commit_url":"https://github.com/octocat/Hello-World/commit/7638417db6d59f3c431d3e1f261cc637155684cd","tree_sha":"827efc6d56897b048c772eb4087f854f46256132","verified":true,"branch":"feature/\"quoted\"","ref_action":"created"}"#;
+
+        assert_eq!(actual, expected);
+    }
+
+    #[test]
+    fn commit_output_serialization_with_pull_request() {
+        let output = CommitOutput {
+            commit_sha: "7638417db6d59f3c431d3e1f261cc637155684cd".to_string(),
+            commit_url: "https://github.com/octocat/Hello-World/commit/7638417db6d59f3c431d3e1f261cc637155684cd".to_string(),
+            tree_sha: "827efc6d56897b048c772eb4087f854f46256132".to_string(),
+            verified: false,
+            branch: "feature".to_string(),
+            ref_action: RefAction::Updated,
+            pr_url: Some("https://github.com/octocat/Hello-World/pull/1347".to_string()),
+            pr_error: None,
+            sync_error: None,
+        };
+
+        let actual = serde_json::to_string(&output).unwrap();
+        let expected = r#"{"commit_sha":"7638417db6d59f3c431d3e1f261cc637155684cd","commit_url":"https://github.com/octocat/Hello-World/commit/7638417db6d59f3c431d3e1f261cc637155684cd","tree_sha":"827efc6d56897b048c772eb4087f854f46256132","verified":false,"branch":"feature","ref_action":"updated","pr_url":"https://github.com/octocat/Hello-World/pull/1347"}"#;
+
+        assert_eq!(actual, expected);
+    }
+
+    #[test]
+    fn commit_output_serialization_with_sync_error() {
+        let output = CommitOutput {
+            commit_sha: "7638417db6d59f3c431d3e1f261cc637155684cd".to_string(),
+            commit_url: "https://github.com/octocat/Hello-World/commit/7638417db6d59f3c431d3e1f261cc637155684cd".to_string(),
+            tree_sha: "827efc6d56897b048c772eb4087f854f46256132".to_string(),
+            verified: true,
+            branch: "main".to_string(),
+            ref_action: RefAction::Updated,
+            pr_url: None,
+            pr_error: None,
+            sync_error: Some(ErrorDetails::from(&Error::conflict("Local branch \"main\" has commits the remote doesn't"))),
+        };
+
+        let actual = serde_json::to_string(&output).unwrap();
+        let expected = r#"{"commit_sha":"7638417db6d59f3c431d3e1f261cc637155684cd","commit_url":"https://github.com/octocat/Hello-World/commit/7638417db6d59f3c431d3e1f261cc637155684cd","tree_sha":"827efc6d56897b048c772eb4087f854f46256132","verified":true,"branch":"main","ref_action":"updated","sync_error":{"kind":"conflict","message":"Local branch \"main\" has commits the remote doesn't"}}"#;
+
+        assert_eq!(actual, expected);
+    }
+
+    #[test]
+    fn error_output_serialization() {
+        let output = ErrorOutput::from(&Error::NoChanges);
+
+        let actual = serde_json::to_string(&output).unwrap();
+        let expected = r#"{"error":{"kind":"no_changes","message":"No changes to commit"}}"#;
+
+        assert_eq!(actual, expected);
+    }
+
+    #[test]
+    fn api_error_output_serialization() {
+        let error = Error::Api {
+            operation: "create a tree".to_string(),
+            status: 422,
+            body: "Invalid tree info".to_string(),
+        };
+        let output = ErrorOutput::from(&error);
+
+        let actual = serde_json::to_string(&output).unwrap();
+        let expected = r#"{"error":{"kind":"api","message":"Unexpected status code 422 while trying to create a tree: Invalid tree info","status":422}}"#;
+
+        assert_eq!(actual, expected);
+    }
+}
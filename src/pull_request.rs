@@ -0,0 +1,201 @@
+use crate::config::Config;
+use crate::error::{Error, Result};
+use crate::github::GitHubClient;
+use crate::github::graphql_api::enable_pull_request_auto_merge::{self, MergeMethod};
+use crate::github::rest_api::shared::PullRequestResponseBody;
+use crate::github::rest_api::{add_labels_to_an_issue, create_a_pull_request, request_reviewers_for_a_pull_request, update_a_pull_request};
+
+/// How to open a pull request for the branch once it has been moved
+#[derive(Debug, Default)]
+pub struct PullRequestOptions {
+    /// Defaults to the commit message's first line, or the branch name when
+    /// replaying commits, when opening a pull request; an open one's title is
+    /// left alone unless this is set
+    pub title: Option<String>,
+    pub body: Option<String>,
+    /// Defaults to the repository's default branch
+    pub base: Option<String>,
+    pub draft: bool,
+    pub labels: Vec<String>,
+    /// Users, or teams in the `<org>/<team>` form
+    pub reviewers: Vec<String>,
+    pub auto_merge: Option<MergeMethod>,
+}
+
+/// The pull request that was opened or updated
+#[derive(Debug)]
+pub struct PullRequest {
+    pub number: u64,
+    pub html_url: String,
+    pub node_id: String,
+}
+
+fn title(options: &PullRequestOptions, commit_message: &str, branch_name: &str) -> String {
+    if let Some(title) = &options.title {
+        return title.clone()
+    }
+
+    match commit_message.lines().next().map(str::trim) {
+        Some(headline) if !headline.is_empty() => headline.to_string(),
+        _ => branch_name.to_string(),
+    }
+}
+
+/// Splits reviewers into users and team slugs, since GitHub takes them in
+/// separate lists
+///
+/// - Teams are given as `<org>/<team>`, but only the slug is sent since the
+///   organization is the repository owner's
+fn split_reviewers(reviewers: &[String]) -> (Vec<String>, Vec<String>) {
+    let mut users = Vec::new();
+    let mut teams = Vec::new();
+
+    for reviewer in reviewers {
+        match reviewer.split_once('/') {
+            Some((_org, team)) => teams.push(team.to_string()),
+            None => users.push(reviewer.clone()),
+        }
+    }
+
+    (users, teams)
+}
+
+fn create_or_update(config: &Config, options: &PullRequestOptions, github_client: &GitHubClient) -> Result<PullRequestResponseBody> {
+    // - Only open pull requests whose head is this branch are considered, so
+    //   rerunning a workflow updates its pull request instead of opening
+    //   another one
+    let existing = github_client.list_pull_requests(&config.git_branch_name)?
+        .into_iter()
+        .next();
+
+    match existing {
+        // - Only what was explicitly given is updated, so rerunning a workflow
+        //   doesn't overwrite a title or body someone edited since
+        Some(existing) => {
+            let payload = update_a_pull_request::RequestBody {
+                title: options.title.clone(),
+                body: options.body.clone(),
+                base: options.base.clone(),
+            };
+
+            if payload.title.is_none() && payload.body.is_none() && payload.base.is_none() {
+                return Ok(existing)
+            }
+
+            github_client.update_a_pull_request(existing.number, &payload)
+        },
+        None => {
+            let base = match &options.base {
+                Some(base) => base.clone(),
+                None => github_client.get_a_repository()?.default_branch,
+            };
+
+            if base == config.git_branch_name {
+                Err(Error::config(format!("Can't open a pull request from {:?} into itself; pass --pr-base to choose another base branch", base)))?
+            }
+
+            let payload = create_a_pull_request::RequestBody {
+                title: title(options, &config.commit_message, &config.git_branch_name),
+                head: config.git_branch_name.clone(),
+                base: base,
+                body: options.body.clone(),
+                draft: options.draft,
+            };
+
+            github_client.create_a_pull_request(&payload)
+        },
+    }
+}
+
+fn enable_auto_merge(pull_request: &PullRequest, merge_method: MergeMethod, github_client: &GitHubClient) -> Result<()> {
+    let input = enable_pull_request_auto_merge::Input {
+        pull_request_id: pull_request.node_id.clone(),
+        merge_method: merge_method,
+    };
+
+    let response = github_client.enable_pull_request_auto_merge(&input)?;
+
+    if !response.errors.is_empty() {
+        let messages: Vec<&str> = response.errors.iter()
+            .map(|error| error.message.as_str())
+            .collect();
+
+        Err(Error::GraphQl {
+            operation: format!("enable auto-merge for pull request #{}", pull_request.number),
+            message: messages.join("; "),
+        })?
+    }
+
+    Ok(())
+}
+
+/// Opens a pull request for the branch, or updates the one that's already
+/// open
+pub fn open_or_update_pull_request(config: &Config, options: &PullRequestOptions, github_client: &GitHubClient) -> Result<PullRequest> {
+    let pull_request = create_or_update(config, options, github_client)?;
+
+    Ok(PullRequest {
+        number: pull_request.number,
+        html_url: pull_request.html_url,
+        node_id: pull_request.node_id,
+    })
+}
+
+/// Applies labels, reviewers, and auto-merge to a pull request that was
+/// opened or updated
+pub fn apply_pull_request_options(pull_request: &PullRequest, options: &PullRequestOptions, github_client: &GitHubClient) -> Result<()> {
+    if !options.labels.is_empty() {
+        let payload = add_labels_to_an_issue::RequestBody {
+            labels: options.labels.clone(),
+        };
+
+        github_client.add_labels_to_an_issue(pull_request.number, &payload)?;
+    }
+
+    if !options.reviewers.is_empty() {
+        let (users, teams) = split_reviewers(&options.reviewers);
+
+        let payload = request_reviewers_for_a_pull_request::RequestBody {
+            reviewers: users,
+            team_reviewers: teams,
+        };
+
+        github_client.request_reviewers_for_a_pull_request(pull_request.number, &payload)?;
+    }
+
+    if let Some(merge_method) = options.auto_merge {
+        enable_auto_merge(pull_request, merge_method, github_client)?;
+    }
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod pull_request_tests {
+    use super::{PullRequestOptions, split_reviewers, title};
+
+    #[test]
+    fn title_defaults_to_commit_message_headline() {
+        let options = PullRequestOptions::default();
+
+        assert_eq!(title(&options, "Add foo\n\nBecause bar", "feature"), "Add foo");
+        assert_eq!(title(&options, "", "feature"), "feature");
+
+        let options = PullRequestOptions {
+            title: Some("Custom".to_string()),
+            ..PullRequestOptions::default()
+        };
+
+        assert_eq!(title(&options, "Add foo", "feature"), "Custom");
+    }
+
+    #[test]
+    fn reviewers_are_split_into_users_and_teams() {
+        let reviewers = vec!["octocat".to_string(), "github/justice-league".to_string()];
+
+        let (users, teams) = split_reviewers(&reviewers);
+
+        assert_eq!(users, vec!["octocat".to_string()]);
+        assert_eq!(teams, vec!["justice-league".to_string()]);
+    }
+}
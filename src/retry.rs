@@ -0,0 +1,295 @@
+use std::collections::hash_map::RandomState;
+use std::hash::{BuildHasher, Hasher};
+use std::time::Duration;
+
+use reqwest::StatusCode;
+use reqwest::header::{self, HeaderMap};
+
+use crate::github::custom_header;
+
+/// How API requests that failed transiently are retried
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub struct RetryPolicy {
+    /// Total number of attempts, including the first one
+    pub max_attempts: u32,
+    /// The longest to wait between two attempts
+    ///
+    /// If GitHub asks for a longer wait, such as when the primary rate limit
+    /// only resets in half an hour, the request fails instead of waiting.
+    pub max_wait: Duration,
+    /// The wait before the first retry, which doubles with every retry
+    pub initial_backoff: Duration,
+}
+
+impl Default for RetryPolicy {
+    fn default() -> RetryPolicy {
+        RetryPolicy {
+            max_attempts: 5,
+            max_wait: Duration::from_secs(60),
+            initial_backoff: Duration::from_secs(1),
+        }
+    }
+}
+
+impl RetryPolicy {
+    /// Exponential backoff with "equal jitter": half of the backoff is fixed,
+    /// and the other half is random so that concurrent runs spread out
+    ///
+    /// - `attempts_made` is the number of attempts that have failed so far
+    fn backoff(&self, attempts_made: u32) -> Duration {
+        // - Capping the exponent keeps the multiplication from overflowing;
+        //   `max_wait` caps the result well before that anyway
+        let exponent = attempts_made.saturating_sub(1).min(16);
+        let backoff = self.initial_backoff.saturating_mul(1 << exponent).min(self.max_wait);
+
+        let half = backoff / 2;
+
+        half + random_duration_up_to(backoff - half)
+    }
+
+    /// Returns how long to wait before the next attempt, or `None` if the
+    /// request shouldn't be retried anymore
+    ///
+    /// - `attempts_made` is the number of attempts that have failed so far
+    /// - `requested_wait` is how long GitHub asked to wait, if it did
+    pub fn wait_before_retry(&self, attempts_made: u32, requested_wait: Option<Duration>) -> Option<Duration> {
+        if attempts_made >= self.max_attempts {
+            return None
+        }
+
+        match requested_wait {
+            Some(wait) if wait > self.max_wait => None,
+            Some(wait) => Some(wait),
+            None => Some(self.backoff(attempts_made)),
+        }
+    }
+
+    /// Like `wait_before_retry`, but falls back to the failure's
+    /// `fallback_wait`, capped by `max_wait`, if GitHub didn't ask for a wait
+    pub fn wait_before_retrying(&self, attempts_made: u32, failure: &RetryableFailure) -> Option<Duration> {
+        let requested_wait = failure.requested_wait
+            .or_else(|| failure.fallback_wait.map(|wait| wait.min(self.max_wait)));
+
+        self.wait_before_retry(attempts_made, requested_wait)
+    }
+}
+
+/// A transient failure worth retrying
+#[derive(Debug, Eq, PartialEq)]
+pub struct RetryableFailure {
+    pub reason: String,
+    /// How long GitHub asked to wait, if it did
+    pub requested_wait: Option<Duration>,
+    /// How long to wait if GitHub didn't ask, when its documentation says to
+    /// wait longer than the usual backoff
+    pub fallback_wait: Option<Duration>,
+}
+
+/// How long GitHub's documentation says to wait after a secondary rate limit
+/// that came without a `Retry-After` header
+const SECONDARY_RATE_LIMIT_WAIT: Duration = Duration::from_secs(60);
+
+/// - `RandomState` is seeded randomly, which is plenty for jitter and avoids
+///   pulling in a random number generator
+fn random_duration_up_to(max: Duration) -> Duration {
+    let max_nanos = max.as_nanos() as u64;
+
+    if max_nanos == 0 {
+        return Duration::ZERO
+    }
+
+    let random = RandomState::new().build_hasher().finish();
+
+    Duration::from_nanos(random % (max_nanos + 1))
+}
+
+fn header_u64(headers: &HeaderMap, name: impl header::AsHeaderName) -> Option<u64> {
+    headers.get(name)
+        .and_then(|value| value.to_str().ok())
+        .and_then(|value| value.trim().parse::<u64>().ok())
+}
+
+/// How long GitHub asked to wait, either directly with `Retry-After` or by
+/// reporting an exhausted rate limit and when it resets
+///
+/// - [Rate limits for the REST API](https://docs.github.com/en/rest/using-the-rest-api/rate-limits-for-the-rest-api#exceeding-the-rate-limit)
+/// - Only the delay-seconds form of `Retry-After` is handled since that's the
+///   form GitHub sends
+fn requested_wait(headers: &HeaderMap, unix_epoch_second_now: u64) -> Option<Duration> {
+    if let Some(seconds) = header_u64(headers, header::RETRY_AFTER) {
+        return Some(Duration::from_secs(seconds))
+    }
+
+    let remaining = header_u64(headers, &*custom_header::X_RATELIMIT_REMAINING);
+    let reset = header_u64(headers, &*custom_header::X_RATELIMIT_RESET);
+
+    match (remaining, reset) {
+        // - The reset time is rounded down to the second, so wait an extra
+        //   second to not retry a moment too early
+        (Some(0), Some(reset)) => Some(Duration::from_secs(reset.saturating_sub(unix_epoch_second_now) + 1)),
+        _ => None,
+    }
+}
+
+/// Whether a 403's body says that a secondary rate limit was exceeded, such
+/// as "You have exceeded a secondary rate limit. Please wait a few minutes
+/// before you try again."
+fn is_secondary_rate_limit(body: &str) -> bool {
+    body.contains("exceeded a secondary rate limit")
+}
+
+/// Returns why a response is worth retrying, or `None` if it isn't
+///
+/// - Server errors and rate limits are retried
+/// - Secondary rate limits are reported with a 403, which is told apart from
+///   a permissions problem by the rate limit headers, or by `body` when
+///   GitHub sent none of them
+pub fn retryable_response(status_code: StatusCode, headers: &HeaderMap, body: Option<&str>, unix_epoch_second_now: u64) -> Option<RetryableFailure> {
+    let requested_wait = requested_wait(headers, unix_epoch_second_now);
+    let mut fallback_wait = None;
+
+    let reason = match status_code {
+        StatusCode::TOO_MANY_REQUESTS => "rate limited".to_string(),
+        StatusCode::FORBIDDEN if requested_wait.is_some() => "rate limited".to_string(),
+        StatusCode::FORBIDDEN if body.is_some_and(is_secondary_rate_limit) => {
+            fallback_wait = Some(SECONDARY_RATE_LIMIT_WAIT);
+            "secondary rate limited".to_string()
+        },
+        StatusCode::INTERNAL_SERVER_ERROR
+        | StatusCode::BAD_GATEWAY
+        | StatusCode::SERVICE_UNAVAILABLE
+        | StatusCode::GATEWAY_TIMEOUT => format!("status code {}", status_code),
+        _ => return None,
+    };
+
+    Some(RetryableFailure {
+        reason: reason,
+        requested_wait: requested_wait,
+        fallback_wait: fallback_wait,
+    })
+}
+
+#[cfg(test)]
+mod retry_tests {
+    use std::time::Duration;
+
+    use reqwest::StatusCode;
+    use reqwest::header::{HeaderMap, HeaderValue};
+
+    use super::{RetryPolicy, SECONDARY_RATE_LIMIT_WAIT, retryable_response};
+
+    const NOW: u64 = 1_700_000_000;
+
+    fn headers(pairs: &[(&'static str, &str)]) -> HeaderMap {
+        let mut headers = HeaderMap::new();
+
+        for (name, value) in pairs {
+            headers.insert(*name, HeaderValue::from_str(value).unwrap());
+        }
+
+        headers
+    }
+
+    #[test]
+    fn backoff_grows_and_is_capped() {
+        let policy = RetryPolicy {
+            max_attempts: 10,
+            max_wait: Duration::from_secs(8),
+            initial_backoff: Duration::from_secs(1),
+        };
+
+        for (attempts_made, expected_cap) in [(1, 1), (2, 2), (3, 4), (4, 8), (5, 8), (9, 8)] {
+            let expected_cap = Duration::from_secs(expected_cap);
+            let wait = policy.wait_before_retry(attempts_made, None).unwrap();
+
+            assert!(wait >= expected_cap / 2, "{:?} is shorter than half of {:?}", wait, expected_cap);
+            assert!(wait <= expected_cap, "{:?} is longer than {:?}", wait, expected_cap);
+        }
+    }
+
+    #[test]
+    fn attempts_are_limited() {
+        let policy = RetryPolicy {
+            max_attempts: 3,
+            ..RetryPolicy::default()
+        };
+
+        assert!(policy.wait_before_retry(2, None).is_some());
+        assert_eq!(policy.wait_before_retry(3, None), None);
+    }
+
+    #[test]
+    fn requested_wait_is_honored_up_to_max_wait() {
+        let policy = RetryPolicy::default();
+
+        assert_eq!(policy.wait_before_retry(1, Some(Duration::from_secs(30))), Some(Duration::from_secs(30)));
+        assert_eq!(policy.wait_before_retry(1, Some(Duration::from_secs(3600))), None);
+    }
+
+    #[test]
+    fn server_errors_are_retryable() {
+        let failure = retryable_response(StatusCode::BAD_GATEWAY, &HeaderMap::new(), None, NOW).unwrap();
+
+        assert_eq!(failure.requested_wait, None);
+    }
+
+    #[test]
+    fn client_errors_are_not_retryable() {
+        assert_eq!(retryable_response(StatusCode::NOT_FOUND, &HeaderMap::new(), None, NOW), None);
+        assert_eq!(retryable_response(StatusCode::UNPROCESSABLE_ENTITY, &HeaderMap::new(), None, NOW), None);
+    }
+
+    #[test]
+    fn forbidden_without_rate_limit_headers_is_not_retryable() {
+        let headers = headers(&[("x-ratelimit-remaining", "4999"), ("x-ratelimit-reset", "1700000100")]);
+
+        assert_eq!(retryable_response(StatusCode::FORBIDDEN, &headers, None, NOW), None);
+    }
+
+    #[test]
+    fn secondary_rate_limit_honors_retry_after() {
+        let headers = headers(&[("retry-after", "42")]);
+
+        let failure = retryable_response(StatusCode::FORBIDDEN, &headers, None, NOW).unwrap();
+
+        assert_eq!(failure.requested_wait, Some(Duration::from_secs(42)));
+    }
+
+    #[test]
+    fn secondary_rate_limit_is_recognized_by_its_body() {
+        let body = r#"{"message":"You have exceeded a secondary rate limit. Please wait a few minutes before you try again."}"#;
+
+        let failure = retryable_response(StatusCode::FORBIDDEN, &HeaderMap::new(), Some(body), NOW).unwrap();
+
+        assert_eq!(failure.requested_wait, None);
+        assert_eq!(failure.fallback_wait, Some(SECONDARY_RATE_LIMIT_WAIT));
+
+        // - The fallback wait is capped by `max_wait` instead of giving up
+        let policy = RetryPolicy {
+            max_wait: Duration::from_secs(8),
+            ..RetryPolicy::default()
+        };
+        assert_eq!(policy.wait_before_retrying(1, &failure), Some(Duration::from_secs(8)));
+        assert_eq!(RetryPolicy::default().wait_before_retrying(1, &failure), Some(SECONDARY_RATE_LIMIT_WAIT));
+
+        // - Other 403s are still permissions problems
+        let body = r#"{"message":"Resource not accessible by integration"}"#;
+        assert_eq!(retryable_response(StatusCode::FORBIDDEN, &HeaderMap::new(), Some(body), NOW), None);
+    }
+
+    #[test]
+    fn primary_rate_limit_waits_until_reset() {
+        let headers = headers(&[("x-ratelimit-remaining", "0"), ("x-ratelimit-reset", "1700000009")]);
+
+        let failure = retryable_response(StatusCode::FORBIDDEN, &headers, None, NOW).unwrap();
+
+        assert_eq!(failure.requested_wait, Some(Duration::from_secs(10)));
+    }
+
+    #[test]
+    fn too_many_requests_is_retryable_without_headers() {
+        let failure = retryable_response(StatusCode::TOO_MANY_REQUESTS, &HeaderMap::new(), None, NOW).unwrap();
+
+        assert_eq!(failure.requested_wait, None);
+    }
+}
@@ -0,0 +1,447 @@
+use std::collections::HashSet;
+use std::path::Path;
+
+use git2::build::{CheckoutBuilder, TreeUpdateBuilder};
+use git2::{Commit, Cred, CredentialType, FetchOptions, IndexEntry, IndexTime, Oid, RemoteCallbacks, Repository};
+use time::OffsetDateTime;
+use time::format_description::well_known::Rfc3339;
+
+use crate::backend::{CreatedCommit, changed_paths};
+use crate::config::Config;
+use crate::create_a_tree_prep::{GitCommitAction, delta_to_actions};
+use crate::error::{Error, Result, parse_oid};
+use crate::git_status::PathStatus;
+use crate::github::GitHubClient;
+use crate::github::rest_api::{create_a_commit, get_a_commit_object};
+use crate::log::{print_intent_plain, print_success_plain};
+
+/// The most commits to rebuild locally before fetching instead, which only
+/// matters when replaying many commits
+const MAX_RECONSTRUCTED_COMMITS: usize = 100;
+
+fn git_signature(signature: &create_a_commit::Signature) -> Result<git2::Signature<'static>> {
+    let date = signature.date.as_deref()
+        .ok_or_else(|| Error::internal(format!("Commit signature for {} <{}> has no date", signature.name, signature.email)))?;
+
+    let date = OffsetDateTime::parse(date, &Rfc3339)
+        .map_err(|e| Error::internal(format!("Unable to parse commit date {:?}: {}", date, e)))?;
+
+    let time = git2::Time::new(date.unix_timestamp(), date.offset().whole_minutes() as i32);
+
+    git2::Signature::new(&signature.name, &signature.email, &time)
+        .map_err(|e| Error::git(format!("create a signature for {} <{}>", signature.name, signature.email), e))
+}
+
+/// Writes the commit object GitHub describes, returning its object ID, which
+/// only matches GitHub's if the object is byte-for-byte identical
+///
+/// - Signed commits come with the exact payload that was signed, so they're
+///   rebuilt exactly
+/// - Unsigned commits are rebuilt from their fields, which can differ from
+///   the original, such as when GitHub reports a date in another time zone
+fn write_commit_object(repo: &Repository, commit: &get_a_commit_object::ResponseBody) -> Result<Oid> {
+    if let (Some(payload), Some(signature)) = (&commit.verification.payload, &commit.verification.signature) {
+        return repo.commit_signed(payload, signature, None)
+            .map_err(|e| Error::git(format!("write signed commit {}", commit.sha), e))
+    }
+
+    let tree = repo.find_tree(parse_oid(&commit.tree.sha)?)
+        .map_err(|e| Error::git(format!("find tree {}", commit.tree.sha), e))?;
+
+    let mut parents = Vec::with_capacity(commit.parents.len());
+
+    for parent in &commit.parents {
+        let parent_commit = repo.find_commit(parse_oid(&parent.sha)?)
+            .map_err(|e| Error::git(format!("find commit {}", parent.sha), e))?;
+
+        parents.push(parent_commit);
+    }
+
+    let parent_refs: Vec<&Commit> = parents.iter().collect();
+
+    let buffer = repo.commit_create_buffer(&git_signature(&commit.author)?, &git_signature(&commit.committer)?, &commit.message, &tree, &parent_refs)
+        .map_err(|e| Error::git(format!("build commit {}", commit.sha), e))?;
+
+    let odb = repo.odb()
+        .map_err(|e| Error::git("open the object database", e))?;
+
+    odb.write(git2::ObjectType::Commit, &buffer)
+        .map_err(|e| Error::git(format!("write commit {}", commit.sha), e))
+}
+
+/// Rebuilds a remote commit and any of its missing ancestors locally from
+/// GitHub's description of them, without fetching
+///
+/// Returns `false` if a commit can't be rebuilt, such as when its tree or
+/// its parents only exist on the remote, or when the rebuilt object doesn't
+/// match.
+///
+/// - `staged_status` is checked against the commit's tree before the commit
+///   is written, so a commit that doesn't match is never added locally
+fn reconstruct_commit(repo: &Repository, github_client: &GitHubClient, sha: &str, remaining_commits: usize, staged_status: Option<&[PathStatus]>) -> Result<bool> {
+    let oid = parse_oid(sha)?;
+
+    if repo.find_commit(oid).is_ok() {
+        return Ok(true)
+    }
+
+    if remaining_commits == 0 {
+        return Ok(false)
+    }
+
+    let commit = github_client.get_a_commit_object(sha)?;
+
+    for parent in &commit.parents {
+        if !reconstruct_commit(repo, github_client, &parent.sha, remaining_commits - 1, None)? {
+            return Ok(false)
+        }
+    }
+
+    let tree_id = parse_oid(&commit.tree.sha)?;
+
+    // - Building the expected tree also writes it, so the check is what makes
+    //   the tree of staged changes available locally
+    if let (Some(status), Some(parent)) = (staged_status, commit.parents.first()) {
+        verify_tree(repo, oid, parse_oid(&parent.sha)?, tree_id, status)?;
+    }
+
+    if repo.find_tree(tree_id).is_err() {
+        return Ok(false)
+    }
+
+    let written_oid = write_commit_object(repo, &commit)?;
+
+    if written_oid != oid {
+        print_intent_plain(&format!("Rebuilt commit {} doesn't match remote commit {}", written_oid, oid));
+        return Ok(false)
+    }
+
+    Ok(true)
+}
+
+/// Fetches the branch from `origin`, authenticating HTTPS remotes with the
+/// GitHub token and SSH remotes with the SSH agent
+fn fetch_branch(repo: &Repository, github_client: &GitHubClient, branch_name: &str) -> Result<()> {
+    let mut remote = repo.find_remote("origin")
+        .map_err(|e| Error::git("find remote \"origin\"", e))?;
+
+    let token = github_client.get_access_token(false)?;
+
+    let mut callbacks = RemoteCallbacks::new();
+
+    callbacks.credentials(move |_url, username_from_url, allowed_types| {
+        if allowed_types.contains(CredentialType::SSH_KEY) {
+            Cred::ssh_key_from_agent(username_from_url.unwrap_or("git"))
+        } else if allowed_types.contains(CredentialType::USER_PASS_PLAINTEXT) {
+            Cred::userpass_plaintext("x-access-token", &token)
+        } else {
+            Cred::default()
+        }
+    });
+
+    let mut fetch_options = FetchOptions::new();
+    fetch_options.remote_callbacks(callbacks);
+
+    let refspec = format!("+refs/heads/{name}:refs/remotes/origin/{name}", name = branch_name);
+
+    print_intent_plain(&format!("Fetching branch {:?} from origin", branch_name));
+
+    remote.fetch(&[&refspec], Some(&mut fetch_options), None)
+        .map_err(|e| Error::git(format!("fetch branch {:?} from origin", branch_name), e))
+}
+
+/// Builds the tree that committing `status` on top of `parent` should have
+/// produced
+fn expected_tree(repo: &Repository, parent: &Commit, status: &[PathStatus]) -> Result<Oid> {
+    let parent_tree = parent.tree()
+        .map_err(|e| Error::git(format!("find the tree of commit {}", parent.id()), e))?;
+
+    let mut builder = TreeUpdateBuilder::new();
+
+    for path_status in status {
+        for action in delta_to_actions(path_status.delta) {
+            match action {
+                GitCommitAction::AddPath => {
+                    builder.upsert(&path_status.path, path_status.object_id, path_status.file_mode);
+                },
+                GitCommitAction::DeletePath => {
+                    builder.remove(&path_status.path);
+                },
+                GitCommitAction::DeleteOriginalPath => {
+                    if let Some(original_path) = &path_status.original_path {
+                        builder.remove(original_path);
+                    }
+                },
+                GitCommitAction::Nop => {},
+                GitCommitAction::Unsupported => {
+                    Err(Error::unsupported_path(&path_status.path, format!("Unsupported delta {:?}", path_status.delta)))?
+                },
+            }
+        }
+    }
+
+    builder.create_updated(repo, &parent_tree)
+        .map_err(|e| Error::git(format!("build the expected tree on top of commit {}", parent.id()), e))
+}
+
+/// Checks that the remote commit changed exactly what was staged
+fn verify_tree(repo: &Repository, commit_id: Oid, parent_id: Oid, tree_id: Oid, status: &[PathStatus]) -> Result<()> {
+    let parent = repo.find_commit(parent_id)
+        .map_err(|e| Error::git(format!("find the parent of commit {}", commit_id), e))?;
+
+    let expected_tree_id = expected_tree(repo, &parent, status)?;
+
+    if expected_tree_id != tree_id {
+        Err(Error::internal(format!("Remote commit {} has tree {}, but the staged changes on top of {} make tree {}; the local repository was left as is", commit_id, tree_id, parent_id, expected_tree_id)))?
+    }
+
+    Ok(())
+}
+
+/// Whether the local branch can be moved to `new_tip` without losing commits
+/// that only exist locally
+fn can_move_local_branch(config: &Config, old_tip: Option<Oid>, new_tip: Oid) -> Result<bool> {
+    let old_tip = match old_tip {
+        Some(old_tip) => old_tip,
+        None => return Ok(true),
+    };
+
+    // - Replayed commits are recreated remotely with new IDs, so the local
+    //   originals are expected to be replaced
+    let was_replayed = config.git_should_replay_commits && old_tip.to_string() == config.git_head_object_id;
+
+    if old_tip == new_tip || was_replayed || config.git_should_force_push {
+        return Ok(true)
+    }
+
+    config.git_repo.graph_descendant_of(new_tip, old_tip)
+        .map_err(|e| Error::git(format!("check whether {} descends from {}", new_tip, old_tip), e))
+}
+
+/// Moves a checked-out branch to `commit`, bringing the index and working
+/// directory up to date with it
+///
+/// - Only the paths that differ between HEAD and `commit` are touched, so
+///   anything staged but not committed stays staged
+/// - `committed_paths` are the ones the commit was made from, which the
+///   working directory already has, so only their index entries are updated
+///   and unstaged changes to them are kept
+/// - Other paths changed remotely, such as when the staged changes were
+///   rebased onto a newer remote tip, are checked out, which fails rather than
+///   overwrite local modifications
+fn update_checked_out_branch(repo: &Repository, reference_name: &str, commit: &Commit, committed_paths: &HashSet<&str>, log_message: &str) -> Result<()> {
+    let head_tree = repo.head()
+        .and_then(|head| head.peel_to_tree())
+        .map_err(|e| Error::git("find the tree of HEAD", e))?;
+    let commit_tree = commit.tree()
+        .map_err(|e| Error::git(format!("find the tree of commit {}", commit.id()), e))?;
+
+    let diff = repo.diff_tree_to_tree(Some(&head_tree), Some(&commit_tree), None)
+        .map_err(|e| Error::git("compare HEAD with the remote commit", e))?;
+
+    let mut changed_paths: Vec<&Path> = diff.deltas()
+        .flat_map(|delta| [delta.old_file().path(), delta.new_file().path()])
+        .flatten()
+        .collect();
+
+    changed_paths.sort();
+    changed_paths.dedup();
+
+    let remote_paths: Vec<&Path> = changed_paths.iter()
+        .copied()
+        .filter(|path| !path.to_str().is_some_and(|path| committed_paths.contains(path)))
+        .collect();
+
+    if !remote_paths.is_empty() {
+        let mut checkout = CheckoutBuilder::new();
+        checkout.safe();
+
+        for path in &remote_paths {
+            checkout.path(path);
+        }
+
+        repo.checkout_tree(commit.as_object(), Some(&mut checkout))
+            .map_err(|e| Error::git(format!("check out the paths that changed remotely in commit {}", commit.id()), e))?;
+    }
+
+    repo.reference(reference_name, commit.id(), true, log_message)
+        .map_err(|e| Error::git(format!("update {}", reference_name), e))?;
+
+    let mut index = repo.index()
+        .map_err(|e| Error::git("open the index", e))?;
+
+    index.read(false)
+        .map_err(|e| Error::git("read the index", e))?;
+
+    for path in changed_paths {
+        let tree_entry = commit_tree.get_path(path).ok();
+        let index_entry = index.get_path(path, 0);
+
+        let display_path = path.to_string_lossy();
+
+        match (tree_entry, index_entry) {
+            (Some(tree_entry), Some(index_entry)) if index_entry.id == tree_entry.id() && index_entry.mode == tree_entry.filemode() as u32 => {},
+            (Some(tree_entry), _) => {
+                let entry = IndexEntry {
+                    ctime: IndexTime::new(0, 0),
+                    mtime: IndexTime::new(0, 0),
+                    dev: 0,
+                    ino: 0,
+                    mode: tree_entry.filemode() as u32,
+                    uid: 0,
+                    gid: 0,
+                    file_size: 0,
+                    id: tree_entry.id(),
+                    flags: 0,
+                    flags_extended: 0,
+                    path: display_path.as_bytes().to_vec(),
+                };
+
+                index.add(&entry)
+                    .map_err(|e| Error::git_path("update the index entry", &display_path, e))?;
+            },
+            (None, Some(_)) => {
+                index.remove_path(path)
+                    .map_err(|e| Error::git_path("remove the index entry", &display_path, e))?;
+            },
+            (None, None) => {},
+        }
+    }
+
+    index.write()
+        .map_err(|e| Error::git("write the index", e))
+}
+
+fn move_local_branch(config: &Config, commit: &Commit, committed_paths: &HashSet<&str>) -> Result<()> {
+    let repo = &config.git_repo;
+    let branch_name = &config.git_branch_name;
+    let local_reference_name = format!("refs/heads/{}", branch_name);
+    let remote_reference_name = format!("refs/remotes/origin/{}", branch_name);
+    let log_message = format!("ghommit: sync with remote commit {}", commit.id());
+
+    let old_tip = repo.refname_to_id(&local_reference_name).ok();
+
+    if !can_move_local_branch(config, old_tip, commit.id())? {
+        Err(Error::conflict(format!("Local branch {:?} has commits that remote commit {} doesn't; not moving it", branch_name, commit.id())))?
+    }
+
+    repo.reference(&remote_reference_name, commit.id(), true, &log_message)
+        .map_err(|e| Error::git(format!("update {}", remote_reference_name), e))?;
+
+    let is_checked_out = repo.head().ok()
+        .is_some_and(|head| head.is_branch() && head.name() == Some(local_reference_name.as_str()));
+
+    match is_checked_out {
+        true => update_checked_out_branch(repo, &local_reference_name, commit, committed_paths, &log_message),
+        false => repo.reference(&local_reference_name, commit.id(), true, &log_message)
+            .map(|_| ())
+            .map_err(|e| Error::git(format!("update {}", local_reference_name), e)),
+    }
+}
+
+/// Makes the local repository match the remote after committing: the remote
+/// commit is rebuilt or fetched, the local and remote-tracking branches are
+/// moved to it, and the index is updated if the branch is checked out
+///
+/// - `staged_status` is checked against the remote commit's tree when the
+///   staged changes were committed, and is `None` when replaying commits
+pub fn sync_local(config: &Config, github_client: &GitHubClient, commit: &CreatedCommit, staged_status: Option<&[PathStatus]>) -> Result<()> {
+    let repo = &config.git_repo;
+    let oid = parse_oid(&commit.sha)?;
+
+    print_intent_plain(&format!("Syncing the local repository with remote commit {}", commit.sha));
+
+    if !reconstruct_commit(repo, github_client, &commit.sha, MAX_RECONSTRUCTED_COMMITS, staged_status)? {
+        fetch_branch(repo, github_client, &config.git_branch_name)?;
+    }
+
+    let local_commit = repo.find_commit(oid)
+        .map_err(|e| Error::git(format!("find commit {} after fetching branch {:?}", oid, config.git_branch_name), e))?;
+
+    // - Rebuilt commits were checked before they were written, but fetched
+    //   ones and ones that were already present weren't
+    if let Some(status) = staged_status {
+        let parent_id = local_commit.parent_id(0)
+            .map_err(|e| Error::git(format!("find the parent of commit {}", oid), e))?;
+
+        verify_tree(repo, oid, parent_id, local_commit.tree_id(), status)?;
+    }
+
+    let committed_paths = changed_paths(staged_status.unwrap_or_default());
+
+    move_local_branch(config, &local_commit, &committed_paths)?;
+
+    print_success_plain(&format!("Local branch {:?} is at {}", config.git_branch_name, commit.sha));
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod sync_local_tests {
+    use std::collections::HashSet;
+
+    use crate::git_status::git_status;
+    use crate::github::rest_api::create_a_commit::{Signature, Tree};
+    use crate::github::rest_api::get_a_commit_object::{Parent, ResponseBody, Verification};
+    use crate::test_utils::test_utils::TempGitRepo;
+
+    use super::{expected_tree, update_checked_out_branch, write_commit_object};
+
+    fn signature() -> Signature {
+        Signature {
+            name: "ghommit".to_string(),
+            email: "ghommit@example.com".to_string(),
+            date: Some("2014-11-07T22:01:45Z".to_string()),
+        }
+    }
+
+    #[test]
+    fn staged_changes_are_rebuilt_into_an_identical_commit() {
+        let repo = TempGitRepo::new();
+
+        let foo = repo.create_or_replace_blob_file("foo", "foo\n".as_bytes());
+        repo.git_add(&foo);
+
+        let status = git_status(&repo.repo).unwrap();
+        let head = repo.repo.head().unwrap().peel_to_commit().unwrap();
+
+        let tree_id = expected_tree(&repo.repo, &head, &status).unwrap();
+        assert_eq!(tree_id, repo.repo.index().unwrap().write_tree().unwrap());
+
+        // - Build what GitHub would have committed, then rebuild it from
+        //   GitHub's description of it
+        let tree = repo.repo.find_tree(tree_id).unwrap();
+        let git_signature = super::git_signature(&signature()).unwrap();
+        let buffer = repo.repo.commit_create_buffer(&git_signature, &git_signature, "Add foo\n", &tree, &[&head]).unwrap();
+        let remote_oid = git2::Oid::hash_object(git2::ObjectType::Commit, &buffer).unwrap();
+
+        let remote_commit = ResponseBody {
+            sha: remote_oid.to_string(),
+            tree: Tree {
+                sha: tree_id.to_string(),
+            },
+            parents: vec![Parent {
+                sha: head.id().to_string(),
+            }],
+            author: signature(),
+            committer: signature(),
+            message: "Add foo\n".to_string(),
+            verification: Verification {
+                verified: false,
+                signature: None,
+                payload: None,
+            },
+        };
+
+        let written_oid = write_commit_object(&repo.repo, &remote_commit).unwrap();
+        assert_eq!(written_oid, remote_oid);
+
+        let commit = repo.repo.find_commit(written_oid).unwrap();
+        let reference_name = repo.repo.head().unwrap().name().unwrap().to_owned();
+        let committed_paths = HashSet::from(["foo"]);
+        update_checked_out_branch(&repo.repo, &reference_name, &commit, &committed_paths, "Sync").unwrap();
+
+        assert_eq!(repo.repo.head().unwrap().target(), Some(written_oid));
+        assert!(git_status(&repo.repo).unwrap().is_empty());
+    }
+}
@@ -1,15 +1,19 @@
 #[cfg(test)]
+#[allow(clippy::expect_fun_call, clippy::module_inception, clippy::needless_borrow, clippy::needless_borrows_for_generic_args, clippy::redundant_pattern_matching)]
 pub mod test_utils {
     use std::fs::{self, File};
     use std::io::Write;
     use std::os::unix;
     use std::path::{PathBuf, Path};
+    use std::sync::Arc;
 
-    use git2::{Repository, Oid, Signature};
+    use git2::{FileMode, IndexEntry, IndexTime, Repository, Oid, Signature};
     use once_cell::sync::Lazy;
     use sha1::{Digest, Sha1};
     use tempfile::{TempDir, tempdir};
 
+    use crate::github::{Authentication, GitHubClient, GitHubEndpoints, GitHubRepo};
+
     pub static DELETED_FILE_OID: Lazy<Oid> = Lazy::new(|| {
         oid_from_str("0000000000000000000000000000000000000000")
     });
@@ -40,6 +44,16 @@ pub mod test_utils {
         oid_from_str(&hash_string)
     }
 
+    /// A client for tests that don't expect to make any requests
+    pub fn github_client() -> GitHubClient {
+        let github_repo = GitHubRepo {
+            owner: "gemini-oss".to_string(),
+            name: "ghommit".to_string(),
+        };
+
+        GitHubClient::new(Authentication::Token(Arc::new("ghp_example".to_string())), GitHubEndpoints::default(), github_repo)
+    }
+
     pub struct TempGitRepo<'a> {
         pub directory: TempDir,
         pub repo: Repository,
@@ -124,6 +138,35 @@ pub mod test_utils {
                 .expect(&format!("Unable to add path {:?} to index on disk", path));
         }
 
+        /// Stages a submodule at `path` pointing to `commit_id`, which only
+        /// needs to exist in the submodule's repository, like `git add` on a
+        /// checked out submodule would
+        pub fn git_add_submodule(&self, path: &str, commit_id: Oid) {
+            let mut index = self.repo.index()
+                .unwrap_or_else(|e| panic!("Unable to access index of repo in {:?}: {}", self.directory, e));
+
+            let entry = IndexEntry {
+                ctime: IndexTime::new(0, 0),
+                mtime: IndexTime::new(0, 0),
+                dev: 0,
+                ino: 0,
+                mode: u32::from(FileMode::Commit),
+                uid: 0,
+                gid: 0,
+                file_size: 0,
+                id: commit_id,
+                flags: path.len().min(0xfff) as u16,
+                flags_extended: 0,
+                path: path.as_bytes().to_vec(),
+            };
+
+            index.add(&entry)
+                .unwrap_or_else(|e| panic!("Unable to add submodule {:?} to index in memory: {}", path, e));
+
+            index.write()
+                .unwrap_or_else(|e| panic!("Unable to add submodule {:?} to index on disk: {}", path, e));
+        }
+
         pub fn git_commit(&self, message: &str) -> Oid {
             let mut index = self.repo.index()
                 .expect(&format!("Unable to access index of repo in {:?}", self.directory));
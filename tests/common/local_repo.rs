@@ -0,0 +1,174 @@
+//! A local repository whose `origin` remote points to the mock's repository
+//! on github.com
+
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use git2::{IndexAddOption, IndexEntry, IndexTime, Oid, Repository, RepositoryInitOptions, Signature};
+use tempfile::TempDir;
+
+use super::mock_github::{NAME, OWNER};
+
+pub struct LocalRepo {
+    pub directory: TempDir,
+    pub repo: Repository,
+}
+
+impl LocalRepo {
+    /// Creates a repository on `main` with an initial commit containing
+    /// `README.md`
+    pub fn new() -> LocalRepo {
+        let directory = tempfile::tempdir()
+            .expect("Failed to create a temporary directory");
+
+        let mut options = RepositoryInitOptions::new();
+        options.initial_head("main");
+
+        let repo = Repository::init_opts(directory.path(), &options)
+            .unwrap_or_else(|e| panic!("Failed to initialize a git repository in {:?}: {}", directory.path(), e));
+
+        {
+            let mut config = repo.config()
+                .unwrap_or_else(|e| panic!("Unable to open the config of the repo in {:?}: {}", directory.path(), e));
+
+            config.set_str("user.name", "ghommit")
+                .expect("Unable to set user.name");
+            config.set_str("user.email", "ghommit@example.com")
+                .expect("Unable to set user.email");
+        }
+
+        repo.remote("origin", &format!("https://github.com/{}/{}.git", OWNER, NAME))
+            .expect("Unable to add the origin remote");
+
+        let local_repo = LocalRepo {
+            directory: directory,
+            repo: repo,
+        };
+
+        local_repo.write_file("README.md", b"# ghommit\n");
+        local_repo.stage_all();
+        local_repo.commit("Initial commit");
+
+        local_repo
+    }
+
+    pub fn path(&self) -> &Path {
+        self.directory.path()
+    }
+
+    pub fn head(&self) -> Oid {
+        self.repo.head()
+            .and_then(|head| head.peel_to_commit())
+            .map(|commit| commit.id())
+            .unwrap_or_else(|e| panic!("Unable to resolve HEAD of the repo in {:?}: {}", self.path(), e))
+    }
+
+    pub fn write_file(&self, path: &str, contents: &[u8]) -> PathBuf {
+        let absolute_path = self.path().join(path);
+
+        if let Some(parent) = absolute_path.parent() {
+            fs::create_dir_all(parent)
+                .unwrap_or_else(|e| panic!("Unable to create directory {:?}: {}", parent, e));
+        }
+
+        fs::write(&absolute_path, contents)
+            .unwrap_or_else(|e| panic!("Unable to write to file {:?}: {}", absolute_path, e));
+
+        absolute_path
+    }
+
+    pub fn remove_file(&self, path: &str) {
+        let absolute_path = self.path().join(path);
+
+        fs::remove_file(&absolute_path)
+            .unwrap_or_else(|e| panic!("Unable to remove file {:?}: {}", absolute_path, e));
+    }
+
+    /// `git add --all`
+    pub fn stage_all(&self) {
+        let mut index = self.repo.index()
+            .unwrap_or_else(|e| panic!("Unable to access index of repo in {:?}: {}", self.path(), e));
+
+        index.add_all(["*"], IndexAddOption::DEFAULT, None)
+            .expect("Unable to add paths to the index in memory");
+        index.update_all(["*"], None)
+            .expect("Unable to update paths in the index in memory");
+        index.write()
+            .expect("Unable to write the index to disk");
+    }
+
+    /// Stages a submodule at `path` pointing to `commit_id`, which only needs
+    /// to exist in the submodule's repository
+    pub fn stage_submodule(&self, path: &str, commit_id: Oid) {
+        let mut index = self.repo.index()
+            .unwrap_or_else(|e| panic!("Unable to access index of repo in {:?}: {}", self.path(), e));
+
+        let entry = IndexEntry {
+            ctime: IndexTime::new(0, 0),
+            mtime: IndexTime::new(0, 0),
+            dev: 0,
+            ino: 0,
+            mode: u32::from(git2::FileMode::Commit),
+            uid: 0,
+            gid: 0,
+            file_size: 0,
+            id: commit_id,
+            flags: path.len().min(0xfff) as u16,
+            flags_extended: 0,
+            path: path.as_bytes().to_vec(),
+        };
+
+        index.add(&entry)
+            .unwrap_or_else(|e| panic!("Unable to add submodule {:?} to the index in memory: {}", path, e));
+        index.write()
+            .expect("Unable to write the index to disk");
+    }
+
+    /// Commits the index on top of HEAD and moves HEAD to the commit
+    pub fn commit(&self, message: &str) -> Oid {
+        let mut index = self.repo.index()
+            .unwrap_or_else(|e| panic!("Unable to access index of repo in {:?}: {}", self.path(), e));
+
+        let tree_id = index.write_tree()
+            .unwrap_or_else(|e| panic!("Unable to write tree of repo in {:?}: {}", self.path(), e));
+        let tree = self.repo.find_tree(tree_id)
+            .unwrap_or_else(|e| panic!("Unable to find tree with ID {}: {}", tree_id, e));
+
+        let signature = self.repo.signature()
+            .expect("Unable to create a signature from the repo's config");
+
+        let parent = self.repo.head().ok()
+            .and_then(|head| head.peel_to_commit().ok());
+        let parents: Vec<&git2::Commit> = parent.iter().collect();
+
+        self.repo.commit(Some("HEAD"), &signature, &signature, message, &tree, &parents)
+            .unwrap_or_else(|e| panic!("Unable to commit in repo at {:?}: {}", self.path(), e))
+    }
+
+    /// Creates a commit on top of `parent_id` that writes `contents` to
+    /// `path`, without touching HEAD, the index, or the working directory
+    ///
+    /// - Pushing it to the mock simulates someone else moving the branch
+    pub fn commit_elsewhere(&self, parent_id: Oid, path: &str, contents: &[u8], message: &str) -> Oid {
+        let parent = self.repo.find_commit(parent_id)
+            .unwrap_or_else(|e| panic!("Unable to find commit {}: {}", parent_id, e));
+        let parent_tree = parent.tree()
+            .unwrap_or_else(|e| panic!("Unable to find the tree of commit {}: {}", parent_id, e));
+
+        let blob_id = self.repo.blob(contents)
+            .expect("Unable to write a blob");
+
+        let tree_id = git2::build::TreeUpdateBuilder::new()
+            .upsert(path, blob_id, git2::FileMode::Blob)
+            .create_updated(&self.repo, &parent_tree)
+            .unwrap_or_else(|e| panic!("Unable to write {:?} on top of the tree of {}: {}", path, parent_id, e));
+        let tree = self.repo.find_tree(tree_id)
+            .unwrap_or_else(|e| panic!("Unable to find tree with ID {}: {}", tree_id, e));
+
+        let signature = Signature::now("someone else", "someone-else@example.com")
+            .expect("Unable to create a signature");
+
+        self.repo.commit(None, &signature, &signature, message, &tree, &[&parent])
+            .unwrap_or_else(|e| panic!("Unable to commit in repo at {:?}: {}", self.path(), e))
+    }
+}
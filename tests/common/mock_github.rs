@@ -0,0 +1,1085 @@
+//! In-process fake of the parts of GitHub's REST API that ghommit uses, backed
+//! by a bare git2 repository so that blobs, trees, and commits get the same
+//! SHAs GitHub would give them
+//!
+//! - Every response closes its connection, so the server handles one request
+//!   per connection, each on its own thread
+//! - Requests are handled one at a time since they share the repository
+//! - Git LFS's batch API is served under the repository's URL like GitHub
+//!   serves it, and points uploads to storage under `/lfs` on the mock
+
+use std::collections::HashMap;
+use std::io::{BufRead, BufReader, Read, Write};
+use std::net::{SocketAddr, TcpListener, TcpStream};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Arc, Mutex, MutexGuard};
+use std::thread;
+
+use base64::Engine;
+use ghommit::github::{Authentication, GitHubClient, GitHubEndpoints, GitHubRepo};
+use git2::{FileMode, Oid, Repository, Signature};
+use jsonwebtoken::{Algorithm, DecodingKey, EncodingKey, Validation};
+use once_cell::sync::Lazy;
+use serde::Deserialize;
+use serde_json::{Value, json};
+use tempfile::TempDir;
+use time::{OffsetDateTime, UtcOffset};
+use time::format_description::well_known::Rfc3339;
+
+use super::local_repo::LocalRepo;
+
+pub const OWNER: &str = "gemini-oss";
+pub const NAME: &str = "ghommit";
+pub const APP_ID: u64 = 1234;
+pub const INSTALLATION_ID: u64 = 5678;
+/// The token accepted as-is, like a personal access token
+pub const TOKEN: &str = "ghp_mock";
+
+/// GitHub includes at most this many files when comparing two commits
+const MAX_COMPARE_FILES: usize = 300;
+
+/// What the LFS batch API's actions say to authenticate to the mock's LFS
+/// storage with, which differs from the GitHub token like GitHub's does
+const LFS_STORAGE_AUTHORIZATION: &str = "RemoteAuth lfs_mock";
+
+/// The GitHub App's private and public keys, as PEM
+///
+/// - Generated once per test binary since generating RSA keys is slow
+static APP_KEY_PAIR: Lazy<(Vec<u8>, Vec<u8>)> = Lazy::new(|| {
+    let rsa = openssl::rsa::Rsa::generate(2048)
+        .expect("Unable to generate an RSA key");
+
+    let private_key_pem = rsa.private_key_to_pem()
+        .expect("Unable to encode the RSA private key as PEM");
+    let public_key_pem = rsa.public_key_to_pem_pkcs1()
+        .expect("Unable to encode the RSA public key as PEM");
+
+    (private_key_pem, public_key_pem)
+});
+
+/// The GitHub App's private key, as PKCS#1 PEM
+pub fn app_private_key_pem() -> &'static [u8] {
+    &APP_KEY_PAIR.0
+}
+
+/// A mock with `main` pushed from a fresh local repository
+pub fn mock_with_main() -> (MockGitHub, LocalRepo) {
+    let mock = MockGitHub::start();
+    let local_repo = LocalRepo::new();
+
+    mock.push(&local_repo.repo, local_repo.head(), "main");
+
+    (mock, local_repo)
+}
+
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct RecordedRequest {
+    pub method: String,
+    /// The path without the query string
+    pub path: String,
+}
+
+struct Request {
+    method: String,
+    path: String,
+    authorization: Option<String>,
+    /// `Value::Null` when the body is empty or isn't JSON
+    body: Value,
+    raw_body: Vec<u8>,
+}
+
+/// A status code and JSON body
+type Reply = (u16, Value);
+
+struct MockPullRequest {
+    head: String,
+    base: String,
+    title: String,
+}
+
+struct State {
+    base_url: String,
+    repo: Repository,
+    issued_tokens: Vec<String>,
+    requests: Vec<RecordedRequest>,
+    /// LFS objects by their SHA-256
+    lfs_objects: HashMap<String, Vec<u8>>,
+    /// The message Update a reference fails with instead of moving the
+    /// branch, if any
+    reference_update_failure: Option<String>,
+    /// Open pull requests, numbered from 1 in the order they were opened
+    pull_requests: Vec<MockPullRequest>,
+    /// A path and contents Create a tree adds to every tree it builds, if
+    /// any
+    extra_tree_entry: Option<(String, String)>,
+}
+
+pub struct MockGitHub {
+    address: SocketAddr,
+    state: Arc<Mutex<State>>,
+    shutting_down: Arc<AtomicBool>,
+    // - Kept so that the repository outlives the server
+    _directory: TempDir,
+}
+
+impl MockGitHub {
+    pub fn start() -> MockGitHub {
+        let directory = tempfile::tempdir()
+            .expect("Failed to create a temporary directory");
+        let repo = Repository::init_bare(directory.path())
+            .unwrap_or_else(|e| panic!("Failed to initialize a bare git repository in {:?}: {}", directory.path(), e));
+
+        let listener = TcpListener::bind("127.0.0.1:0")
+            .expect("Unable to listen on a local port");
+        let address = listener.local_addr()
+            .expect("Unable to get the local address being listened on");
+
+        let state = Arc::new(Mutex::new(State {
+            base_url: format!("http://{}", address),
+            repo: repo,
+            issued_tokens: Vec::new(),
+            requests: Vec::new(),
+            lfs_objects: HashMap::new(),
+            reference_update_failure: None,
+            pull_requests: Vec::new(),
+            extra_tree_entry: None,
+        }));
+        let shutting_down = Arc::new(AtomicBool::new(false));
+
+        {
+            let state = Arc::clone(&state);
+            let shutting_down = Arc::clone(&shutting_down);
+
+            thread::spawn(move || {
+                for stream in listener.incoming() {
+                    if shutting_down.load(Ordering::SeqCst) {
+                        break
+                    }
+
+                    if let Ok(stream) = stream {
+                        let state = Arc::clone(&state);
+
+                        thread::spawn(move || serve_connection(stream, &state));
+                    }
+                }
+            });
+        }
+
+        MockGitHub {
+            address: address,
+            state: state,
+            shutting_down: shutting_down,
+            _directory: directory,
+        }
+    }
+
+    fn lock(&self) -> MutexGuard<'_, State> {
+        self.state.lock()
+            .expect("Mock GitHub state mutex poisoned")
+    }
+
+    /// The URL to use as `GHOMMIT_GITHUB_API_URL`
+    pub fn base_url(&self) -> String {
+        format!("http://{}", self.address)
+    }
+
+    pub fn endpoints(&self) -> GitHubEndpoints {
+        GitHubEndpoints::for_rest_api_base_url(&self.base_url())
+    }
+
+    pub fn token_authentication(&self) -> Authentication {
+        Authentication::Token(Arc::new(TOKEN.to_string()))
+    }
+
+    pub fn app_authentication(&self, installation_id: Option<u64>) -> Authentication {
+        let private_key = EncodingKey::from_rsa_pem(app_private_key_pem())
+            .expect("Unable to read the mock GitHub App's private key");
+
+        Authentication::GitHubApp {
+            app_id: APP_ID,
+            installation_id: installation_id,
+            private_key: private_key,
+        }
+    }
+
+    pub fn github_client(&self, authentication: Authentication) -> GitHubClient {
+        let github_repo = GitHubRepo {
+            owner: OWNER.to_string(),
+            name: NAME.to_string(),
+        };
+
+        GitHubClient::new(authentication, self.endpoints(), github_repo)
+    }
+
+    /// Copies every object in `local_repo` and points `branch_name` at
+    /// `commit_id`, like a force push would
+    pub fn push(&self, local_repo: &Repository, commit_id: Oid, branch_name: &str) {
+        let state = self.lock();
+
+        let local_odb = local_repo.odb()
+            .unwrap_or_else(|e| panic!("Unable to open the object database of {:?}: {}", local_repo.path(), e));
+        let remote_odb = state.repo.odb()
+            .expect("Unable to open the mock's object database");
+
+        local_odb.foreach(|object_id| {
+            let object = local_odb.read(*object_id)
+                .unwrap_or_else(|e| panic!("Unable to read object {} from {:?}: {}", object_id, local_repo.path(), e));
+
+            remote_odb.write(object.kind(), object.data())
+                .unwrap_or_else(|e| panic!("Unable to write object {} to the mock: {}", object_id, e));
+
+            true
+        }).unwrap_or_else(|e| panic!("Unable to copy the objects of {:?}: {}", local_repo.path(), e));
+
+        state.repo.reference(&format!("refs/heads/{}", branch_name), commit_id, true, "push")
+            .unwrap_or_else(|e| panic!("Unable to point branch {:?} at {}: {}", branch_name, commit_id, e));
+    }
+
+    pub fn branch_tip(&self, branch_name: &str) -> Option<Oid> {
+        self.lock().repo.find_reference(&format!("refs/heads/{}", branch_name))
+            .ok()
+            .and_then(|reference| reference.target())
+    }
+
+    /// The contents of the blob at `path` in `commit_id`'s tree
+    pub fn file_content(&self, commit_id: Oid, path: &str) -> Option<Vec<u8>> {
+        self.with_repo(|repo| {
+            let tree = repo.find_commit(commit_id).ok()?.tree().ok()?;
+            let entry = tree.get_path(std::path::Path::new(path)).ok()?;
+            let blob = repo.find_blob(entry.id()).ok()?;
+
+            Some(blob.content().to_vec())
+        })
+    }
+
+    pub fn with_repo<R>(&self, f: impl FnOnce(&Repository) -> R) -> R {
+        f(&self.lock().repo)
+    }
+
+    /// The contents of the LFS object with SHA-256 `oid`, if it was uploaded
+    pub fn lfs_object(&self, oid: &str) -> Option<Vec<u8>> {
+        self.lock().lfs_objects.get(oid).cloned()
+    }
+
+    /// Stores an LFS object as if it had been uploaded before, returning its
+    /// SHA-256
+    pub fn store_lfs_object(&self, contents: &[u8]) -> String {
+        let oid = sha256_hex(contents);
+
+        self.lock().lfs_objects.insert(oid.clone(), contents.to_vec());
+
+        oid
+    }
+
+    /// Makes every Update a reference request fail with a 422 and `message`,
+    /// like GitHub does for refusals that have nothing to do with the branch
+    /// moving
+    pub fn fail_reference_updates(&self, message: &str) {
+        self.lock().reference_update_failure = Some(message.to_string());
+    }
+
+    /// Makes every Create a tree request also add `path` with `content`, so
+    /// that the trees built don't match the ones requested
+    pub fn add_to_created_trees(&self, path: &str, content: &str) {
+        self.lock().extra_tree_entry = Some((path.to_string(), content.to_string()));
+    }
+
+    pub fn requests(&self) -> Vec<RecordedRequest> {
+        self.lock().requests.clone()
+    }
+
+    /// The number of requests made with `method` to a path ending in
+    /// `path_suffix`
+    pub fn request_count(&self, method: &str, path_suffix: &str) -> usize {
+        self.lock().requests.iter()
+            .filter(|request| request.method == method && request.path.ends_with(path_suffix))
+            .count()
+    }
+}
+
+impl Drop for MockGitHub {
+    fn drop(&mut self) {
+        self.shutting_down.store(true, Ordering::SeqCst);
+
+        // - Wake the accept loop up so that it sees it's shutting down
+        let _ = TcpStream::connect(self.address);
+    }
+}
+
+fn serve_connection(stream: TcpStream, state: &Mutex<State>) {
+    let Some(request) = read_request(&stream) else {
+        return
+    };
+
+    let (status, body) = match state.lock() {
+        Ok(mut state) => {
+            state.requests.push(RecordedRequest {
+                method: request.method.clone(),
+                path: request.path.clone(),
+            });
+
+            route(&mut state, &request)
+        },
+        Err(_) => internal_error("Mock GitHub state mutex poisoned"),
+    };
+
+    let _ = write_response(stream, status, &body);
+}
+
+fn read_request(stream: &TcpStream) -> Option<Request> {
+    let mut reader = BufReader::new(stream);
+
+    let mut request_line = String::new();
+    reader.read_line(&mut request_line).ok()?;
+
+    let mut parts = request_line.split_whitespace();
+    let method = parts.next()?.to_string();
+    let target = parts.next()?;
+    let path = target.split_once('?').map_or(target, |(path, _)| path).to_string();
+
+    let mut headers = HashMap::new();
+
+    loop {
+        let mut line = String::new();
+        reader.read_line(&mut line).ok()?;
+
+        let line = line.trim_end();
+
+        if line.is_empty() {
+            break
+        }
+
+        if let Some((name, value)) = line.split_once(':') {
+            headers.insert(name.trim().to_ascii_lowercase(), value.trim().to_string());
+        }
+    }
+
+    let content_length = headers.get("content-length")
+        .and_then(|value| value.parse::<usize>().ok())
+        .unwrap_or(0);
+
+    let mut body = vec![0; content_length];
+    reader.read_exact(&mut body).ok()?;
+
+    Some(Request {
+        method: method,
+        path: path,
+        authorization: headers.remove("authorization"),
+        body: serde_json::from_slice(&body).unwrap_or(Value::Null),
+        raw_body: body,
+    })
+}
+
+fn write_response(mut stream: TcpStream, status: u16, body: &Value) -> std::io::Result<()> {
+    let reason = match status {
+        200 => "OK",
+        201 => "Created",
+        204 => "No Content",
+        401 => "Unauthorized",
+        404 => "Not Found",
+        422 => "Unprocessable Entity",
+        _ => "Internal Server Error",
+    };
+
+    let body = match status {
+        204 => String::new(),
+        _ => body.to_string(),
+    };
+
+    write!(
+        stream,
+        "HTTP/1.1 {} {}\r\nContent-Type: application/json; charset=utf-8\r\nContent-Length: {}\r\nConnection: close\r\n\r\n{}",
+        status, reason, body.len(), body,
+    )?;
+
+    stream.flush()
+}
+
+fn message(status: u16, message: impl Into<String>) -> Reply {
+    (status, json!({ "message": message.into() }))
+}
+
+fn not_found() -> Reply {
+    message(404, "Not Found")
+}
+
+fn unprocessable(text: impl Into<String>) -> Reply {
+    message(422, text)
+}
+
+fn internal_error(text: impl Into<String>) -> Reply {
+    message(500, text)
+}
+
+fn route(state: &mut State, request: &Request) -> Reply {
+    let segments: Vec<&str> = request.path.trim_start_matches('/').split('/').collect();
+
+    let result = match (request.method.as_str(), segments.as_slice()) {
+        ("POST", ["app", "installations", installation_id, "access_tokens"]) => {
+            verify_jwt(request).and_then(|_| create_an_installation_access_token(state, installation_id))
+        },
+        ("POST", [owner, name, "info", "lfs", "objects", "batch"]) if *owner == OWNER && *name == format!("{}.git", NAME) => {
+            verify_basic_access_token(state, request).and_then(|_| lfs_batch(state, &request.body))
+        },
+        ("PUT", ["lfs", "objects", oid]) => {
+            verify_lfs_storage_authorization(request).and_then(|_| lfs_upload(state, oid, &request.raw_body))
+        },
+        ("POST", ["lfs", "verify"]) => {
+            verify_lfs_storage_authorization(request).and_then(|_| lfs_verify(state, &request.body))
+        },
+        (method, ["repos", owner, name, rest @ ..]) if *owner == OWNER && *name == NAME => {
+            match (method, rest) {
+                ("GET", ["installation"]) => {
+                    verify_jwt(request).map(|_| (200, json!({ "id": INSTALLATION_ID, "app_id": APP_ID })))
+                },
+                _ => verify_access_token(state, request).and_then(|_| route_repo(state, method, rest, &request.body)),
+            }
+        },
+        _ => Err(not_found()),
+    };
+
+    result.unwrap_or_else(|reply| reply)
+}
+
+fn route_repo(state: &mut State, method: &str, segments: &[&str], body: &Value) -> Result<Reply, Reply> {
+    match (method, segments) {
+        ("POST", ["git", "blobs"]) => create_a_blob(state, body),
+        ("POST", ["git", "trees"]) => create_a_tree(state, body),
+        ("POST", ["git", "commits"]) => create_a_commit(state, body),
+        ("GET", ["git", "commits", sha]) => get_a_commit_object(state, sha),
+        ("POST", ["git", "refs"]) => create_a_reference(state, body),
+        ("GET", ["git", "refs", name @ ..]) => get_a_reference(state, &name.join("/")),
+        ("PATCH", ["git", "refs", name @ ..]) => update_a_reference(state, &name.join("/"), body),
+        ("DELETE", ["git", "refs", name @ ..]) => delete_a_reference(state, &name.join("/")),
+        ("GET", ["compare", range]) => compare_two_commits(state, range),
+        ("GET", []) => Ok((200, json!({ "default_branch": "main" }))),
+        ("GET", ["pulls"]) => list_pull_requests(state),
+        ("POST", ["pulls"]) => create_a_pull_request(state, body),
+        ("POST", ["pulls", number, "requested_reviewers"]) => request_reviewers_for_a_pull_request(state, number),
+        _ => Err(not_found()),
+    }
+}
+
+#[derive(Deserialize)]
+struct Claims {
+    iss: String,
+}
+
+fn bearer_token(request: &Request) -> Result<&str, Reply> {
+    request.authorization.as_deref()
+        .and_then(|value| value.strip_prefix("Bearer "))
+        .ok_or_else(|| message(401, "Requires authentication"))
+}
+
+fn verify_jwt(request: &Request) -> Result<(), Reply> {
+    let jwt = bearer_token(request)?;
+
+    let decoding_key = DecodingKey::from_rsa_pem(&APP_KEY_PAIR.1)
+        .map_err(|e| internal_error(format!("Unable to read the mock GitHub App's public key: {}", e)))?;
+
+    let claims = jsonwebtoken::decode::<Claims>(jwt, &decoding_key, &Validation::new(Algorithm::RS256))
+        .map_err(|e| message(401, format!("A JSON web token could not be decoded: {}", e)))?
+        .claims;
+
+    match claims.iss == APP_ID.to_string() {
+        true => Ok(()),
+        false => Err(message(401, format!("'Issuer' claim ('iss') must be {}", APP_ID))),
+    }
+}
+
+fn verify_access_token(state: &State, request: &Request) -> Result<(), Reply> {
+    let token = bearer_token(request)?;
+
+    match is_valid_access_token(state, token) {
+        true => Ok(()),
+        false => Err(message(401, "Bad credentials")),
+    }
+}
+
+fn is_valid_access_token(state: &State, token: &str) -> bool {
+    token == TOKEN || state.issued_tokens.iter().any(|issued| issued == token)
+}
+
+/// Git LFS authenticates like git over HTTPS, with the token as the password
+fn verify_basic_access_token(state: &State, request: &Request) -> Result<(), Reply> {
+    let credentials = request.authorization.as_deref()
+        .and_then(|value| value.strip_prefix("Basic "))
+        .and_then(|encoded| base64::engine::general_purpose::STANDARD.decode(encoded).ok())
+        .and_then(|decoded| String::from_utf8(decoded).ok())
+        .ok_or_else(|| message(401, "Requires authentication"))?;
+
+    match credentials.split_once(':') {
+        Some((_, token)) if is_valid_access_token(state, token) => Ok(()),
+        _ => Err(message(401, "Bad credentials")),
+    }
+}
+
+fn verify_lfs_storage_authorization(request: &Request) -> Result<(), Reply> {
+    match request.authorization.as_deref() == Some(LFS_STORAGE_AUTHORIZATION) {
+        true => Ok(()),
+        false => Err(message(401, "Bad LFS storage credentials")),
+    }
+}
+
+fn string_field<'a>(value: &'a Value, name: &str) -> Result<&'a str, Reply> {
+    value.get(name)
+        .and_then(|field| field.as_str())
+        .ok_or_else(|| unprocessable(format!("Invalid request: {:?} is not a string", name)))
+}
+
+fn parse_oid(sha: &str) -> Result<Oid, Reply> {
+    Oid::from_str(sha)
+        .map_err(|_| unprocessable(format!("{:?} is not a valid SHA", sha)))
+}
+
+fn format_git_time(time: git2::Time) -> String {
+    let offset = UtcOffset::from_whole_seconds(time.offset_minutes() * 60)
+        .unwrap_or(UtcOffset::UTC);
+
+    OffsetDateTime::from_unix_timestamp(time.seconds())
+        .map(|date| date.to_offset(offset))
+        .ok()
+        .and_then(|date| date.format(&Rfc3339).ok())
+        .unwrap_or_default()
+}
+
+fn signature_json(signature: &Signature) -> Value {
+    json!({
+        "name": signature.name().unwrap_or_default(),
+        "email": signature.email().unwrap_or_default(),
+        "date": format_git_time(signature.when()),
+    })
+}
+
+/// Reads `author` or `committer` from a create a commit request, defaulting
+/// to the authenticated app like GitHub does
+fn parse_signature(value: Option<&Value>) -> Result<Signature<'static>, Reply> {
+    let Some(value) = value.filter(|value| !value.is_null()) else {
+        return Signature::now("ghommit-mock[bot]", "ghommit-mock[bot]@users.noreply.github.com")
+            .map_err(|e| internal_error(e.to_string()))
+    };
+
+    let name = string_field(value, "name")?;
+    let email = string_field(value, "email")?;
+
+    let signature = match value.get("date").and_then(|date| date.as_str()) {
+        Some(date) => {
+            let date = OffsetDateTime::parse(date, &Rfc3339)
+                .map_err(|e| unprocessable(format!("Invalid date {:?}: {}", date, e)))?;
+            let time = git2::Time::new(date.unix_timestamp(), date.offset().whole_minutes() as i32);
+
+            Signature::new(name, email, &time)
+        },
+        None => Signature::now(name, email),
+    };
+
+    signature.map_err(|e| unprocessable(e.to_string()))
+}
+
+fn commit_json(state: &State, commit_id: Oid) -> Result<Value, Reply> {
+    let commit = state.repo.find_commit(commit_id)
+        .map_err(|_| not_found())?;
+
+    let api_url = format!("{}/repos/{}/{}/git", state.base_url, OWNER, NAME);
+
+    let parents: Vec<Value> = commit.parent_ids()
+        .map(|parent_id| json!({
+            "sha": parent_id.to_string(),
+            "url": format!("{}/commits/{}", api_url, parent_id),
+            "html_url": format!("https://github.com/{}/{}/commit/{}", OWNER, NAME, parent_id),
+        }))
+        .collect();
+
+    Ok(json!({
+        "sha": commit_id.to_string(),
+        "node_id": format!("C_mock_{}", commit_id),
+        "url": format!("{}/commits/{}", api_url, commit_id),
+        "html_url": format!("https://github.com/{}/{}/commit/{}", OWNER, NAME, commit_id),
+        "author": signature_json(&commit.author()),
+        "committer": signature_json(&commit.committer()),
+        "message": commit.message().unwrap_or_default(),
+        "tree": {
+            "sha": commit.tree_id().to_string(),
+            "url": format!("{}/trees/{}", api_url, commit.tree_id()),
+        },
+        "parents": parents,
+        "verification": {
+            "verified": false,
+            "reason": "unsigned",
+            "signature": null,
+            "payload": null,
+        },
+    }))
+}
+
+fn reference_json(state: &State, full_name: &str, commit_id: Oid) -> Value {
+    let api_url = format!("{}/repos/{}/{}/git", state.base_url, OWNER, NAME);
+
+    json!({
+        "ref": full_name,
+        "node_id": format!("REF_mock_{}", full_name),
+        "url": format!("{}/{}", api_url, full_name),
+        "object": {
+            "type": "commit",
+            "sha": commit_id.to_string(),
+            "url": format!("{}/commits/{}", api_url, commit_id),
+        },
+    })
+}
+
+/// [Create an installation access token for an app](https://docs.github.com/en/rest/apps/apps?apiVersion=2022-11-28#create-an-installation-access-token-for-an-app)
+fn create_an_installation_access_token(state: &mut State, installation_id: &str) -> Result<Reply, Reply> {
+    if installation_id != INSTALLATION_ID.to_string() {
+        return Err(not_found())
+    }
+
+    let token = format!("ghs_mock_{}", state.issued_tokens.len() + 1);
+    let expires_at = (OffsetDateTime::now_utc() + time::Duration::hours(1)).format(&Rfc3339)
+        .map_err(|e| internal_error(e.to_string()))?;
+
+    state.issued_tokens.push(token.clone());
+
+    Ok((201, json!({ "token": token, "expires_at": expires_at })))
+}
+
+/// [Create a blob](https://docs.github.com/en/rest/git/blobs?apiVersion=2022-11-28#create-a-blob)
+fn create_a_blob(state: &mut State, body: &Value) -> Result<Reply, Reply> {
+    let content = string_field(body, "content")?;
+
+    let bytes = match body.get("encoding").and_then(|encoding| encoding.as_str()) {
+        Some("base64") => base64::engine::general_purpose::STANDARD.decode(content)
+            .map_err(|e| unprocessable(format!("Invalid base64 content: {}", e)))?,
+        Some("utf-8") | None => content.as_bytes().to_vec(),
+        Some(encoding) => Err(unprocessable(format!("Unsupported encoding {:?}", encoding)))?,
+    };
+
+    let blob_id = state.repo.blob(&bytes)
+        .map_err(|e| internal_error(e.to_string()))?;
+
+    Ok((201, json!({
+        "url": format!("{}/repos/{}/{}/git/blobs/{}", state.base_url, OWNER, NAME, blob_id),
+        "sha": blob_id.to_string(),
+    })))
+}
+
+/// [Create a tree](https://docs.github.com/en/rest/git/trees?apiVersion=2022-11-28#create-a-tree)
+///
+/// - Like GitHub, `base_tree` may be a commit, which stands for its tree
+fn create_a_tree(state: &mut State, body: &Value) -> Result<Reply, Reply> {
+    let repo = &state.repo;
+
+    let base_tree = match body.get("base_tree").and_then(|base_tree| base_tree.as_str()) {
+        Some(sha) => repo.find_object(parse_oid(sha)?, None)
+            .and_then(|object| object.peel_to_tree())
+            .map_err(|_| unprocessable(format!("Invalid base_tree: {}", sha)))?,
+        None => {
+            let empty_tree_id = repo.treebuilder(None)
+                .and_then(|builder| builder.write())
+                .map_err(|e| internal_error(e.to_string()))?;
+
+            repo.find_tree(empty_tree_id)
+                .map_err(|e| internal_error(e.to_string()))?
+        },
+    };
+
+    let nodes = body.get("tree")
+        .and_then(|tree| tree.as_array())
+        .ok_or_else(|| unprocessable("Invalid request: \"tree\" is not an array"))?;
+
+    let mut builder = git2::build::TreeUpdateBuilder::new();
+
+    for node in nodes {
+        let path = string_field(node, "path")?;
+        let mode = string_field(node, "mode")?;
+        let node_type = string_field(node, "type")?;
+
+        let file_mode = match mode {
+            "100644" => FileMode::Blob,
+            "100755" => FileMode::BlobExecutable,
+            "120000" => FileMode::Link,
+            "160000" => FileMode::Commit,
+            "040000" => FileMode::Tree,
+            _ => Err(unprocessable(format!("Invalid tree mode {:?} for {:?}", mode, path)))?,
+        };
+
+        match (node.get("content"), node.get("sha")) {
+            (Some(Value::String(content)), _) => {
+                let blob_id = repo.blob(content.as_bytes())
+                    .map_err(|e| internal_error(e.to_string()))?;
+
+                builder.upsert(path, blob_id, file_mode);
+            },
+            (_, Some(Value::Null)) => {
+                builder.remove(path);
+            },
+            (_, Some(Value::String(sha))) => {
+                let object_id = parse_oid(sha)?;
+
+                // - Submodules point to commits in other repositories, so only
+                //   blobs and trees have to exist
+                let exists = match node_type {
+                    "blob" => repo.find_blob(object_id).is_ok(),
+                    "tree" => repo.find_tree(object_id).is_ok(),
+                    "commit" => true,
+                    _ => Err(unprocessable(format!("Invalid tree type {:?} for {:?}", node_type, path)))?,
+                };
+
+                if !exists {
+                    Err(unprocessable(format!("GitRPC::BadObjectState: {} ({:?}) does not exist", sha, path)))?
+                }
+
+                builder.upsert(path, object_id, file_mode);
+            },
+            _ => Err(unprocessable(format!("Must supply either tree.sha or tree.content for {:?}", path)))?,
+        }
+    }
+
+    if let Some((path, content)) = &state.extra_tree_entry {
+        let blob_id = repo.blob(content.as_bytes())
+            .map_err(|e| internal_error(e.to_string()))?;
+
+        builder.upsert(path, blob_id, FileMode::Blob);
+    }
+
+    let tree_id = builder.create_updated(repo, &base_tree)
+        .map_err(|e| unprocessable(format!("GitRPC::BadObjectState: {}", e.message())))?;
+
+    Ok((201, json!({
+        "sha": tree_id.to_string(),
+        "url": format!("{}/repos/{}/{}/git/trees/{}", state.base_url, OWNER, NAME, tree_id),
+        "tree": [],
+        "truncated": false,
+    })))
+}
+
+/// [Create a commit](https://docs.github.com/en/rest/git/commits?apiVersion=2022-11-28#create-a-commit)
+fn create_a_commit(state: &mut State, body: &Value) -> Result<Reply, Reply> {
+    let repo = &state.repo;
+
+    let tree_sha = string_field(body, "tree")?;
+    let tree = repo.find_tree(parse_oid(tree_sha)?)
+        .map_err(|_| unprocessable(format!("Tree SHA does not exist: {}", tree_sha)))?;
+
+    let parent_shas = body.get("parents")
+        .and_then(|parents| parents.as_array())
+        .ok_or_else(|| unprocessable("Invalid request: \"parents\" is not an array"))?;
+
+    let mut parents = Vec::with_capacity(parent_shas.len());
+
+    for parent_sha in parent_shas {
+        let parent_sha = parent_sha.as_str()
+            .ok_or_else(|| unprocessable("Invalid request: parent is not a string"))?;
+
+        let parent = repo.find_commit(parse_oid(parent_sha)?)
+            .map_err(|_| unprocessable(format!("Parent SHA does not exist or is not a commit object: {}", parent_sha)))?;
+
+        parents.push(parent);
+    }
+
+    let message = string_field(body, "message")?;
+    let author = parse_signature(body.get("author"))?;
+    let committer = match body.get("committer").filter(|committer| !committer.is_null()) {
+        Some(committer) => parse_signature(Some(committer))?,
+        None => author.clone(),
+    };
+
+    let parent_refs: Vec<&git2::Commit> = parents.iter().collect();
+
+    let commit_id = repo.commit(None, &author, &committer, message, &tree, &parent_refs)
+        .map_err(|e| internal_error(e.to_string()))?;
+
+    Ok((201, commit_json(state, commit_id)?))
+}
+
+/// [Get a commit object](https://docs.github.com/en/rest/git/commits?apiVersion=2022-11-28#get-a-commit-object)
+fn get_a_commit_object(state: &mut State, sha: &str) -> Result<Reply, Reply> {
+    let commit_id = Oid::from_str(sha)
+        .map_err(|_| not_found())?;
+
+    Ok((200, commit_json(state, commit_id)?))
+}
+
+/// [Create a reference](https://docs.github.com/en/rest/git/refs?apiVersion=2022-11-28#create-a-reference)
+fn create_a_reference(state: &mut State, body: &Value) -> Result<Reply, Reply> {
+    let full_name = string_field(body, "ref")?;
+    let sha = string_field(body, "sha")?;
+
+    if !full_name.starts_with("refs/") || full_name.matches('/').count() < 2 {
+        Err(unprocessable(format!("{:?} is not a valid ref name", full_name)))?
+    }
+
+    if state.repo.find_reference(full_name).is_ok() {
+        Err(unprocessable("Reference already exists"))?
+    }
+
+    let commit_id = parse_oid(sha)?;
+
+    if state.repo.find_commit(commit_id).is_err() {
+        Err(unprocessable("Object does not exist"))?
+    }
+
+    state.repo.reference(full_name, commit_id, false, "create a reference")
+        .map_err(|e| unprocessable(e.message().to_string()))?;
+
+    Ok((201, reference_json(state, full_name, commit_id)))
+}
+
+/// [Delete a reference](https://docs.github.com/en/rest/git/refs?apiVersion=2022-11-28#delete-a-reference)
+fn delete_a_reference(state: &mut State, partial_name: &str) -> Result<Reply, Reply> {
+    let full_name = format!("refs/{}", partial_name);
+
+    let mut reference = state.repo.find_reference(&full_name)
+        .map_err(|_| unprocessable("Reference does not exist"))?;
+
+    reference.delete()
+        .map_err(|e| unprocessable(e.message().to_string()))?;
+
+    Ok((204, Value::Null))
+}
+
+/// [Get a reference](https://docs.github.com/en/rest/git/refs?apiVersion=2022-11-28#get-a-reference)
+fn get_a_reference(state: &mut State, partial_name: &str) -> Result<Reply, Reply> {
+    let full_name = format!("refs/{}", partial_name);
+
+    let commit_id = state.repo.find_reference(&full_name)
+        .ok()
+        .and_then(|reference| reference.target())
+        .ok_or_else(not_found)?;
+
+    Ok((200, reference_json(state, &full_name, commit_id)))
+}
+
+/// [Update a reference](https://docs.github.com/en/rest/git/refs?apiVersion=2022-11-28#update-a-reference)
+fn update_a_reference(state: &mut State, partial_name: &str, body: &Value) -> Result<Reply, Reply> {
+    let full_name = format!("refs/{}", partial_name);
+
+    let sha = string_field(body, "sha")?;
+    let force = body.get("force").and_then(|force| force.as_bool()).unwrap_or(false);
+
+    if let Some(message) = &state.reference_update_failure {
+        Err(unprocessable(message.clone()))?
+    }
+
+    let current_id = state.repo.find_reference(&full_name)
+        .ok()
+        .and_then(|reference| reference.target())
+        .ok_or_else(|| unprocessable("Reference does not exist"))?;
+
+    let commit_id = parse_oid(sha)?;
+
+    if state.repo.find_commit(commit_id).is_err() {
+        Err(unprocessable("Object does not exist"))?
+    }
+
+    let is_fast_forward = commit_id == current_id || state.repo.graph_descendant_of(commit_id, current_id).unwrap_or(false);
+
+    if !force && !is_fast_forward {
+        Err(unprocessable("Update is not a fast forward"))?
+    }
+
+    state.repo.reference(&full_name, commit_id, true, "update a reference")
+        .map_err(|e| unprocessable(e.message().to_string()))?;
+
+    Ok((200, reference_json(state, &full_name, commit_id)))
+}
+
+fn pull_request_json(state: &State, number: usize) -> Value {
+    let pull_request = &state.pull_requests[number - 1];
+
+    json!({
+        "number": number,
+        "html_url": format!("https://github.com/{}/{}/pull/{}", OWNER, NAME, number),
+        "node_id": format!("PR_mock_{}", number),
+        "title": pull_request.title,
+        "head": { "ref": pull_request.head },
+        "base": { "ref": pull_request.base },
+    })
+}
+
+/// [List pull requests](https://docs.github.com/en/rest/pulls/pulls?apiVersion=2022-11-28#list-pull-requests)
+///
+/// - Query parameters aren't read, so every pull request is listed, which is
+///   enough while tests open them for one branch
+fn list_pull_requests(state: &mut State) -> Result<Reply, Reply> {
+    let pull_requests: Vec<Value> = (1..=state.pull_requests.len())
+        .map(|number| pull_request_json(state, number))
+        .collect();
+
+    Ok((200, Value::Array(pull_requests)))
+}
+
+/// [Create a pull request](https://docs.github.com/en/rest/pulls/pulls?apiVersion=2022-11-28#create-a-pull-request)
+fn create_a_pull_request(state: &mut State, body: &Value) -> Result<Reply, Reply> {
+    let head = string_field(body, "head")?;
+    let base = string_field(body, "base")?;
+    let title = string_field(body, "title")?;
+
+    if state.repo.find_branch(head, git2::BranchType::Local).is_err() {
+        Err(unprocessable(format!("Validation Failed: head {:?} does not exist", head)))?
+    }
+
+    state.pull_requests.push(MockPullRequest {
+        head: head.to_string(),
+        base: base.to_string(),
+        title: title.to_string(),
+    });
+
+    Ok((201, pull_request_json(state, state.pull_requests.len())))
+}
+
+/// [Request reviewers for a pull request](https://docs.github.com/en/rest/pulls/review-requests?apiVersion=2022-11-28#request-reviewers-for-a-pull-request)
+///
+/// - The repository has no collaborators, so every request is refused the way
+///   GitHub refuses reviewers who aren't collaborators
+fn request_reviewers_for_a_pull_request(state: &mut State, number: &str) -> Result<Reply, Reply> {
+    let number = number.parse::<usize>()
+        .map_err(|_| not_found())?;
+
+    if number == 0 || number > state.pull_requests.len() {
+        Err(not_found())?
+    }
+
+    Err(unprocessable(format!("Reviews may only be requested from collaborators. One or more of the users or teams you specified is not a collaborator of the {}/{} repository.", OWNER, NAME)))
+}
+
+/// [Compare two commits](https://docs.github.com/en/rest/commits/commits?apiVersion=2022-11-28#compare-two-commits)
+///
+/// - Like GitHub, the files are the changes from the merge base to `head`
+fn compare_two_commits(state: &mut State, range: &str) -> Result<Reply, Reply> {
+    let repo = &state.repo;
+
+    let (base, head) = range.split_once("...")
+        .ok_or_else(not_found)?;
+
+    let resolve = |revision: &str| {
+        repo.revparse_single(revision)
+            .and_then(|object| object.peel_to_commit())
+            .map_err(|_| not_found())
+    };
+
+    let base = resolve(base)?;
+    let head = resolve(head)?;
+
+    let (ahead_by, behind_by) = repo.graph_ahead_behind(head.id(), base.id())
+        .map_err(|e| internal_error(e.to_string()))?;
+
+    let status = match (ahead_by, behind_by) {
+        (0, 0) => "identical",
+        (_, 0) => "ahead",
+        (0, _) => "behind",
+        _ => "diverged",
+    };
+
+    let merge_base = repo.merge_base(base.id(), head.id())
+        .and_then(|merge_base_id| repo.find_commit(merge_base_id))
+        .map_err(|e| internal_error(e.to_string()))?;
+
+    let old_tree = merge_base.tree().map_err(|e| internal_error(e.to_string()))?;
+    let new_tree = head.tree().map_err(|e| internal_error(e.to_string()))?;
+
+    let mut diff = repo.diff_tree_to_tree(Some(&old_tree), Some(&new_tree), None)
+        .map_err(|e| internal_error(e.to_string()))?;
+    diff.find_similar(None)
+        .map_err(|e| internal_error(e.to_string()))?;
+
+    let files: Vec<Value> = diff.deltas()
+        .take(MAX_COMPARE_FILES)
+        .map(|delta| {
+            let path_of = |file: git2::DiffFile| file.path().map(|path| path.to_string_lossy().into_owned());
+            let old_path = path_of(delta.old_file());
+            let new_path = path_of(delta.new_file());
+
+            let (status, filename, previous_filename) = match delta.status() {
+                git2::Delta::Added => ("added", new_path, None),
+                git2::Delta::Deleted => ("removed", old_path, None),
+                git2::Delta::Renamed => ("renamed", new_path, old_path),
+                _ => ("modified", new_path, None),
+            };
+
+            match previous_filename {
+                Some(previous_filename) => json!({ "filename": filename, "status": status, "previous_filename": previous_filename }),
+                None => json!({ "filename": filename, "status": status }),
+            }
+        })
+        .collect();
+
+    Ok((200, json!({
+        "status": status,
+        "ahead_by": ahead_by,
+        "behind_by": behind_by,
+        "total_commits": ahead_by,
+        "files": files,
+    })))
+}
+
+fn sha256_hex(contents: &[u8]) -> String {
+    openssl::sha::sha256(contents).iter()
+        .map(|byte| format!("{:02x}", byte))
+        .collect()
+}
+
+fn lfs_object_spec(value: &Value) -> Result<(String, u64), Reply> {
+    let oid = string_field(value, "oid")?;
+    let size = value.get("size")
+        .and_then(|size| size.as_u64())
+        .ok_or_else(|| unprocessable("Invalid request: \"size\" is not a number"))?;
+
+    Ok((oid.to_string(), size))
+}
+
+/// Asks for objects the mock doesn't have to be uploaded to and verified with
+/// the mock's LFS storage, like GitHub's LFS API does with its own
+fn lfs_batch(state: &mut State, body: &Value) -> Result<Reply, Reply> {
+    if string_field(body, "operation")? != "upload" {
+        return Err(unprocessable("Only uploads are supported by the mock"))
+    }
+
+    let objects = body.get("objects")
+        .and_then(|objects| objects.as_array())
+        .ok_or_else(|| unprocessable("Invalid request: \"objects\" is not an array"))?;
+
+    let mut response_objects = Vec::new();
+
+    for object in objects {
+        let (oid, size) = lfs_object_spec(object)?;
+
+        let response_object = match state.lfs_objects.contains_key(&oid) {
+            true => json!({ "oid": oid, "size": size }),
+            false => {
+                let header = json!({ "Authorization": LFS_STORAGE_AUTHORIZATION });
+
+                json!({
+                    "oid": oid,
+                    "size": size,
+                    "actions": {
+                        "upload": { "href": format!("{}/lfs/objects/{}", state.base_url, oid), "header": header },
+                        "verify": { "href": format!("{}/lfs/verify", state.base_url), "header": header },
+                    },
+                })
+            },
+        };
+
+        response_objects.push(response_object);
+    }
+
+    Ok((200, json!({ "transfer": "basic", "objects": response_objects })))
+}
+
+fn lfs_upload(state: &mut State, oid: &str, contents: &[u8]) -> Result<Reply, Reply> {
+    if sha256_hex(contents) != oid {
+        return Err(unprocessable(format!("Uploaded contents don't match LFS object {}", oid)))
+    }
+
+    state.lfs_objects.insert(oid.to_string(), contents.to_vec());
+
+    Ok((200, json!({})))
+}
+
+fn lfs_verify(state: &mut State, body: &Value) -> Result<Reply, Reply> {
+    let (oid, size) = lfs_object_spec(body)?;
+
+    match state.lfs_objects.get(&oid) {
+        Some(contents) if contents.len() as u64 == size => Ok((200, json!({}))),
+        _ => Err(not_found()),
+    }
+}
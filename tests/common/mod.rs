@@ -0,0 +1,9 @@
+//! Helpers shared by the integration tests
+//!
+//! - Each integration test crate only uses some of these, so unused ones are
+//!   allowed
+
+#![allow(dead_code)]
+
+pub mod local_repo;
+pub mod mock_github;
@@ -0,0 +1,664 @@
+//! Runs the `ghommit` binary against the mock GitHub API, end to end
+
+#![allow(clippy::redundant_field_names)]
+
+mod common;
+
+use std::process::{Command, Output};
+
+use serde_json::Value;
+
+use common::local_repo::LocalRepo;
+use common::mock_github::{APP_ID, MockGitHub, TOKEN, app_private_key_pem, mock_with_main};
+
+/// Environment variables that would change how ghommit behaves if they leaked
+/// in from the environment the tests run in
+const INHERITED_ENVIRONMENT_VARIABLES: [&str; 12] = [
+    "GHOMMIT_GITHUB_APP_ID",
+    "GHOMMIT_GITHUB_APP_INSTALLATION_ID",
+    "GHOMMIT_GITHUB_APP_PRIVATE_KEY_PATH",
+    "GHOMMIT_GITHUB_APP_PRIVATE_KEY_PEM_DATA",
+    "GHOMMIT_GITHUB_HOST",
+    "GHOMMIT_GITHUB_TOKEN",
+    "GITHUB_ACTIONS",
+    "GITHUB_OUTPUT",
+    "GITHUB_STEP_SUMMARY",
+    "GITHUB_TOKEN",
+    "HTTPS_PROXY",
+    "ALL_PROXY",
+];
+
+fn ghommit(mock: &MockGitHub, local_repo: &LocalRepo, environment: &[(&str, &str)], args: &[&str]) -> Output {
+    let mut command = Command::new(env!("CARGO_BIN_EXE_ghommit"));
+
+    for name in INHERITED_ENVIRONMENT_VARIABLES {
+        command.env_remove(name);
+    }
+
+    command
+        .current_dir(local_repo.path())
+        .env("GHOMMIT_GITHUB_API_URL", mock.base_url())
+        .env("NO_PROXY", "127.0.0.1")
+        .envs(environment.iter().copied())
+        .args(args)
+        .output()
+        .expect("Unable to run ghommit")
+}
+
+fn ghommit_with_token(mock: &MockGitHub, local_repo: &LocalRepo, args: &[&str]) -> Output {
+    ghommit(mock, local_repo, &[("GHOMMIT_GITHUB_TOKEN", TOKEN)], args)
+}
+
+/// The JSON ghommit prints as the last line of stdout
+fn json_output(output: &Output) -> Value {
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    let last_line = stdout.lines().last()
+        .unwrap_or_else(|| panic!("ghommit printed nothing to stdout; stderr: {}", String::from_utf8_lossy(&output.stderr)));
+
+    serde_json::from_str(last_line)
+        .unwrap_or_else(|e| panic!("Unable to parse ghommit's output as JSON: {:?}: {}", last_line, e))
+}
+
+fn assert_success(output: &Output) {
+    assert!(output.status.success(), "ghommit failed with {}; stderr: {}", output.status, String::from_utf8_lossy(&output.stderr));
+}
+
+#[test]
+fn commits_staged_changes() {
+    let (mock, local_repo) = mock_with_main();
+    let base = local_repo.head();
+
+    local_repo.write_file("foo", b"foo\n");
+    local_repo.write_file("bar.bin", &[0x00, 0x80, 0xff]);
+    local_repo.stage_all();
+    // - Unstaged changes aren't committed
+    local_repo.write_file("foo", b"unstaged\n");
+
+    let output = ghommit_with_token(&mock, &local_repo, &["-m", "Add foo and bar"]);
+    assert_success(&output);
+
+    let json = json_output(&output);
+    let commit_id = mock.branch_tip("main").unwrap();
+
+    assert_eq!(json["commit_sha"], commit_id.to_string());
+    assert_eq!(json["branch"], "main");
+    assert_eq!(json["ref_action"], "updated");
+
+    assert_eq!(mock.file_content(commit_id, "foo").unwrap(), b"foo\n");
+    assert_eq!(mock.file_content(commit_id, "bar.bin").unwrap(), [0x00, 0x80, 0xff]);
+
+    mock.with_repo(|repo| {
+        let commit = repo.find_commit(commit_id).unwrap();
+
+        // - A message given with a single -m is used verbatim
+        assert_eq!(commit.message(), Some("Add foo and bar"));
+        assert_eq!(commit.parent_ids().collect::<Vec<_>>(), vec![base]);
+    });
+}
+
+#[test]
+fn creates_a_new_branch() {
+    let (mock, local_repo) = mock_with_main();
+
+    local_repo.write_file("foo", b"foo\n");
+    local_repo.stage_all();
+
+    let output = ghommit_with_token(&mock, &local_repo, &["-m", "Add foo", "--branch", "feature"]);
+    assert_success(&output);
+
+    let json = json_output(&output);
+
+    assert_eq!(json["ref_action"], "created");
+    assert_eq!(json["commit_sha"], mock.branch_tip("feature").unwrap().to_string());
+    assert_eq!(mock.branch_tip("main"), Some(local_repo.head()));
+}
+
+#[test]
+fn commits_on_top_of_an_existing_branch() {
+    let (mock, local_repo) = mock_with_main();
+
+    local_repo.write_file("deps.lock", b"v1\n");
+    local_repo.stage_all();
+
+    let output = ghommit_with_token(&mock, &local_repo, &["-m", "Update deps", "--branch", "bot/update-deps"]);
+    assert_success(&output);
+    let first_commit_id = mock.branch_tip("bot/update-deps").unwrap();
+
+    // - The next run starts from main again and changes another path
+    local_repo.remove_file("deps.lock");
+    local_repo.write_file("tools.lock", b"v1\n");
+    local_repo.stage_all();
+
+    let output = ghommit_with_token(&mock, &local_repo, &["-m", "Update tools", "--branch", "bot/update-deps"]);
+    assert_success(&output);
+
+    let json = json_output(&output);
+    let commit_id = mock.branch_tip("bot/update-deps").unwrap();
+
+    assert_eq!(json["ref_action"], "updated");
+    assert_eq!(json["commit_sha"], commit_id.to_string());
+    assert_eq!(mock.file_content(commit_id, "deps.lock").unwrap(), b"v1\n");
+    assert_eq!(mock.file_content(commit_id, "tools.lock").unwrap(), b"v1\n");
+    assert_eq!(mock.branch_tip("main"), Some(local_repo.head()));
+
+    mock.with_repo(|repo| {
+        assert_eq!(repo.find_commit(commit_id).unwrap().parent_ids().collect::<Vec<_>>(), vec![first_commit_id]);
+    });
+}
+
+#[test]
+fn existing_branch_changes_to_the_staged_paths_are_a_conflict() {
+    let (mock, local_repo) = mock_with_main();
+
+    local_repo.write_file("deps.lock", b"v1\n");
+    local_repo.stage_all();
+
+    let output = ghommit_with_token(&mock, &local_repo, &["-m", "Update deps", "--branch", "bot/update-deps"]);
+    assert_success(&output);
+    let first_commit_id = mock.branch_tip("bot/update-deps").unwrap();
+
+    // - The remote branch already changed `deps.lock` since main
+    local_repo.write_file("deps.lock", b"v2\n");
+    local_repo.stage_all();
+
+    let output = ghommit_with_token(&mock, &local_repo, &["-m", "Update deps again", "--branch", "bot/update-deps"]);
+
+    assert_eq!(output.status.code(), Some(10));
+    assert_eq!(json_output(&output)["error"]["kind"], "conflict");
+    assert_eq!(mock.branch_tip("bot/update-deps"), Some(first_commit_id));
+}
+
+#[test]
+fn graphql_failures_delete_the_branch_they_created() {
+    let (mock, local_repo) = mock_with_main();
+
+    local_repo.write_file("foo", b"foo\n");
+    local_repo.stage_all();
+
+    // - The mock doesn't serve the GraphQL API, so createCommitOnBranch fails
+    //   once the branch has been created for it
+    let output = ghommit_with_token(&mock, &local_repo, &["-m", "Add foo", "--branch", "feature", "--backend", "graphql"]);
+
+    assert!(!output.status.success());
+    assert_eq!(mock.request_count("POST", "/git/refs"), 1);
+    assert_eq!(mock.branch_tip("feature"), None);
+}
+
+#[test]
+fn deletes_and_renames_files() {
+    let (mock, local_repo) = mock_with_main();
+
+    local_repo.write_file("old", b"contents that are long enough to be detected as a rename\n");
+    local_repo.write_file("deleted", b"deleted\n");
+    local_repo.stage_all();
+    local_repo.commit("Add files");
+    mock.push(&local_repo.repo, local_repo.head(), "main");
+
+    local_repo.remove_file("old");
+    local_repo.remove_file("deleted");
+    local_repo.write_file("new", b"contents that are long enough to be detected as a rename\n");
+    local_repo.stage_all();
+
+    let output = ghommit_with_token(&mock, &local_repo, &["-m", "Rename and delete"]);
+    assert_success(&output);
+
+    let commit_id = mock.branch_tip("main").unwrap();
+
+    assert!(mock.file_content(commit_id, "old").is_none());
+    assert!(mock.file_content(commit_id, "deleted").is_none());
+    assert!(mock.file_content(commit_id, "new").is_some());
+
+    let expected_tree_id = local_repo.repo.index().unwrap().write_tree().unwrap();
+    assert_eq!(json_output(&output)["tree_sha"], expected_tree_id.to_string());
+}
+
+#[test]
+fn commits_the_index_on_top_of_an_older_base() {
+    let (mock, local_repo) = mock_with_main();
+
+    local_repo.write_file("foo", b"foo\n");
+    local_repo.stage_all();
+    let base = local_repo.commit("Add foo");
+    mock.push(&local_repo.repo, base, "main");
+
+    // - Only in HEAD, not in the base
+    local_repo.write_file("bar", b"bar\n");
+    local_repo.write_file("qux", b"qux\n");
+    local_repo.stage_all();
+    local_repo.commit("Add bar and qux");
+
+    local_repo.write_file("bar", b"bar\nbar\n");
+    local_repo.write_file("baz", b"baz\n");
+    local_repo.remove_file("foo");
+    local_repo.stage_all();
+
+    let output = ghommit_with_token(&mock, &local_repo, &["-m", "Update files", "--base", &base.to_string()]);
+    assert_success(&output);
+
+    let commit_id = mock.branch_tip("main").unwrap();
+
+    assert!(mock.file_content(commit_id, "foo").is_none());
+    assert_eq!(mock.file_content(commit_id, "bar").unwrap(), b"bar\nbar\n");
+    assert_eq!(mock.file_content(commit_id, "baz").unwrap(), b"baz\n");
+    assert_eq!(mock.file_content(commit_id, "qux").unwrap(), b"qux\n");
+
+    mock.with_repo(|repo| {
+        assert_eq!(repo.find_commit(commit_id).unwrap().parent_ids().collect::<Vec<_>>(), vec![base]);
+    });
+
+    let expected_tree_id = local_repo.repo.index().unwrap().write_tree().unwrap();
+    assert_eq!(json_output(&output)["tree_sha"], expected_tree_id.to_string());
+}
+
+#[test]
+fn references_blobs_the_remote_already_has() {
+    let (mock, local_repo) = mock_with_main();
+
+    local_repo.write_file("image.bin", &[0x00, 0x80, 0xff]);
+    local_repo.stage_all();
+    local_repo.commit("Add image");
+    mock.push(&local_repo.repo, local_repo.head(), "main");
+
+    local_repo.write_file("copy.bin", &[0x00, 0x80, 0xff]);
+    local_repo.stage_all();
+
+    let output = ghommit_with_token(&mock, &local_repo, &["-m", "Copy image"]);
+    assert_success(&output);
+
+    let commit_id = mock.branch_tip("main").unwrap();
+
+    assert_eq!(mock.file_content(commit_id, "copy.bin").unwrap(), [0x00, 0x80, 0xff]);
+    assert_eq!(mock.request_count("POST", "/git/blobs"), 0);
+}
+
+#[test]
+fn uploads_blobs_only_unpushed_commits_have() {
+    let (mock, local_repo) = mock_with_main();
+
+    mock.push(&local_repo.repo, local_repo.head(), "feature");
+
+    // - HEAD is never pushed, so when following the remote branch, its blobs
+    //   aren't on the remote
+    local_repo.write_file("image.bin", &[0x00, 0x80, 0xff]);
+    local_repo.stage_all();
+    local_repo.commit("Add image");
+
+    local_repo.write_file("copy.bin", &[0x00, 0x80, 0xff]);
+    local_repo.stage_all();
+
+    let output = ghommit_with_token(&mock, &local_repo, &["-m", "Copy image", "--branch", "feature"]);
+    assert_success(&output);
+
+    let commit_id = mock.branch_tip("feature").unwrap();
+
+    assert_eq!(mock.file_content(commit_id, "copy.bin").unwrap(), [0x00, 0x80, 0xff]);
+    assert_eq!(mock.request_count("POST", "/git/blobs"), 1);
+}
+
+#[test]
+fn creates_large_trees_in_several_requests() {
+    let (mock, local_repo) = mock_with_main();
+
+    // - 1 MiB each, the most that's sent inline, for 5 MiB in total
+    for name in ["a", "b", "c", "d", "e"] {
+        local_repo.write_file(name, name.repeat(1024 * 1024).as_bytes());
+    }
+
+    // - Over 1 MiB, so uploaded as a blob instead
+    local_repo.write_file("large", "large\n".repeat(200 * 1024).as_bytes());
+    local_repo.stage_all();
+
+    let output = ghommit_with_token(&mock, &local_repo, &["-m", "Add large files"]);
+    assert_success(&output);
+
+    assert_eq!(mock.request_count("POST", "/git/blobs"), 1);
+    assert_eq!(mock.request_count("POST", "/git/trees"), 2);
+
+    let expected_tree_id = local_repo.repo.index().unwrap().write_tree().unwrap();
+    assert_eq!(json_output(&output)["tree_sha"], expected_tree_id.to_string());
+}
+
+#[test]
+fn updates_submodules() {
+    let (mock, local_repo) = mock_with_main();
+
+    let old_commit_id = git2::Oid::from_str("1111111111111111111111111111111111111111").unwrap();
+    let new_commit_id = git2::Oid::from_str("2222222222222222222222222222222222222222").unwrap();
+
+    local_repo.write_file(".gitmodules", b"[submodule \"sub\"]\n\tpath = sub\n\turl = https://github.com/gemini-oss/sub.git\n");
+    local_repo.stage_all();
+    local_repo.stage_submodule("sub", old_commit_id);
+
+    let output = ghommit_with_token(&mock, &local_repo, &["-m", "Add sub"]);
+    assert_success(&output);
+
+    local_repo.commit("Add sub");
+    mock.push(&local_repo.repo, local_repo.head(), "main");
+
+    local_repo.stage_submodule("sub", new_commit_id);
+
+    let output = ghommit_with_token(&mock, &local_repo, &["-m", "Bump sub"]);
+    assert_success(&output);
+
+    let commit_id = mock.branch_tip("main").unwrap();
+
+    mock.with_repo(|repo| {
+        let tree = repo.find_commit(commit_id).unwrap().tree().unwrap();
+        let entry = tree.get_name("sub").unwrap();
+
+        assert_eq!(entry.filemode(), i32::from(git2::FileMode::Commit));
+        assert_eq!(entry.id(), new_commit_id);
+    });
+
+    let expected_tree_id = local_repo.repo.index().unwrap().write_tree().unwrap();
+    assert_eq!(json_output(&output)["tree_sha"], expected_tree_id.to_string());
+}
+
+const LFS_GITATTRIBUTES: &[u8] = b"*.bin filter=lfs diff=lfs merge=lfs -text\n";
+
+/// Writes an LFS pointer to `contents` at `path`, like `git add` with Git LFS
+/// installed would stage it, and stores the object in `.git/lfs/objects` if
+/// `store_locally`, returning the object's SHA-256
+fn write_lfs_file(local_repo: &LocalRepo, path: &str, contents: &[u8], store_locally: bool) -> String {
+    let oid: String = openssl::sha::sha256(contents).iter()
+        .map(|byte| format!("{:02x}", byte))
+        .collect();
+
+    let pointer = format!("version https://git-lfs.github.com/spec/v1\noid sha256:{}\nsize {}\n", oid, contents.len());
+    local_repo.write_file(path, pointer.as_bytes());
+
+    if store_locally {
+        local_repo.write_file(&format!(".git/lfs/objects/{}/{}/{}", &oid[0..2], &oid[2..4], oid), contents);
+    }
+
+    oid
+}
+
+#[test]
+fn uploads_lfs_objects() {
+    let (mock, local_repo) = mock_with_main();
+
+    local_repo.write_file(".gitattributes", LFS_GITATTRIBUTES);
+    let oid = write_lfs_file(&local_repo, "image.bin", &[0x00, 0x80, 0xff], true);
+    local_repo.stage_all();
+
+    let output = ghommit_with_token(&mock, &local_repo, &["-m", "Add image"]);
+    assert_success(&output);
+
+    let commit_id = mock.branch_tip("main").unwrap();
+    let pointer = mock.file_content(commit_id, "image.bin").unwrap();
+
+    assert!(String::from_utf8(pointer).unwrap().contains(&oid));
+    assert_eq!(mock.lfs_object(&oid).unwrap(), [0x00, 0x80, 0xff]);
+    assert_eq!(mock.request_count("POST", "/lfs/verify"), 1);
+}
+
+#[test]
+fn skips_lfs_objects_github_already_has() {
+    let (mock, local_repo) = mock_with_main();
+
+    let stored_oid = mock.store_lfs_object(&[0x00, 0x80, 0xff]);
+
+    local_repo.write_file(".gitattributes", LFS_GITATTRIBUTES);
+    // - Not stored locally, which is fine since it doesn't need uploading
+    let oid = write_lfs_file(&local_repo, "image.bin", &[0x00, 0x80, 0xff], false);
+    local_repo.stage_all();
+
+    let output = ghommit_with_token(&mock, &local_repo, &["-m", "Add image"]);
+    assert_success(&output);
+
+    assert_eq!(oid, stored_oid);
+    assert_eq!(mock.request_count("POST", "/objects/batch"), 1);
+    assert_eq!(mock.request_count("PUT", &format!("/lfs/objects/{}", oid)), 0);
+}
+
+#[test]
+fn missing_lfs_objects_are_refused() {
+    let (mock, local_repo) = mock_with_main();
+
+    local_repo.write_file(".gitattributes", LFS_GITATTRIBUTES);
+    write_lfs_file(&local_repo, "image.bin", &[0x00, 0x80, 0xff], false);
+    local_repo.stage_all();
+
+    let output = ghommit_with_token(&mock, &local_repo, &["-m", "Add image"]);
+
+    assert_eq!(output.status.code(), Some(6));
+    assert_eq!(json_output(&output)["error"]["kind"], "unsupported");
+    assert_eq!(mock.branch_tip("main"), Some(local_repo.head()));
+    assert_eq!(mock.request_count("POST", "/git/trees"), 0);
+}
+
+#[test]
+fn lfs_tracked_contents_are_refused() {
+    let (mock, local_repo) = mock_with_main();
+
+    local_repo.write_file(".gitattributes", LFS_GITATTRIBUTES);
+    // - Staged without Git LFS's clean filter, so the contents themselves
+    local_repo.write_file("image.bin", &[0x00, 0x80, 0xff]);
+    local_repo.stage_all();
+
+    let output = ghommit_with_token(&mock, &local_repo, &["-m", "Add image"]);
+
+    assert_eq!(output.status.code(), Some(6));
+    assert!(String::from_utf8_lossy(&output.stderr).contains("git lfs install"));
+    assert_eq!(mock.branch_tip("main"), Some(local_repo.head()));
+    assert_eq!(mock.request_count("POST", "/objects/batch"), 0);
+}
+
+#[test]
+fn replays_unpushed_commits() {
+    let (mock, local_repo) = mock_with_main();
+
+    local_repo.write_file("first", b"first\n");
+    local_repo.stage_all();
+    let first = local_repo.commit("First change");
+
+    local_repo.write_file("second", b"second\n");
+    local_repo.stage_all();
+    let second = local_repo.commit("Second change");
+
+    let output = ghommit_with_token(&mock, &local_repo, &["--replay"]);
+    assert_success(&output);
+
+    let commit_id = mock.branch_tip("main").unwrap();
+
+    mock.with_repo(|repo| {
+        let last = repo.find_commit(commit_id).unwrap();
+        let previous = last.parent(0).unwrap();
+
+        // - The trees match the local commits' trees exactly
+        assert_eq!(last.tree_id(), local_repo.repo.find_commit(second).unwrap().tree_id());
+        assert_eq!(previous.tree_id(), local_repo.repo.find_commit(first).unwrap().tree_id());
+        assert_eq!(last.message(), Some("Second change"));
+        assert_eq!(previous.message(), Some("First change"));
+    });
+}
+
+#[test]
+fn replayed_trees_that_differ_are_a_conflict() {
+    let (mock, local_repo) = mock_with_main();
+    let base = local_repo.head();
+
+    local_repo.write_file("first", b"first\n");
+    local_repo.stage_all();
+    let first = local_repo.commit("First change");
+
+    mock.add_to_created_trees("unexpected", "unexpected\n");
+
+    let output = ghommit_with_token(&mock, &local_repo, &["--replay"]);
+
+    assert_eq!(output.status.code(), Some(10));
+    assert_eq!(json_output(&output)["error"]["kind"], "conflict");
+    assert!(json_output(&output)["error"]["message"].as_str().unwrap().contains(&first.to_string()));
+    assert_eq!(mock.branch_tip("main"), Some(base));
+}
+
+#[test]
+fn sync_local_keeps_changes_outside_the_pathspecs_staged() {
+    let (mock, local_repo) = mock_with_main();
+
+    local_repo.write_file("foo", b"foo\n");
+    local_repo.write_file("bar", b"bar\n");
+    local_repo.stage_all();
+
+    let output = ghommit_with_token(&mock, &local_repo, &["-m", "Add foo", "--sync-local", "foo"]);
+    assert_success(&output);
+
+    let commit_id = mock.branch_tip("main").unwrap();
+
+    assert!(mock.file_content(commit_id, "bar").is_none());
+    assert_eq!(local_repo.head(), commit_id);
+
+    // - `bar` is still staged on top of the new commit
+    let repo = git2::Repository::open(local_repo.path()).unwrap();
+    let statuses = repo.statuses(None).unwrap();
+    let status_of = |path: &str| statuses.iter().find(|entry| entry.path() == Some(path)).map(|entry| entry.status());
+
+    assert_eq!(status_of("bar"), Some(git2::Status::INDEX_NEW));
+    assert_eq!(status_of("foo"), None);
+}
+
+#[test]
+fn sync_local_failures_still_report_the_commit() {
+    let (mock, local_repo) = mock_with_main();
+    let base = local_repo.head();
+
+    // - The local branch has a commit the remote one won't, so it can't be
+    //   moved to the new commit
+    local_repo.write_file("local", b"local\n");
+    local_repo.stage_all();
+    let local_commit = local_repo.commit("Local change");
+
+    local_repo.write_file("foo", b"foo\n");
+    local_repo.stage_all();
+
+    let output = ghommit_with_token(&mock, &local_repo, &["-m", "Add foo", "--base", &base.to_string(), "--sync-local"]);
+    assert_success(&output);
+
+    let json = json_output(&output);
+    let commit_id = mock.branch_tip("main").unwrap();
+
+    assert_eq!(json["commit_sha"], commit_id.to_string());
+    assert_eq!(json["sync_error"]["kind"], "conflict");
+    assert!(String::from_utf8_lossy(&output.stderr).contains("::warning::"));
+    assert_eq!(local_repo.head(), local_commit);
+}
+
+#[test]
+fn pull_request_failures_still_report_the_commit() {
+    let (mock, local_repo) = mock_with_main();
+
+    local_repo.write_file("foo", b"foo\n");
+    local_repo.stage_all();
+
+    let output = ghommit_with_token(&mock, &local_repo, &["-m", "Add foo", "--branch", "feature", "--create-pr", "--pr-reviewer", "octocat"]);
+    assert_success(&output);
+
+    let json = json_output(&output);
+    let commit_id = mock.branch_tip("feature").unwrap();
+
+    // - The pull request was opened before requesting reviewers failed
+    assert_eq!(json["commit_sha"], commit_id.to_string());
+    assert_eq!(json["pr_url"], "https://github.com/gemini-oss/ghommit/pull/1");
+    assert_eq!(json["pr_error"]["kind"], "api");
+    assert_eq!(json["pr_error"]["status"], 422);
+    assert!(String::from_utf8_lossy(&output.stderr).contains("::warning::"));
+}
+
+#[test]
+fn dry_runs_without_credentials() {
+    let (mock, local_repo) = mock_with_main();
+
+    local_repo.write_file("foo", b"foo\n");
+    local_repo.stage_all();
+
+    let output = ghommit(&mock, &local_repo, &[], &["-m", "Add foo", "--dry-run"]);
+    assert_success(&output);
+
+    // - The local repository never fetched, so it can't tell whether the
+    //   branch exists
+    assert!(String::from_utf8_lossy(&output.stdout).contains("Branch \"main\": remote state unknown (not fetched)"));
+    assert!(mock.requests().is_empty());
+}
+
+#[test]
+fn authenticates_as_a_github_app() {
+    let (mock, local_repo) = mock_with_main();
+
+    local_repo.write_file("foo", b"foo\n");
+    local_repo.stage_all();
+
+    let app_id = APP_ID.to_string();
+    let private_key = String::from_utf8(app_private_key_pem().to_vec()).unwrap();
+
+    let environment = [
+        ("GHOMMIT_GITHUB_APP_ID", app_id.as_str()),
+        ("GHOMMIT_GITHUB_APP_PRIVATE_KEY_PEM_DATA", private_key.as_str()),
+    ];
+
+    let output = ghommit(&mock, &local_repo, &environment, &["-m", "Add foo"]);
+    assert_success(&output);
+
+    assert_eq!(mock.request_count("GET", "/installation"), 1);
+    assert_eq!(mock.request_count("POST", "/access_tokens"), 1);
+}
+
+#[test]
+fn rejected_credentials_are_an_auth_error() {
+    let (mock, local_repo) = mock_with_main();
+
+    local_repo.write_file("foo", b"foo\n");
+    local_repo.stage_all();
+
+    let output = ghommit(&mock, &local_repo, &[("GHOMMIT_GITHUB_TOKEN", "ghp_wrong")], &["-m", "Add foo"]);
+
+    assert_eq!(output.status.code(), Some(7));
+    assert_eq!(json_output(&output)["error"]["kind"], "auth");
+    assert_eq!(mock.branch_tip("main"), Some(local_repo.head()));
+}
+
+#[test]
+fn conflicting_remote_changes_are_a_conflict() {
+    let (mock, local_repo) = mock_with_main();
+    let base = local_repo.head();
+
+    let remote_tip = local_repo.commit_elsewhere(base, "foo", b"theirs\n", "Someone else's change");
+    mock.push(&local_repo.repo, remote_tip, "main");
+
+    local_repo.write_file("foo", b"ours\n");
+    local_repo.stage_all();
+
+    let output = ghommit_with_token(&mock, &local_repo, &["-m", "Add foo"]);
+
+    assert_eq!(output.status.code(), Some(10));
+    assert_eq!(json_output(&output)["error"]["kind"], "conflict");
+    assert_eq!(mock.branch_tip("main"), Some(remote_tip));
+}
+
+#[test]
+fn reports_failures_to_github_actions() {
+    let (mock, local_repo) = mock_with_main();
+    let environment_files = tempfile::tempdir().unwrap();
+    let github_output = environment_files.path().join("output");
+    let github_step_summary = environment_files.path().join("summary");
+
+    local_repo.write_file("foo", b"foo\n");
+    local_repo.stage_all();
+
+    let environment = [
+        ("GHOMMIT_GITHUB_TOKEN", "ghp_wrong"),
+        ("GITHUB_ACTIONS", "true"),
+        ("GITHUB_OUTPUT", github_output.to_str().unwrap()),
+        ("GITHUB_STEP_SUMMARY", github_step_summary.to_str().unwrap()),
+    ];
+
+    let output = ghommit(&mock, &local_repo, &environment, &["-m", "Add foo"]);
+
+    assert_eq!(output.status.code(), Some(7));
+
+    let step_outputs = std::fs::read_to_string(&github_output).unwrap();
+
+    assert!(step_outputs.contains("error_kind=auth\n"));
+    assert!(!step_outputs.contains("commit_sha="));
+    assert!(std::fs::read_to_string(&github_step_summary).unwrap().contains("- Failed: auth\n"));
+}
@@ -1,96 +1,118 @@
-#![allow(dead_code)]
+#![allow(clippy::redundant_field_names)]
+
+mod common;
 
-use std::env;
 use std::sync::Arc;
 
+use ghommit::backend::{BackendKind, RefAction, new_backend};
 use ghommit::config::{CommandLineArguments, Config, EnvironmentVariableConfig, GitConfig};
-use ghommit::github::{GitHubClient, GitHubRepo};
-use ghommit::github::rest_api::{create_a_blob, create_a_tree};
-
-struct EnvironmentVariableTestConfig {
-    base_tree_id: String,
-    commit_message: String,
-    repo_path: String,
-    github_repo_owner: String,
-    github_repo_name: String,
-}
-
-impl EnvironmentVariableTestConfig {
-    fn environment_variable(name: &str) -> String {
-        match env::var(name) {
-            Ok(result) => result,
-            Err(_) => panic!("Environment variable not set: {}", name),
-        }
-    }
-
-    pub fn gather() -> EnvironmentVariableTestConfig {
-        EnvironmentVariableTestConfig {
-            base_tree_id: Self::environment_variable("GHOMMIT_TEST_BASE_TREE_ID"),
-            commit_message: Self::environment_variable("GHOMMIT_TEST_COMMIT_MESSAGE"),
-            repo_path: Self::environment_variable("GHOMMIT_TEST_REPO_PATH"),
-            github_repo_owner: Self::environment_variable("GHOMMIT_TEST_GITHUB_REPO_OWNER"),
-            github_repo_name: Self::environment_variable("GHOMMIT_TEST_GITHUB_REPO_NAME"),
-        }
-    }
+use ghommit::error::Error;
+use ghommit::git_status::{StatusOptions, git_status_with};
+use ghommit::github::{GitHubClient, HttpClientOptions};
+use ghommit::github::rest_api::{create_a_blob, create_a_commit, create_a_reference, create_a_tree, get_a_reference, update_a_reference};
+use ghommit::identity::CommitIdentityOptions;
+use ghommit::retry::RetryPolicy;
+
+use common::local_repo::LocalRepo;
+use common::mock_github::{INSTALLATION_ID, MockGitHub, mock_with_main};
+
+/// Authenticates as the GitHub App, looking the installation up, so that
+/// every request goes through the installation token endpoints too
+fn default_github_client(mock: &MockGitHub) -> GitHubClient {
+    mock.github_client(mock.app_authentication(None))
 }
 
-fn default_github_client() -> GitHubClient {
-    let env_config = EnvironmentVariableConfig::gather().unwrap();
-    let test_config = EnvironmentVariableTestConfig::gather();
+fn default_config(mock: &MockGitHub, local_repo: &LocalRepo) -> Config {
+    let cli_args = CommandLineArguments {
+        commit_backend: BackendKind::Rest,
+        commit_identity: CommitIdentityOptions::default(),
+        git_base_ref: None,
+        git_branch_name: None,
+        commit_message: "ghommit test message".to_string(),
+        git_should_force_push: false,
+        git_should_replay_commits: false,
+        git_should_sync_local: false,
+        git_status_options: StatusOptions::default(),
+        github_http_client_options: HttpClientOptions::default(),
+        github_max_concurrent_uploads: 8,
+        github_retry_policy: RetryPolicy::default(),
+        pull_request: None,
+        should_clean_up_commit_message: false,
+        should_dry_run: false,
+        should_edit_commit_message: false,
+    };
+    let maybe_repo = git2::Repository::open(local_repo.path());
+    let env_config = EnvironmentVariableConfig {
+        github_authentication: Some(mock.app_authentication(Some(INSTALLATION_ID))),
+        github_endpoints: mock.endpoints(),
+        github_host: "github.com".to_string(),
+    };
+    let git_config = GitConfig::gather(maybe_repo, &env_config.github_host, None, None).unwrap();
+
+    Config::from(cli_args, git_config, env_config)
+}
 
-    let github_repo = GitHubRepo {
-        owner: test_config.github_repo_owner,
-        name: test_config.github_repo_name,
+fn create_a_tree_with_file(github_client: &GitHubClient, base_tree: &str, path: &str, content: &str) -> create_a_tree::ResponseBody {
+    let payload = create_a_tree::RequestBody {
+        base_tree: base_tree.to_string(),
+        tree: vec![
+            create_a_tree::TreeNode {
+                path: path.to_owned(),
+                file_mode: create_a_tree::FileMode::Blob,
+                node_type: create_a_tree::NodeType::Blob,
+                sha_or_content: create_a_tree::ShaOrContent::Content(content.to_owned()),
+            },
+        ],
     };
 
-    GitHubClient::new(
-        env_config.github_app_id,
-        env_config.github_app_installation_id,
-        env_config.github_app_private_key,
-        github_repo,
-    )
+    github_client.create_a_tree(&payload).unwrap()
 }
 
-fn default_config() -> Config {
-    let test_config = EnvironmentVariableTestConfig::gather();
+fn create_a_commit_with_file(github_client: &GitHubClient, parent_sha: &str, path: &str, content: &str) -> create_a_commit::ResponseBody {
+    let tree = create_a_tree_with_file(github_client, parent_sha, path, content);
 
-    let cli_args = CommandLineArguments {
-        commit_message: "ghommit test message".to_string(),
-        git_should_force_push: false,
+    let payload = create_a_commit::RequestBody {
+        message: format!("Write {}", path),
+        parents: vec![parent_sha.to_string()],
+        tree: tree.sha,
+        author: None,
+        committer: None,
     };
-    let maybe_repo = git2::Repository::open(&test_config.repo_path);
-    let git_config = GitConfig::gather(maybe_repo).unwrap();
-    let env_config = EnvironmentVariableConfig::gather().unwrap();
 
-    Config::from(cli_args, git_config, env_config)
+    github_client.create_a_commit(&payload).unwrap()
 }
 
 #[test]
-#[ignore]
 fn access_token_caching() {
-    let github_client = default_github_client();
+    let mock = MockGitHub::start();
+    let github_client = default_github_client(&mock);
 
     let expected = github_client.get_access_token(false).unwrap();
     let actual = github_client.get_access_token(false).unwrap();
 
     assert!(Arc::ptr_eq(&actual, &expected));
+    assert_eq!(mock.request_count("POST", "/access_tokens"), 1);
+    assert_eq!(mock.request_count("GET", "/installation"), 1);
 }
 
 #[test]
-#[ignore]
 fn access_token_forcing() {
-    let github_client = default_github_client();
+    let mock = MockGitHub::start();
+    let github_client = default_github_client(&mock);
 
     let access_token_1 = github_client.get_access_token(false).unwrap();
     let access_token_2 = github_client.get_access_token(true).unwrap();
 
     assert!(!Arc::ptr_eq(&access_token_1, &access_token_2));
+    assert_ne!(access_token_1, access_token_2);
+    // - The installation is only looked up once
+    assert_eq!(mock.request_count("GET", "/installation"), 1);
 }
 
 #[test]
-#[ignore]
 fn create_a_blob_text() {
-    let github_client = default_github_client();
+    let mock = MockGitHub::start();
+    let github_client = default_github_client(&mock);
 
     let payload = create_a_blob::RequestBody {
         content: "hello",
@@ -104,9 +126,9 @@ fn create_a_blob_text() {
 }
 
 #[test]
-#[ignore]
 fn create_a_blob_binary() {
-    let github_client = default_github_client();
+    let mock = MockGitHub::start();
+    let github_client = default_github_client(&mock);
 
     // printf '\x80' | base64
     let first_invalid_utf8_byte_base64 = "gA==";
@@ -124,47 +146,189 @@ fn create_a_blob_binary() {
 }
 
 #[test]
-#[ignore]
 fn create_a_commit() {
-    assert!(false, "Not implemented")
+    let (mock, local_repo) = mock_with_main();
+    let github_client = default_github_client(&mock);
+
+    let parent_sha = local_repo.head().to_string();
+    let commit = create_a_commit_with_file(&github_client, &parent_sha, "foo", "foo\n");
+
+    let commit_object = github_client.get_a_commit_object(&commit.sha).unwrap();
+
+    assert_eq!(commit_object.tree.sha, commit.tree.sha);
+    assert_eq!(commit_object.parents.len(), 1);
+    assert_eq!(commit_object.parents[0].sha, parent_sha);
+    assert_eq!(commit_object.message, "Write foo");
+    assert!(!commit.verification.verified);
+
+    let commit_id = git2::Oid::from_str(&commit.sha).unwrap();
+    assert_eq!(mock.file_content(commit_id, "foo").unwrap(), b"foo\n");
+    assert_eq!(mock.file_content(commit_id, "README.md").unwrap(), b"# ghommit\n");
 }
 
 #[test]
-#[ignore]
 fn create_a_reference() {
-    assert!(false, "Not implemented")
+    let (mock, local_repo) = mock_with_main();
+    let github_client = default_github_client(&mock);
+
+    let payload = create_a_reference::RequestBody {
+        reference: "refs/heads/feature".to_string(),
+        sha: local_repo.head().to_string(),
+    };
+
+    match github_client.create_a_reference(&payload).unwrap() {
+        create_a_reference::ResponseBody::Ok(reference) => {
+            assert_eq!(reference.reference, "refs/heads/feature");
+            assert_eq!(reference.object.sha, local_repo.head().to_string());
+        },
+        create_a_reference::ResponseBody::UnprocessableEntity(e) => panic!("Reference was not created: {}", e.message),
+    }
+
+    assert_eq!(mock.branch_tip("feature"), Some(local_repo.head()));
+
+    // - Creating it again fails since it already exists
+    assert!(matches!(github_client.create_a_reference(&payload).unwrap(), create_a_reference::ResponseBody::UnprocessableEntity(_)));
 }
 
 #[test]
-#[ignore]
 fn create_a_tree() {
-    let test_config = EnvironmentVariableTestConfig::gather();
-    let github_client = default_github_client();
+    let (mock, local_repo) = mock_with_main();
+    let github_client = default_github_client(&mock);
 
-    let payload = create_a_tree::RequestBody {
-        base_tree: test_config.base_tree_id,
-        tree: vec![
-            create_a_tree::TreeNode {
-                path: "foo".to_owned(),
-                file_mode: create_a_tree::FileMode::Blob,
-                node_type: create_a_tree::NodeType::Blob,
-                sha_or_content: create_a_tree::ShaOrContent::Content("foo\n".to_owned()),
-            },
-        ],
-    };
+    let base_tree_id = local_repo.repo.find_commit(local_repo.head()).unwrap().tree_id();
+
+    let tree = create_a_tree_with_file(&github_client, &base_tree_id.to_string(), "foo", "foo\n");
+
+    // - The tree matches what git would have built locally
+    let expected_tree_id = mock.with_repo(|repo| {
+        let base_tree = repo.find_tree(base_tree_id).unwrap();
+        let blob_id = repo.blob(b"foo\n").unwrap();
+
+        git2::build::TreeUpdateBuilder::new()
+            .upsert("foo", blob_id, git2::FileMode::Blob)
+            .create_updated(repo, &base_tree)
+            .unwrap()
+    });
 
-    // - The request succeeding is good enough for now
-    github_client.create_a_tree(&payload).unwrap();
+    assert_eq!(tree.sha, expected_tree_id.to_string());
 }
 
 #[test]
-#[ignore]
 fn get_a_reference() {
-    assert!(false, "Not implemented")
+    let (mock, local_repo) = mock_with_main();
+    let github_client = default_github_client(&mock);
+
+    match github_client.get_a_reference("heads/main").unwrap() {
+        get_a_reference::ResponseBody::Ok(reference) => {
+            assert_eq!(reference.reference, "refs/heads/main");
+            assert_eq!(reference.object.sha, local_repo.head().to_string());
+        },
+        get_a_reference::ResponseBody::NotFound(_) => panic!("Expected refs/heads/main to exist"),
+    }
+
+    assert!(matches!(github_client.get_a_reference("heads/missing").unwrap(), get_a_reference::ResponseBody::NotFound(_)));
 }
 
 #[test]
-#[ignore]
 fn update_a_reference() {
-    assert!(false, "Not implemented")
+    let (mock, local_repo) = mock_with_main();
+    let github_client = default_github_client(&mock);
+
+    let base_sha = local_repo.head().to_string();
+    let first = create_a_commit_with_file(&github_client, &base_sha, "foo", "foo\n");
+    let sibling = create_a_commit_with_file(&github_client, &base_sha, "bar", "bar\n");
+
+    let update = |sha: &str, force: bool| {
+        let payload = update_a_reference::RequestBody {
+            sha: sha.to_string(),
+            force: force,
+        };
+
+        github_client.update_a_reference("heads/main", &payload).unwrap()
+    };
+
+    // - Fast-forward
+    assert!(matches!(update(&first.sha, false), update_a_reference::ResponseBody::Ok(_)));
+    assert_eq!(mock.branch_tip("main").unwrap().to_string(), first.sha);
+
+    // - Not a fast-forward, so it's rejected unless forced
+    assert!(matches!(update(&sibling.sha, false), update_a_reference::ResponseBody::UnprocessableEntity(_)));
+    assert_eq!(mock.branch_tip("main").unwrap().to_string(), first.sha);
+
+    assert!(matches!(update(&sibling.sha, true), update_a_reference::ResponseBody::Ok(_)));
+    assert_eq!(mock.branch_tip("main").unwrap().to_string(), sibling.sha);
+}
+
+#[test]
+fn commit_staged_changes_moves_the_branch() {
+    let (mock, local_repo) = mock_with_main();
+
+    local_repo.write_file("docs/foo.md", b"foo\n");
+    local_repo.write_file("image.bin", &[0x80, 0x81]);
+    local_repo.write_file("README.md", b"# ghommit\n\nUpdated\n");
+    local_repo.stage_all();
+
+    let config = default_config(&mock, &local_repo);
+    let github_client = mock.github_client(config.github_authentication.clone().unwrap());
+    let status = git_status_with(&config.git_repo, local_repo.head(), &config.git_status_options).unwrap();
+
+    let commit = new_backend(BackendKind::Rest, &github_client).commit_staged_changes(&config, &status).unwrap();
+
+    assert_eq!(commit.ref_action, RefAction::Updated);
+    assert_eq!(mock.branch_tip("main").unwrap().to_string(), commit.sha);
+
+    // - The tree is the one the staged changes would have been committed as
+    let expected_tree_id = local_repo.repo.index().unwrap().write_tree().unwrap();
+    assert_eq!(commit.tree_sha, expected_tree_id.to_string());
+
+    // - Only the binary file is uploaded as a blob
+    assert_eq!(mock.request_count("POST", "/git/blobs"), 1);
+}
+
+#[test]
+fn commit_staged_changes_rebases_onto_unrelated_remote_changes() {
+    let (mock, local_repo) = mock_with_main();
+    let base = local_repo.head();
+
+    let remote_tip = local_repo.commit_elsewhere(base, "other.md", b"other\n", "Someone else's change");
+    mock.push(&local_repo.repo, remote_tip, "main");
+
+    local_repo.write_file("foo.md", b"foo\n");
+    local_repo.stage_all();
+
+    let config = default_config(&mock, &local_repo);
+    let github_client = mock.github_client(config.github_authentication.clone().unwrap());
+    let status = git_status_with(&config.git_repo, local_repo.head(), &config.git_status_options).unwrap();
+
+    let commit = new_backend(BackendKind::Rest, &github_client).commit_staged_changes(&config, &status).unwrap();
+    let commit_id = git2::Oid::from_str(&commit.sha).unwrap();
+
+    let parent_id = mock.with_repo(|repo| repo.find_commit(commit_id).unwrap().parent_id(0).unwrap());
+    assert_eq!(parent_id, remote_tip);
+    assert_eq!(mock.file_content(commit_id, "other.md").unwrap(), b"other\n");
+    assert_eq!(mock.file_content(commit_id, "foo.md").unwrap(), b"foo\n");
+}
+
+#[test]
+fn commit_staged_changes_fails_on_a_rejected_reference_update() {
+    let (mock, local_repo) = mock_with_main();
+    let base = local_repo.head();
+
+    local_repo.write_file("foo.md", b"foo\n");
+    local_repo.stage_all();
+    mock.fail_reference_updates("Object does not exist");
+
+    let config = default_config(&mock, &local_repo);
+    let github_client = mock.github_client(config.github_authentication.clone().unwrap());
+    let status = git_status_with(&config.git_repo, local_repo.head(), &config.git_status_options).unwrap();
+
+    let error = new_backend(BackendKind::Rest, &github_client).commit_staged_changes(&config, &status).unwrap_err();
+
+    assert!(matches!(&error, Error::Api { status: 422, body, .. } if body == "Object does not exist"), "{:?}", error);
+    assert_eq!(mock.branch_tip("main").unwrap(), base);
+
+    // - The refusal isn't mistaken for the branch moving, so the commit isn't
+    //   rebuilt and retried
+    assert_eq!(mock.request_count("POST", "/git/commits"), 1);
+    assert_eq!(mock.request_count("PATCH", "/git/refs/heads/main"), 1);
 }